@@ -0,0 +1,97 @@
+//! Benchmarks reading the storage of a large account, comparing the collected `ContractStore`
+//! with consuming the borrowing iterators directly.
+//!
+//! The number of heap allocations per operation is printed before the timings.
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    sync::atomic::{AtomicUsize, Ordering},
+};
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use tycho_core::{
+    models::{
+        contract::{Account, AccountDelta},
+        Chain, ChangeType,
+    },
+    Bytes,
+};
+
+struct CountingAllocator;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAllocator = CountingAllocator;
+
+const SLOTS: u64 = 1000;
+
+fn word(value: u64) -> Bytes {
+    Bytes::from(value).lpad(32, 0)
+}
+
+fn delta() -> AccountDelta {
+    AccountDelta::new(
+        Chain::Ethereum,
+        Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F"),
+        (0..SLOTS)
+            .map(|i| (word(i), Some(word(i + 1))))
+            .collect(),
+        Some(word(100)),
+        None,
+        ChangeType::Creation,
+    )
+}
+
+fn account() -> Account {
+    delta().into_account_without_tx()
+}
+
+/// Slot data in the shape the storage writer turns it into.
+fn rows(store: impl Iterator<Item = (Bytes, Option<Bytes>)>) -> Vec<(Bytes, Option<Bytes>)> {
+    store.collect()
+}
+
+fn count_allocations<T>(name: &str, f: impl FnOnce() -> T) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    black_box(f());
+    println!("{name}: {} allocations", ALLOCATIONS.load(Ordering::Relaxed) - before);
+}
+
+fn bench_account_store(c: &mut Criterion) {
+    let account = account();
+    let delta = delta();
+
+    count_allocations("store", || rows(account.store().into_iter()));
+    count_allocations("store_iter", || rows(account.store_iter()));
+    count_allocations("dirty_slots", || rows(delta.dirty_slots().into_iter()));
+    count_allocations("dirty_slots_iter", || rows(delta.dirty_slots_iter()));
+    count_allocations("apply_delta", || account.clone().apply_delta(&delta));
+
+    let mut group = c.benchmark_group("account_store");
+    group.bench_function("store", |b| b.iter(|| rows(account.store().into_iter())));
+    group.bench_function("store_iter", |b| b.iter(|| rows(account.store_iter())));
+    group.bench_function("dirty_slots", |b| b.iter(|| rows(delta.dirty_slots().into_iter())));
+    group.bench_function("dirty_slots_iter", |b| b.iter(|| rows(delta.dirty_slots_iter())));
+    group.bench_function("apply_delta", |b| {
+        b.iter_batched(
+            || account.clone(),
+            |mut account| account.apply_delta(&delta),
+            criterion::BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_account_store);
+criterion_main!(benches);
@@ -1556,6 +1556,7 @@ mod test {
                     change: models::ChangeType::Creation,
                     creation_tx: Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000c351").unwrap(),
                     created_at: NaiveDateTime::from_timestamp_opt(base_ts + 5000, 0).unwrap(),
+                    linked_component: None,
                 }),
             ]),
             deleted_protocol_components: HashMap::from([
@@ -1573,6 +1574,7 @@ mod test {
                     change: models::ChangeType::Deletion,
                     creation_tx: Bytes::from_str("0x0000000000000000000000000000000000000000000000000000000000009c41").unwrap(),
                     created_at: NaiveDateTime::from_timestamp_opt(base_ts + 4000, 0).unwrap(),
+                    linked_component: None,
                 }),
             ]),
             component_balances: HashMap::from([
@@ -12,6 +12,7 @@ extern crate pretty_assertions;
 
 pub use hex_bytes::Bytes;
 
+use chrono::{DateTime, NaiveDateTime, Utc};
 use tiny_keccak::{Hasher, Keccak};
 
 /// Compute the Keccak-256 hash of input bytes.
@@ -26,3 +27,32 @@ pub fn keccak256<T: AsRef<[u8]>>(bytes: T) -> [u8; 32] {
 
     output
 }
+
+/// Interprets a naive timestamp as UTC.
+///
+/// All naive timestamps in this crate (block timestamps, versions, `created_at` fields, ...)
+/// are implicitly UTC. Use this helper whenever a timezone aware value is required.
+pub fn assume_utc(ndt: NaiveDateTime) -> DateTime<Utc> {
+    DateTime::from_naive_utc_and_offset(ndt, Utc)
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn test_assume_utc_preserves_instant() {
+        let ndt: NaiveDateTime = "2020-01-01T01:00:00".parse().unwrap();
+
+        let res = assume_utc(ndt);
+
+        assert_eq!(
+            res,
+            Utc.with_ymd_and_hms(2020, 1, 1, 1, 0, 0)
+                .unwrap()
+        );
+        assert_eq!(res.timestamp(), ndt.and_utc().timestamp());
+    }
+}
@@ -19,7 +19,7 @@ use super::{
     contract::{AccountBalance, AccountChangesWithTx},
     protocol::ProtocolChangesWithTx,
     token::CurrencyToken,
-    Address, ExtractorIdentity, NormalisedMessage,
+    Address, BlockHash, ExtractorIdentity, NormalisedMessage,
 };
 
 #[derive(Clone, Default, PartialEq, Serialize, Deserialize, Debug)]
@@ -56,6 +56,26 @@ impl Transaction {
     pub fn new(hash: Bytes, block_hash: Bytes, from: Bytes, to: Option<Bytes>, index: u64) -> Self {
         Transaction { hash, block_hash, from, to, index }
     }
+
+    /// Whether this transaction deploys a new contract.
+    ///
+    /// Contract creations have no recipient (`to` is `None`). This is distinct from a
+    /// transaction sent to the zero address, which carries `Some(0x00..00)`.
+    pub fn is_contract_creation(&self) -> bool {
+        self.to.is_none()
+    }
+}
+
+/// Describes what was undone when a chain was reverted to an earlier block.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReorgEvent {
+    pub chain: Chain,
+    /// The block the chain was reverted to.
+    pub common_ancestor: BlockHash,
+    /// Blocks that are no longer canonical, ordered by block number.
+    pub reverted_blocks: Vec<BlockHash>,
+    /// Contracts whose storage or balance was reverted, sorted by address.
+    pub reverted_contracts: Vec<Address>,
 }
 
 pub struct BlockTransactionDeltas<T> {
@@ -228,6 +248,33 @@ impl TxWithChanges {
             ));
         }
 
+        // Validate all of `other` first, so a failing merge leaves `self` untouched.
+        for (address, update) in other.account_deltas.iter() {
+            if *address != update.address {
+                return Err(format!(
+                    "Can't merge AccountUpdates keyed by a differing address: {:#x} != {:#x}",
+                    address, update.address
+                ));
+            }
+            if let Some(existing) = self.account_deltas.get(address) {
+                if existing.chain != update.chain {
+                    return Err(format!(
+                        "Can't merge AccountUpdates from differing chains; Expected {}, got {}",
+                        existing.chain, update.chain
+                    ));
+                }
+            }
+        }
+        if let Some((key, value)) = other
+            .state_updates
+            .iter()
+            .find(|(key, value)| **key != value.component_id)
+        {
+            return Err(format!(
+                "Can't merge ProtocolStates keyed by a differing component id: {} != {}",
+                key, value.component_id
+            ));
+        }
         self.tx = other.tx;
 
         // Merge new protocol components
@@ -364,3 +411,74 @@ pub mod fixtures {
         )
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const HASH_256_0: &str = "0x0000000000000000000000000000000000000000000000000000000000000000";
+    const HASH_256_1: &str = "0x0000000000000000000000000000000000000000000000000000000000000001";
+
+    fn account_delta(address: &str) -> AccountDelta {
+        AccountDelta::new(
+            Chain::Ethereum,
+            Bytes::from(address),
+            HashMap::new(),
+            Some(Bytes::from(1u64).lpad(32, 0)),
+            None,
+            crate::models::ChangeType::Update,
+        )
+    }
+
+    #[test]
+    fn test_merge_tx_with_changes_rejects_mismatched_state_key() {
+        let address = "0x6b175474e89094c44da98b954eedeac495271d0f";
+        let mut base = TxWithChanges {
+            tx: fixtures::create_transaction(HASH_256_0, HASH_256_0, 10),
+            ..Default::default()
+        };
+        let expected = base.clone();
+        let other = TxWithChanges {
+            account_deltas: [(Bytes::from(address), account_delta(address))].into(),
+            state_updates: [(
+                "State1".to_owned(),
+                ProtocolComponentStateDelta::new("State2", HashMap::new(), Default::default()),
+            )]
+            .into(),
+            tx: fixtures::create_transaction(HASH_256_1, HASH_256_0, 11),
+            ..Default::default()
+        };
+
+        let res = base.merge(other);
+
+        assert_eq!(
+            res,
+            Err("Can't merge ProtocolStates keyed by a differing component id: State1 != State2"
+                .to_owned())
+        );
+        assert_eq!(base, expected);
+    }
+
+    #[test]
+    fn test_merge_tx_with_changes_rejects_mismatched_account_key() {
+        let mut base = TxWithChanges {
+            tx: fixtures::create_transaction(HASH_256_0, HASH_256_0, 10),
+            ..Default::default()
+        };
+        let expected = base.clone();
+        let other = TxWithChanges {
+            account_deltas: [(
+                Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f"),
+                account_delta("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            )]
+            .into(),
+            tx: fixtures::create_transaction(HASH_256_1, HASH_256_0, 11),
+            ..Default::default()
+        };
+
+        let res = base.merge(other);
+
+        assert!(res.is_err());
+        assert_eq!(base, expected);
+    }
+}
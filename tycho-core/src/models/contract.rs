@@ -5,16 +5,32 @@ use tracing::warn;
 use crate::{
     keccak256,
     models::{Chain, ChangeType, ContractId, DeltaError},
+    serde_primitives::{change_type_lowercase, hex_hashmap_slots},
+    storage::StorageError,
     Bytes,
 };
 
 use super::{
     blockchain::Transaction,
     protocol::{ComponentBalance, ProtocolComponent},
-    Address, Balance, Code, CodeHash, ComponentId, StoreKey, StoreVal, TxHash,
+    Address, Balance, Code, CodeHash, ComponentId, ContractStore, StoreKey, StoreVal, TxHash,
 };
 
-#[derive(Clone, Debug, PartialEq)]
+/// Keccak256 hash of empty code, the code hash of every account without code.
+pub const EMPTY_CODE_HASH: [u8; 32] = [
+    0xc5, 0xd2, 0x46, 0x01, 0x86, 0xf7, 0x23, 0x3c, 0x92, 0x7e, 0x7d, 0xb2, 0xdc, 0xc7, 0x03, 0xc0,
+    0xe5, 0x00, 0xb6, 0x53, 0xca, 0x82, 0x27, 0x3b, 0x7b, 0xfa, 0xd8, 0x04, 0x5d, 0x85, 0xa4, 0x70,
+];
+
+/// The value an [`Account`] stores for a slot deleted by a delta: a zeroed 32 byte word.
+///
+/// Every conversion from an [`AccountDelta`] into an [`Account`] uses this value, so the same
+/// delta always results in the same store.
+pub fn deleted_slot_value() -> StoreVal {
+    Bytes::zero(32)
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Account {
     pub chain: Chain,
     pub address: Address,
@@ -56,11 +72,55 @@ impl Account {
         }
     }
 
+    /// Sets `code_hash` to the keccak256 hash of the account's current `code`.
+    ///
+    /// Accounts without code end up with [`EMPTY_CODE_HASH`]. Converting the account into an
+    /// [`AccountDelta`] keeps the code, so accounts built back from the delta carry the same
+    /// hash.
+    pub fn with_computed_code_hash(&mut self) {
+        self.code_hash = Bytes::from(keccak256(&self.code));
+    }
+
+    /// Iterates over the account's storage as it is written to the database.
+    ///
+    /// Every slot is yielded with a `Some` value, only the `Bytes` handles are cloned, which
+    /// does not copy the underlying data.
+    pub fn store_iter(&self) -> impl Iterator<Item = (StoreKey, Option<StoreVal>)> + '_ {
+        self.slots
+            .iter()
+            .map(|(k, v)| (k.clone(), Some(v.clone())))
+    }
+
+    /// Collects [`Account::store_iter`] into a `ContractStore`.
+    pub fn store(&self) -> ContractStore {
+        self.store_iter().collect()
+    }
+
     pub fn set_balance(&mut self, new_balance: &Balance, modified_at: &Balance) {
         self.native_balance = new_balance.clone();
         self.balance_modify_tx = modified_at.clone();
     }
 
+    /// Replaces the account's storage with the slots yielded by `iter`.
+    ///
+    /// Allows callers that stream slot rows (e.g. straight from the database) to populate the
+    /// account without materialising an intermediate `ContractStore` first.
+    ///
+    /// A `None` value marks a deleted slot, it is retained in the store as a zeroed word, see
+    /// [`deleted_slot_value`].
+    pub fn set_store_from_iter<'a>(
+        &mut self,
+        iter: impl Iterator<Item = (&'a StoreKey, Option<&'a StoreVal>)>,
+    ) {
+        self.slots.clear();
+        self.slots.extend(iter.map(|(k, v)| {
+            let value = v
+                .cloned()
+                .unwrap_or_else(deleted_slot_value);
+            (k.clone(), value)
+        }));
+    }
+
     pub fn apply_delta(&mut self, delta: &AccountDelta) -> Result<(), DeltaError> {
         let self_id = (self.chain, &self.address);
         let other_id = (delta.chain, &delta.address);
@@ -75,14 +135,40 @@ impl Account {
         }
         self.slots.extend(
             delta
-                .slots
-                .clone()
-                .into_iter()
-                .map(|(k, v)| (k, v.unwrap_or_default())),
+                .dirty_slots_iter()
+                .map(|(k, v)| (k, v.unwrap_or_else(deleted_slot_value))),
         );
         // TODO: Update modify_tx, code_modify_tx and code_hash.
         Ok(())
     }
+
+    /// Reconstructs an account by applying `updates` in order on top of `base`.
+    ///
+    /// Slots, balance and code of each update are applied via [`Account::apply_delta`]. A
+    /// [`ChangeType::Deletion`] resets the account to an empty state: storage is cleared and
+    /// balance and code are zeroed. Updates following a deletion are applied to that empty
+    /// account.
+    ///
+    /// Returns a `StorageError::Unexpected` if any update targets a different chain or address
+    /// than the base account.
+    pub fn from_base_and_updates(
+        base: Account,
+        updates: &[AccountDelta],
+    ) -> Result<Account, StorageError> {
+        let mut account = base;
+        for update in updates {
+            account
+                .apply_delta(update)
+                .map_err(|err| StorageError::Unexpected(err.to_string()))?;
+            if update.change == ChangeType::Deletion {
+                account.slots.clear();
+                account.native_balance = Balance::default();
+                account.code = Code::default();
+                account.code_hash = Bytes::from(EMPTY_CODE_HASH);
+            }
+        }
+        Ok(account)
+    }
 }
 
 #[derive(Clone, Debug, PartialEq, Serialize, Deserialize, Default)]
@@ -120,6 +206,20 @@ impl AccountDelta {
         ContractId::new(self.chain, self.address.clone())
     }
 
+    /// Iterates over the slots changed by this delta, `None` marking a deleted slot.
+    ///
+    /// Only the `Bytes` handles are cloned, which does not copy the underlying data.
+    pub fn dirty_slots_iter(&self) -> impl Iterator<Item = (StoreKey, Option<StoreVal>)> + '_ {
+        self.slots
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+    }
+
+    /// Collects [`AccountDelta::dirty_slots_iter`] into a `ContractStore`.
+    pub fn dirty_slots(&self) -> ContractStore {
+        self.dirty_slots_iter().collect()
+    }
+
     pub fn into_account(self, tx: &Transaction) -> Account {
         let empty_hash = keccak256(Vec::new());
         Account::new(
@@ -128,7 +228,7 @@ impl AccountDelta {
             format!("{:#020x}", self.address),
             self.slots
                 .into_iter()
-                .map(|(k, v)| (k, v.map(Into::into).unwrap_or_default()))
+                .map(|(k, v)| (k, v.unwrap_or_else(deleted_slot_value)))
                 .collect(),
             self.balance.unwrap_or_default(),
             self.code.clone().unwrap_or_default(),
@@ -153,7 +253,7 @@ impl AccountDelta {
             format!("{:#020x}", self.address),
             self.slots
                 .into_iter()
-                .map(|(k, v)| (k, v.map(Into::into).unwrap_or_default()))
+                .map(|(k, v)| (k, v.unwrap_or_else(deleted_slot_value)))
                 .collect(),
             self.balance.unwrap_or_default(),
             self.code.clone().unwrap_or_default(),
@@ -179,10 +279,8 @@ impl AccountDelta {
             self.chain,
             self.address.clone(),
             format!("{:#020x}", self.address),
-            self.slots
-                .clone()
-                .into_iter()
-                .map(|(k, v)| (k, v.unwrap_or_default()))
+            self.dirty_slots_iter()
+                .map(|(k, v)| (k, v.unwrap_or_else(deleted_slot_value)))
                 .collect(),
             self.balance.clone().unwrap_or_default(),
             self.code.clone().unwrap_or_default(),
@@ -210,10 +308,13 @@ impl AccountDelta {
     /// could be used as needed. However, you should give preference to
     /// utilizing [AccountChangesWithTx] for merging, when possible.
     ///
+    /// The change types are composed as well, see [`ChangeType::merge`]: a creation followed
+    /// by an update stays a creation, while anything followed by a deletion becomes a deletion.
+    ///
     /// # Errors
     ///
-    /// It returns an `CoreError::MergeError` error if `self.address` and
-    /// `other.address` are not identical.
+    /// It returns an error if `self.address` and `other.address` or `self.chain` and
+    /// `other.chain` are not identical.
     ///
     /// # Arguments
     ///
@@ -226,6 +327,12 @@ impl AccountDelta {
                 self.address, other.address
             ));
         }
+        if self.chain != other.chain {
+            return Err(format!(
+                "Can't merge AccountUpdates from differing chains; Expected {}, got {}",
+                self.chain, other.chain
+            ));
+        }
 
         self.slots.extend(other.slots);
 
@@ -233,6 +340,7 @@ impl AccountDelta {
             self.balance = Some(balance)
         }
         self.code = other.code.or(self.code.take());
+        self.change = self.change.merge(&other.change);
 
         Ok(())
     }
@@ -244,6 +352,313 @@ impl AccountDelta {
     pub fn is_creation(&self) -> bool {
         self.change == ChangeType::Creation
     }
+
+    /// Encodes the delta into a compact binary representation.
+    ///
+    /// All integers are big endian. The layout is:
+    ///
+    /// | Field        | Size        | Description                                           |
+    /// |--------------|-------------|-------------------------------------------------------|
+    /// | chain        | 1           | Chain tag, see `chain_tag`                            |
+    /// | change       | 1           | 0 = Update, 1 = Deletion, 2 = Creation                |
+    /// | address      | 20          | Account address                                       |
+    /// | slot count   | 4           | Number of slot entries that follow                    |
+    /// | slots        | 65 per slot | 32 byte key, 1 byte value flag, 32 byte value         |
+    /// | balance      | 1 + (4 + n) | Presence flag, followed by length and bytes if set    |
+    /// | code         | 1 + (4 + n) | Presence flag, followed by length and bytes if set    |
+    ///
+    /// Slot keys and values are left padded to 32 bytes, a deleted slot is encoded with its
+    /// value flag set to 0 and a zeroed value.
+    ///
+    /// # Errors
+    ///
+    /// Returns a `StorageError::EncodeError` if the address is not 20 bytes long or a slot key or
+    /// value exceeds 32 bytes.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, StorageError> {
+        if self.address.len() != 20 {
+            return Err(StorageError::EncodeError(format!(
+                "AccountDelta address must be 20 bytes: got {:#x}",
+                self.address
+            )));
+        }
+        let mut buf = Vec::with_capacity(26 + self.slots.len() * 65);
+        buf.push(chain_tag(&self.chain));
+        buf.push(match self.change {
+            ChangeType::Update => 0,
+            ChangeType::Deletion => 1,
+            ChangeType::Creation => 2,
+        });
+        buf.extend_from_slice(&self.address);
+        buf.extend_from_slice(&(self.slots.len() as u32).to_be_bytes());
+        for (k, v) in self.slots.iter() {
+            if k.len() > 32 {
+                return Err(StorageError::EncodeError(format!(
+                    "Slot key exceeds 32 bytes: {:#x}",
+                    k
+                )));
+            }
+            buf.extend_from_slice(&k.lpad(32, 0));
+            match v {
+                Some(v) => {
+                    if v.len() > 32 {
+                        return Err(StorageError::EncodeError(format!(
+                            "Slot value exceeds 32 bytes: {:#x}",
+                            v
+                        )));
+                    }
+                    buf.push(1);
+                    buf.extend_from_slice(&v.lpad(32, 0));
+                }
+                None => {
+                    buf.push(0);
+                    buf.extend_from_slice(&[0; 32]);
+                }
+            }
+        }
+        for field in [&self.balance, &self.code] {
+            match field {
+                Some(data) => {
+                    buf.push(1);
+                    buf.extend_from_slice(&(data.len() as u32).to_be_bytes());
+                    buf.extend_from_slice(data);
+                }
+                None => buf.push(0),
+            }
+        }
+        Ok(buf)
+    }
+
+    /// Decodes a delta previously encoded with [`AccountDelta::to_bytes`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a `StorageError::DecodeError` if the input is truncated, contains trailing data
+    /// or holds an unknown chain or change tag.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, StorageError> {
+        let mut reader = ByteReader(data);
+        let chain = chain_from_tag(reader.take(1)?[0])?;
+        let change = match reader.take(1)?[0] {
+            0 => ChangeType::Update,
+            1 => ChangeType::Deletion,
+            2 => ChangeType::Creation,
+            tag => return Err(StorageError::DecodeError(format!("Unknown change tag: {}", tag))),
+        };
+        let address = Address::from(reader.take(20)?);
+        let n_slots = reader.take_u32()? as usize;
+        let mut slots = HashMap::with_capacity(n_slots);
+        for _ in 0..n_slots {
+            let key = Slot::try_from(reader.take(32)?)?;
+            let flag = reader.take(1)?[0];
+            let value = Word::try_from(reader.take(32)?)?;
+            slots.insert(key.into(), if flag == 0 { None } else { Some(value.into()) });
+        }
+        let balance = reader.take_optional()?;
+        let code = reader.take_optional()?;
+        if !reader.0.is_empty() {
+            return Err(StorageError::DecodeError(format!(
+                "Unexpected {} trailing bytes in AccountDelta",
+                reader.0.len()
+            )));
+        }
+        Ok(Self { chain, address, slots, balance, code, change })
+    }
+}
+
+/// An [`AccountDelta`] in the hex encoding forwarded to downstream consumers.
+///
+/// Unlike the derived serde format of [`AccountDelta`], slot keys and values are encoded as 0x
+/// prefixed 32 byte hex strings and the change type as a lowercase string. Since slots are
+/// written without padding, conversion from an [`AccountDelta`] fails for slot keys or values
+/// that are not exactly 32 bytes, and deserialization rejects them as well.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HexAccountDelta {
+    chain: Chain,
+    address: Address,
+    #[serde(with = "hex_hashmap_slots")]
+    slots: HashMap<StoreKey, Option<StoreVal>>,
+    balance: Option<Balance>,
+    code: Option<Code>,
+    #[serde(with = "change_type_lowercase")]
+    change: ChangeType,
+}
+
+impl TryFrom<AccountDelta> for HexAccountDelta {
+    type Error = StorageError;
+
+    fn try_from(value: AccountDelta) -> Result<Self, Self::Error> {
+        let words = value
+            .slots
+            .iter()
+            .flat_map(|(k, v)| std::iter::once(k).chain(v));
+        for word in words {
+            if word.len() != 32 {
+                return Err(StorageError::EncodeError(format!(
+                    "Slot key or value must be 32 bytes: got {:#x}",
+                    word
+                )));
+            }
+        }
+        Ok(Self {
+            chain: value.chain,
+            address: value.address,
+            slots: value.slots,
+            balance: value.balance,
+            code: value.code,
+            change: value.change,
+        })
+    }
+}
+
+impl From<HexAccountDelta> for AccountDelta {
+    fn from(value: HexAccountDelta) -> Self {
+        Self::new(value.chain, value.address, value.slots, value.balance, value.code, value.change)
+    }
+}
+
+/// Implements the 32-byte word contract shared by [`Slot`] and [`Word`].
+///
+/// Both `TryFrom` conversions only accept exactly 32 bytes. Shorter values have to be widened
+/// explicitly through `from_padded`, so every value is exactly 32 bytes wide.
+macro_rules! bytes32_newtype {
+    ($name:ident, $inner:ty) => {
+        impl $name {
+            pub fn to_bytes32(&self) -> [u8; 32] {
+                self.0
+            }
+
+            /// Left pads `value` with zeros to 32 bytes.
+            ///
+            /// Errors if `value` is longer than 32 bytes.
+            pub fn from_padded(value: &[u8]) -> Result<Self, StorageError> {
+                if value.len() > 32 {
+                    return Err(StorageError::DecodeError(format!(
+                        "{} exceeds 32 bytes: got {} bytes",
+                        stringify!($name),
+                        value.len()
+                    )));
+                }
+                let mut raw = [0u8; 32];
+                raw[32 - value.len()..].copy_from_slice(value);
+                Ok(Self(raw))
+            }
+        }
+
+        impl From<[u8; 32]> for $name {
+            fn from(value: [u8; 32]) -> Self {
+                Self(value)
+            }
+        }
+
+        impl TryFrom<&[u8]> for $name {
+            type Error = StorageError;
+
+            fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
+                let raw: [u8; 32] = value.try_into().map_err(|_| {
+                    StorageError::DecodeError(format!(
+                        "{} must be 32 bytes: got {} bytes",
+                        stringify!($name),
+                        value.len()
+                    ))
+                })?;
+                Ok(Self(raw))
+            }
+        }
+
+        impl TryFrom<$inner> for $name {
+            type Error = StorageError;
+
+            fn try_from(value: $inner) -> Result<Self, Self::Error> {
+                Self::try_from(value.as_ref())
+            }
+        }
+
+        impl From<$name> for $inner {
+            fn from(value: $name) -> Self {
+                Bytes::from(value.0.to_vec())
+            }
+        }
+    };
+}
+
+/// A contract storage slot key, always 32 bytes wide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Slot([u8; 32]);
+
+/// A contract storage value, always 32 bytes wide.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct Word([u8; 32]);
+
+bytes32_newtype!(Slot, StoreKey);
+bytes32_newtype!(Word, StoreVal);
+
+/// Computes the slot changes needed to go from `before` to `after`.
+///
+/// Slots that were added or whose value changed are returned with their new value. Slots only
+/// present in `before` are returned with an explicit `None` to mark them as removed. Unchanged
+/// slots are omitted.
+pub fn diff_stores(before: &ContractStore, after: &ContractStore) -> ContractStore {
+    let changed = after
+        .iter()
+        .filter(|(slot, value)| before.get(*slot) != Some(*value))
+        .map(|(slot, value)| (slot.clone(), value.clone()));
+    let removed = before
+        .iter()
+        .filter(|(slot, value)| value.is_some() && !after.contains_key(*slot))
+        .map(|(slot, _)| (slot.clone(), None));
+    changed.chain(removed).collect()
+}
+
+/// Stable one byte tag for a chain, used in binary encodings.
+fn chain_tag(chain: &Chain) -> u8 {
+    match chain {
+        Chain::Ethereum => 0,
+        Chain::Starknet => 1,
+        Chain::ZkSync => 2,
+        Chain::Arbitrum => 3,
+        Chain::Base => 4,
+    }
+}
+
+fn chain_from_tag(tag: u8) -> Result<Chain, StorageError> {
+    match tag {
+        0 => Ok(Chain::Ethereum),
+        1 => Ok(Chain::Starknet),
+        2 => Ok(Chain::ZkSync),
+        3 => Ok(Chain::Arbitrum),
+        4 => Ok(Chain::Base),
+        _ => Err(StorageError::DecodeError(format!("Unknown chain tag: {}", tag))),
+    }
+}
+
+/// Minimal cursor over a byte slice used to decode binary encodings.
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], StorageError> {
+        if self.0.len() < n {
+            return Err(StorageError::DecodeError(format!(
+                "Unexpected end of input: expected {} bytes, got {}",
+                n,
+                self.0.len()
+            )));
+        }
+        let (head, tail) = self.0.split_at(n);
+        self.0 = tail;
+        Ok(head)
+    }
+
+    fn take_u32(&mut self) -> Result<u32, StorageError> {
+        let raw = self.take(4)?;
+        Ok(u32::from_be_bytes([raw[0], raw[1], raw[2], raw[3]]))
+    }
+
+    fn take_optional(&mut self) -> Result<Option<Bytes>, StorageError> {
+        if self.take(1)?[0] == 0 {
+            return Ok(None);
+        }
+        let len = self.take_u32()? as usize;
+        Ok(Some(Bytes::from(self.take(len)?)))
+    }
 }
 
 impl From<Account> for AccountDelta {
@@ -415,7 +830,8 @@ impl From<&AccountChangesWithTx> for Vec<Account> {
     /// is actually a creation.
     ///
     /// Assumes that all relevant changes are set on `self` if something is
-    /// missing, it will use the corresponding types default.
+    /// missing, it will use the corresponding types default. Deleted slots are stored as
+    /// [`deleted_slot_value`].
     /// Will use the associated transaction as creation, balance and code modify
     /// transaction.
     fn from(value: &AccountChangesWithTx) -> Self {
@@ -431,7 +847,7 @@ impl From<&AccountChangesWithTx> for Vec<Account> {
                     update
                         .slots
                         .into_iter()
-                        .map(|(k, v)| (k, v.unwrap_or_default())) //TODO: is default ok here or should it be Bytes::zero(32)
+                        .map(|(k, v)| (k, v.unwrap_or_else(deleted_slot_value)))
                         .collect(),
                     update.balance.unwrap_or_default(),
                     update.code.clone().unwrap_or_default(),
@@ -454,6 +870,7 @@ impl From<&AccountChangesWithTx> for Vec<Account> {
 #[cfg(test)]
 mod test {
     use chrono::NaiveDateTime;
+    use rand::Rng;
     use rstest::rstest;
     use std::str::FromStr;
 
@@ -506,6 +923,155 @@ mod test {
         assert_eq!(update_left, exp);
     }
 
+    #[test]
+    fn test_account_delta_serde_format() {
+        let delta = update_slots_delta();
+
+        let json = serde_json::to_value(&delta).unwrap();
+        let res: AccountDelta = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(json["change"], "Update");
+        assert_eq!(res, delta);
+    }
+
+    #[test]
+    fn test_hex_account_delta_serde_round_trip() {
+        let mut delta = update_slots_delta();
+        delta.balance = Some(Bytes::from(420u64).lpad(32, 0));
+
+        let json = serde_json::to_value(HexAccountDelta::try_from(delta.clone()).unwrap()).unwrap();
+        let res: HexAccountDelta = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(json["change"], "update");
+        assert_eq!(json["slots"][format!("0x{:0>64x}", 1)], format!("0x{:0>64x}", 2));
+        assert_eq!(AccountDelta::from(res), delta);
+    }
+
+    #[test]
+    fn test_hex_account_delta_from_short_slot() {
+        let mut delta = update_slots_delta();
+        delta
+            .slots
+            .insert(Bytes::from("0x01"), Some(Bytes::zero(32)));
+
+        let res = HexAccountDelta::try_from(delta);
+
+        assert!(matches!(res, Err(StorageError::EncodeError(_))));
+    }
+
+    #[rstest]
+    #[case::short_key(serde_json::json!({"0x01": format!("0x{:0>64x}", 2)}))]
+    #[case::short_value(serde_json::json!({format!("0x{:0>64x}", 1): "0x02"}))]
+    fn test_hex_account_delta_deserialize_invalid_slot(#[case] slots: serde_json::Value) {
+        let json = serde_json::json!({
+            "chain": "ethereum",
+            "address": "0xe688b84b23f322a994a53dbf8e15fa82cdb71127",
+            "slots": slots,
+            "balance": null,
+            "code": null,
+            "change": "update"
+        });
+
+        let res = serde_json::from_value::<HexAccountDelta>(json);
+
+        assert!(res.is_err());
+    }
+
+    #[test]
+    fn test_hex_account_delta_deserialize_capitalized_change() {
+        let hex_delta = HexAccountDelta::try_from(update_slots_delta()).unwrap();
+        let mut json = serde_json::to_value(hex_delta).unwrap();
+        json["change"] = serde_json::json!("Creation");
+
+        let res: HexAccountDelta = serde_json::from_value(json).unwrap();
+
+        assert_eq!(AccountDelta::from(res).change, ChangeType::Creation);
+    }
+
+    #[test]
+    fn test_account_delta_bytes_round_trip() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..100 {
+            let n_slots = rng.gen_range(0..50);
+            let slots = (0..n_slots)
+                .map(|_| {
+                    let value = if rng.gen_bool(0.2) { None } else { Some(Bytes::random(32)) };
+                    (Bytes::random(32), value)
+                })
+                .collect();
+            let delta = AccountDelta::new(
+                Chain::Arbitrum,
+                Bytes::random(20),
+                slots,
+                rng.gen_bool(0.5)
+                    .then(|| Bytes::random(rng.gen_range(0..32))),
+                rng.gen_bool(0.5)
+                    .then(|| Bytes::random(rng.gen_range(0..256))),
+                ChangeType::Creation,
+            );
+
+            let res = AccountDelta::from_bytes(&delta.to_bytes().unwrap()).unwrap();
+
+            assert_eq!(res, delta);
+        }
+    }
+
+    #[test]
+    fn test_account_delta_from_bytes_truncated() {
+        let encoded = update_slots_delta().to_bytes().unwrap();
+
+        let res = AccountDelta::from_bytes(&encoded[..encoded.len() - 1]);
+
+        assert!(matches!(res, Err(StorageError::DecodeError(_))));
+    }
+
+    #[test]
+    fn test_account_delta_to_bytes_invalid() {
+        let mut short_address = update_slots_delta();
+        short_address.address = Bytes::from("0x1234");
+        let mut long_slot = update_slots_delta();
+        long_slot
+            .slots
+            .insert(Bytes::random(33), None);
+
+        assert!(matches!(short_address.to_bytes(), Err(StorageError::EncodeError(_))));
+        assert!(matches!(long_slot.to_bytes(), Err(StorageError::EncodeError(_))));
+    }
+
+    #[test]
+    fn test_merge_account_delta_change_types() {
+        let mut delta = update_slots_delta();
+        delta.change = ChangeType::Creation;
+        let mut deletion = AccountDelta::deleted(&delta.chain, &delta.address);
+        deletion.balance = Some(Bytes::from(0u64).lpad(32, 0));
+
+        delta
+            .merge(update_balance_delta())
+            .unwrap();
+        assert_eq!(delta.change, ChangeType::Creation);
+        assert_eq!(delta.balance, Some(Bytes::from(420u64).lpad(32, 0)));
+
+        delta.merge(deletion).unwrap();
+        assert_eq!(delta.change, ChangeType::Deletion);
+        assert_eq!(delta.balance, Some(Bytes::from(0u64).lpad(32, 0)));
+        assert_eq!(delta.slots, slots([(0, 1), (1, 2)]));
+    }
+
+    #[test]
+    fn test_merge_account_delta_wrong_chain() {
+        let mut update_left = update_balance_delta();
+        let mut update_right = update_slots_delta();
+        update_right.chain = Chain::Base;
+
+        let res = update_left.merge(update_right);
+
+        assert_eq!(
+            res,
+            Err("Can't merge AccountUpdates from differing chains; Expected ethereum, got base"
+                .into())
+        );
+    }
+
     #[test]
     fn test_merge_account_delta_wrong_address() {
         let mut update_left = update_balance_delta();
@@ -566,6 +1132,123 @@ mod test {
         )
     }
 
+    #[test]
+    fn test_account_store() {
+        let mut account = account();
+        account.slots = [
+            (Bytes::from(1u64).lpad(32, 0), Bytes::from(10u64).lpad(32, 0)),
+            (Bytes::from(2u64).lpad(32, 0), Bytes::new()),
+        ]
+        .into();
+
+        let res = account.store();
+
+        assert_eq!(
+            res,
+            [
+                (Bytes::from(1u64).lpad(32, 0), Some(Bytes::from(10u64).lpad(32, 0))),
+                (Bytes::from(2u64).lpad(32, 0), Some(Bytes::new())),
+            ]
+            .into()
+        );
+        assert_eq!(account.store_iter().count(), 2);
+    }
+
+    #[test]
+    fn test_dirty_slots() {
+        let mut delta = update_slots_delta();
+        delta
+            .slots
+            .insert(Bytes::from(2u64).lpad(32, 0), None);
+
+        let res = delta.dirty_slots();
+
+        assert_eq!(res, delta.slots);
+        assert_eq!(delta.dirty_slots_iter().count(), 3);
+    }
+
+    #[test]
+    fn test_slot_bytes32_round_trip() {
+        let raw = [7u8; 32];
+
+        let slot = Slot::from(raw);
+
+        assert_eq!(slot.to_bytes32(), raw);
+        assert_eq!(StoreKey::from(slot), Bytes::from(raw.to_vec()));
+    }
+
+    #[test]
+    fn test_word_from_padded() {
+        let word = Word::from_padded(&Bytes::from(1u8)).unwrap();
+
+        assert_eq!(StoreVal::from(word), Bytes::from(1u8).lpad(32, 0));
+        assert_eq!(word.to_bytes32()[31], 1);
+    }
+
+    #[test]
+    fn test_word_from_padded_rejects_oversized() {
+        let res = Word::from_padded(&[1u8; 33]);
+
+        assert!(matches!(res, Err(StorageError::DecodeError(_))));
+    }
+
+    #[rstest]
+    #[case::short(31)]
+    #[case::long(33)]
+    #[case::empty(0)]
+    fn test_slot_try_from_rejects_wrong_length(#[case] len: usize) {
+        let raw = vec![1u8; len];
+
+        assert!(matches!(Slot::try_from(raw.as_slice()), Err(StorageError::DecodeError(_))));
+        assert!(matches!(Word::try_from(raw.as_slice()), Err(StorageError::DecodeError(_))));
+        assert!(matches!(
+            Slot::try_from(Bytes::from(raw.clone())),
+            Err(StorageError::DecodeError(_))
+        ));
+        assert!(matches!(Word::try_from(Bytes::from(raw)), Err(StorageError::DecodeError(_))));
+    }
+
+    #[test]
+    fn test_account_from_base_and_updates() {
+        let base = account();
+        let mut exp = account();
+        exp.native_balance = Bytes::from(420u64).lpad(32, 0);
+        exp.slots = slots([(0, 1), (1, 2)])
+            .into_iter()
+            .map(|(k, v)| (k, v.unwrap()))
+            .collect();
+
+        let res =
+            Account::from_base_and_updates(base, &[update_balance_delta(), update_slots_delta()])
+                .unwrap();
+
+        assert_eq!(res, exp);
+    }
+
+    #[test]
+    fn test_account_from_base_and_updates_deletion() {
+        let base = account();
+        let deletion = AccountDelta::deleted(&base.chain, &base.address);
+
+        let res = Account::from_base_and_updates(base, &[update_slots_delta(), deletion]).unwrap();
+
+        assert!(res.slots.is_empty());
+        assert_eq!(res.native_balance, Bytes::default());
+        assert_eq!(res.code, Bytes::default());
+        assert_eq!(res.code_hash, Bytes::from(keccak256(Vec::new())));
+    }
+
+    #[test]
+    fn test_account_from_base_and_updates_mismatch() {
+        let base = account();
+        let mut update = update_slots_delta();
+        update.chain = Chain::Base;
+
+        let res = Account::from_base_and_updates(base, &[update]);
+
+        assert!(matches!(res, Err(StorageError::Unexpected(_))));
+    }
+
     #[test]
     fn test_account_from_update_w_tx() {
         let update = tx_vm_update();
@@ -582,6 +1265,85 @@ mod test {
         );
     }
 
+    #[test]
+    fn test_empty_code_hash() {
+        let mut acc = account();
+        acc.code = Code::default();
+        acc.code_hash = Bytes::zero(32);
+
+        acc.with_computed_code_hash();
+
+        assert_eq!(keccak256(Vec::new()), EMPTY_CODE_HASH);
+        assert_eq!(acc.code_hash, Bytes::from(EMPTY_CODE_HASH));
+    }
+
+    #[test]
+    fn test_with_computed_code_hash_survives_delta() {
+        let mut acc = account();
+        acc.code_hash = Bytes::zero(32);
+
+        acc.with_computed_code_hash();
+        let res = AccountDelta::from(acc.clone()).into_account_without_tx();
+
+        assert_eq!(acc.code_hash, Bytes::from(keccak256(vec![0, 0, 0, 0])));
+        assert_eq!(res.code_hash, acc.code_hash);
+    }
+
+    #[test]
+    fn test_set_store_from_iter() {
+        let mut acc = account();
+        acc.slots
+            .insert(Bytes::from(9u64).lpad(32, 0), Bytes::from(9u64).lpad(32, 0));
+        let store = slots([(0, 1), (1, 2)]);
+        let exp: HashMap<Bytes, Bytes> = store
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone().unwrap()))
+            .collect();
+
+        acc.set_store_from_iter(
+            store
+                .iter()
+                .map(|(k, v)| (k, v.as_ref())),
+        );
+
+        assert_eq!(acc.slots, exp);
+    }
+
+    #[test]
+    fn test_set_store_from_iter_deleted_slot() {
+        let mut acc = account();
+        let slot = Bytes::from(1u64).lpad(32, 0);
+
+        acc.set_store_from_iter([(&slot, None)].into_iter());
+
+        assert_eq!(acc.slots, HashMap::from([(slot, Bytes::zero(32))]));
+    }
+
+    #[test]
+    fn test_deleted_slot_conversions() {
+        let slot = Bytes::from(1u64).lpad(32, 0);
+        let mut delta = update_slots_delta();
+        delta.slots = HashMap::from([(slot.clone(), None)]);
+        let tx = block_fixtures::transaction01();
+        let changes = AccountChangesWithTx::new(
+            HashMap::from([(delta.address.clone(), delta.clone())]),
+            HashMap::new(),
+            HashMap::new(),
+            HashMap::new(),
+            tx.clone(),
+        );
+        let mut applied = delta.clone().into_account_without_tx();
+        applied.slots = HashMap::from([(slot.clone(), Bytes::from(10u64).lpad(32, 0))]);
+        applied.apply_delta(&delta).unwrap();
+        let exp = HashMap::from([(slot, deleted_slot_value())]);
+
+        assert_eq!(applied.slots, exp);
+        assert_eq!(delta.ref_into_account(&tx).slots, exp);
+        assert_eq!(delta.clone().into_account(&tx).slots, exp);
+        assert_eq!(delta.into_account_without_tx().slots, exp);
+        assert_eq!(Vec::<Account>::from(&changes)[0].slots, exp);
+    }
+
     #[rstest]
     #[case::diff_block(
     block_fixtures::create_transaction(HASH_256_1, HASH_256_1, 11),
@@ -626,6 +1388,7 @@ mod test {
             change: ChangeType::Creation,
             creation_tx: tx_hash,
             created_at: NaiveDateTime::from_timestamp_opt(1000, 0).unwrap(),
+            linked_component: None,
         }
     }
 
@@ -790,4 +1553,38 @@ mod test {
 
         assert_eq!(to_merge_on.account_deltas, acc_update);
     }
+
+    #[test]
+    fn test_diff_stores() {
+        let slot = |b: u8| Bytes::from(vec![b]);
+        let before: ContractStore = [
+            (slot(1), Some(slot(10))),
+            (slot(2), Some(slot(20))),
+            (slot(3), Some(slot(30))),
+            (slot(4), None),
+        ]
+        .into_iter()
+        .collect();
+        let after: ContractStore =
+            [(slot(1), Some(slot(10))), (slot(2), Some(slot(21))), (slot(5), Some(slot(50)))]
+                .into_iter()
+                .collect();
+        let exp: ContractStore =
+            [(slot(2), Some(slot(21))), (slot(3), None), (slot(5), Some(slot(50)))]
+                .into_iter()
+                .collect();
+
+        let res = diff_stores(&before, &after);
+
+        assert_eq!(res, exp);
+    }
+
+    #[test]
+    fn test_diff_stores_unchanged() {
+        let store: ContractStore = [(Bytes::from(vec![1u8]), Some(Bytes::from(vec![10u8])))]
+            .into_iter()
+            .collect();
+
+        assert!(diff_stores(&store, &store).is_empty());
+    }
 }
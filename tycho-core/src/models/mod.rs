@@ -3,9 +3,9 @@ pub mod contract;
 pub mod protocol;
 pub mod token;
 
-use crate::{dto, Bytes};
+use crate::{dto, storage::StorageError, Bytes};
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, fmt::Display, sync::Arc};
+use std::{collections::HashMap, fmt::Display, str::FromStr, sync::Arc};
 use strum_macros::{Display, EnumString};
 use thiserror::Error;
 
@@ -45,6 +45,9 @@ pub type ContractStore = HashMap<StoreKey, Option<StoreVal>>;
 /// Multiple key values stores grouped by account address.
 pub type AccountToContractStore = HashMap<Address, ContractStore>;
 
+/// The entries of a [`ContractStore`], sorted by key.
+pub type OrderedContractStore = Vec<(StoreKey, Option<StoreVal>)>;
+
 /// Component id literal type to uniquely identify a component.
 pub type ComponentId = String;
 
@@ -131,14 +134,28 @@ pub trait NormalisedMessage:
     fn as_any(&self) -> &dyn std::any::Any;
 }
 
-#[derive(PartialEq, Debug, Clone, Default, Deserialize, Serialize)]
+#[derive(PartialEq, Debug, Clone, Default, Deserialize, Serialize, Display)]
+#[strum(serialize_all = "lowercase")]
 pub enum ImplementationType {
     #[default]
     Vm,
     Custom,
 }
 
-#[derive(PartialEq, Debug, Clone, Default, Deserialize, Serialize)]
+impl FromStr for ImplementationType {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "vm" => Ok(Self::Vm),
+            "custom" => Ok(Self::Custom),
+            _ => Err(StorageError::DecodeError(format!("Unknown implementation type: {}", s))),
+        }
+    }
+}
+
+#[derive(PartialEq, Debug, Clone, Default, Deserialize, Serialize, Display)]
+#[strum(serialize_all = "lowercase")]
 pub enum FinancialType {
     #[default]
     Swap,
@@ -147,6 +164,20 @@ pub enum FinancialType {
     Leverage,
 }
 
+impl FromStr for FinancialType {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "swap" => Ok(Self::Swap),
+            "psm" => Ok(Self::Psm),
+            "debt" => Ok(Self::Debt),
+            "leverage" => Ok(Self::Leverage),
+            _ => Err(StorageError::DecodeError(format!("Unknown financial type: {}", s))),
+        }
+    }
+}
+
 #[derive(Debug, PartialEq, Clone, Default, Deserialize, Serialize)]
 pub struct ProtocolType {
     pub name: String,
@@ -174,6 +205,20 @@ pub enum ChangeType {
     Creation,
 }
 
+impl ChangeType {
+    /// Composes two consecutive changes into the change they amount to.
+    ///
+    /// A deletion always wins, a creation followed by an update remains a creation. In every
+    /// other case the later change is kept.
+    pub fn merge(&self, other: &Self) -> Self {
+        match (self, other) {
+            (_, Self::Deletion) => Self::Deletion,
+            (Self::Creation, _) => Self::Creation,
+            (_, other) => *other,
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct ContractId {
     pub address: Address,
@@ -193,7 +238,24 @@ impl ContractId {
 
 impl Display for ContractId {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}: 0x{}", self.chain, hex::encode(&self.address))
+        write!(f, "{}:0x{}", self.chain, hex::encode(&self.address))
+    }
+}
+
+/// Parses the `"{chain}:0x{address}"` form emitted by `Display`.
+impl FromStr for ContractId {
+    type Err = StorageError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (chain, address) = s.split_once(':').ok_or_else(|| {
+            StorageError::DecodeError(format!("Expected '<chain>:<address>', got '{}'", s))
+        })?;
+        let chain = Chain::from_str(chain)
+            .map_err(|_| StorageError::DecodeError(format!("Unknown chain '{}'", chain)))?;
+        let address = Address::from_str(address).map_err(|err| {
+            StorageError::DecodeError(format!("Invalid contract address '{}': {}", address, err))
+        })?;
+        Ok(Self::new(chain, address))
     }
 }
 
@@ -224,3 +286,57 @@ pub enum DeltaError {
     #[error("Id mismatch: {0} vs {1}")]
     IdMismatch(String, String),
 }
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    #[test]
+    fn test_contract_id_round_trip() {
+        let id = ContractId::new(
+            Chain::Arbitrum,
+            Bytes::from_str("0x6b175474e89094c44da98b954eedeac495271d0f").unwrap(),
+        );
+
+        let s = id.to_string();
+
+        assert_eq!(s, "arbitrum:0x6b175474e89094c44da98b954eedeac495271d0f");
+        assert_eq!(ContractId::from_str(&s).unwrap(), id);
+    }
+
+    #[rstest]
+    #[case::missing_separator("ethereum0x6b17")]
+    #[case::unknown_chain("solana:0x6b17")]
+    #[case::invalid_address("ethereum:0xzz")]
+    fn test_contract_id_from_str_invalid(#[case] input: &str) {
+        let res = ContractId::from_str(input);
+
+        assert!(matches!(res, Err(StorageError::DecodeError(_))));
+    }
+
+    #[rstest]
+    #[case::vm(ImplementationType::Vm, "vm")]
+    #[case::custom(ImplementationType::Custom, "custom")]
+    fn test_implementation_type_round_trip(#[case] value: ImplementationType, #[case] s: &str) {
+        assert_eq!(value.to_string(), s);
+        assert_eq!(ImplementationType::from_str(s).unwrap(), value);
+    }
+
+    #[rstest]
+    #[case::swap(FinancialType::Swap, "swap")]
+    #[case::psm(FinancialType::Psm, "psm")]
+    #[case::debt(FinancialType::Debt, "debt")]
+    #[case::leverage(FinancialType::Leverage, "leverage")]
+    fn test_financial_type_round_trip(#[case] value: FinancialType, #[case] s: &str) {
+        assert_eq!(value.to_string(), s);
+        assert_eq!(FinancialType::from_str(s).unwrap(), value);
+    }
+
+    #[test]
+    fn test_protocol_type_enums_from_str_unknown() {
+        assert!(matches!(ImplementationType::from_str("wasm"), Err(StorageError::DecodeError(_))));
+        assert!(matches!(FinancialType::from_str("Swap"), Err(StorageError::DecodeError(_))));
+    }
+}
@@ -1,8 +1,9 @@
 use crate::{
+    assume_utc,
     models::{Chain, ChangeType},
     Bytes,
 };
-use chrono::NaiveDateTime;
+use chrono::{DateTime, NaiveDateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::{hash_map::Entry, HashMap, HashSet};
 use tracing::warn;
@@ -35,6 +36,9 @@ pub struct ProtocolComponent {
     pub change: ChangeType,
     pub creation_tx: TxHash,
     pub created_at: NaiveDateTime,
+    /// The chain and id of the counterpart this component is linked to on another chain, if any.
+    #[serde(default)]
+    pub linked_component: Option<(Chain, ComponentId)>,
 }
 
 impl ProtocolComponent {
@@ -62,8 +66,58 @@ impl ProtocolComponent {
             change,
             creation_tx,
             created_at,
+            linked_component: None,
         }
     }
+
+    /// Whether `token` is one of this component's tokens.
+    ///
+    /// Components usually hold only a handful of tokens, so this is a plain scan.
+    pub fn contains_token(&self, token: &Address) -> bool {
+        self.tokens.contains(token)
+    }
+
+    /// Returns the creation time as a timezone aware UTC timestamp.
+    pub fn created_at_utc(&self) -> DateTime<Utc> {
+        assume_utc(self.created_at)
+    }
+
+    /// Diffs this component's tokens against a previously stored token list, see
+    /// [`diff_tokens`].
+    pub fn diff_tokens(&self, stored: &[Address]) -> (Vec<Address>, Vec<Address>) {
+        diff_tokens(&self.tokens, stored)
+    }
+}
+
+/// Diffs a token list against a previously stored token list.
+///
+/// Returns `(added, removed)`: tokens present in `current` but not in `stored`, and tokens in
+/// `stored` that are no longer present in `current`. Duplicates in either list are ignored,
+/// the order of first occurrence is preserved.
+pub fn diff_tokens(current: &[Address], stored: &[Address]) -> (Vec<Address>, Vec<Address>) {
+    let current = dedup_addresses(current);
+    let stored = dedup_addresses(stored);
+    let added = current
+        .iter()
+        .filter(|t| !stored.contains(t))
+        .cloned()
+        .collect();
+    let removed = stored
+        .iter()
+        .filter(|t| !current.contains(t))
+        .cloned()
+        .collect();
+    (added, removed)
+}
+
+/// Removes duplicate addresses, keeping the first occurrence of each.
+fn dedup_addresses(addresses: &[Address]) -> Vec<Address> {
+    let mut seen = HashSet::new();
+    addresses
+        .iter()
+        .filter(|a| seen.insert(*a))
+        .cloned()
+        .collect()
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -146,6 +200,10 @@ impl ProtocolComponentStateDelta {
     /// NB: It is assumed that `other` is a more recent update than `self` is and the two are
     /// combined accordingly.
     ///
+    /// Deleted attributes are reconciled with updated ones: an attribute deleted in `other` is
+    /// dropped from `self.updated_attributes`, while an attribute set again in `other` is no
+    /// longer considered deleted.
+    ///
     /// # Errors
     /// This method will return `CoreError::MergeError` if any of the above
     /// conditions is violated.
@@ -242,6 +300,17 @@ impl ProtocolChangesWithTx {
                 self.tx.index, other.tx.index
             ));
         }
+        // Validate all of `other` first, so a failing merge leaves `self` untouched.
+        if let Some((key, value)) = other
+            .protocol_states
+            .iter()
+            .find(|(key, value)| **key != value.component_id)
+        {
+            return Err(format!(
+                "Can't merge ProtocolStates keyed by a differing component id: {} != {}",
+                key, value.component_id
+            ));
+        }
         self.tx = other.tx;
         // Merge protocol states
         for (key, value) in other.protocol_states {
@@ -358,6 +427,114 @@ mod test {
         assert_eq!(state_1.deleted_attributes, expected_del_attributes);
     }
 
+    #[test]
+    fn test_diff_tokens() {
+        let weth = Bytes::from("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let usdc = Bytes::from("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let dai = Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f");
+        let component = ProtocolComponent {
+            tokens: vec![weth.clone(), usdc.clone(), usdc.clone()],
+            ..Default::default()
+        };
+
+        let (added, removed) = component.diff_tokens(&[weth.clone(), dai.clone(), dai.clone()]);
+
+        assert_eq!(added, vec![usdc]);
+        assert_eq!(removed, vec![dai]);
+    }
+
+    #[test]
+    fn test_contains_token() {
+        let weth = Bytes::from("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2");
+        let usdc = Bytes::from("0xa0b86991c6218b36c1d19d4a2e9eb0ce3606eb48");
+        let dai = Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f");
+        let component = ProtocolComponent {
+            tokens: vec![weth.clone(), usdc.clone(), usdc.clone()],
+            ..Default::default()
+        };
+
+        assert!(component.contains_token(&weth));
+        assert!(component.contains_token(&usdc));
+        assert!(!component.contains_token(&dai));
+    }
+
+    #[test]
+    fn test_merge_protocol_state_update_with_tx_wrong_key() {
+        let mut base_state = protocol_state_with_tx();
+        let expected = base_state.clone();
+        let new_states: HashMap<String, ProtocolComponentStateDelta> =
+            vec![("State1".to_owned(), create_state("State2".to_owned()))]
+                .into_iter()
+                .collect();
+        let new_tx = block_fixtures::create_transaction(HASH_256_1, HASH_256_0, 11);
+        let tx_update =
+            ProtocolChangesWithTx { protocol_states: new_states, tx: new_tx, ..Default::default() };
+
+        let res = base_state.merge(tx_update);
+
+        assert_eq!(
+            res,
+            Err("Can't merge ProtocolStates keyed by a differing component id: State1 != State2"
+                .to_owned())
+        );
+        assert_eq!(base_state, expected);
+    }
+
+    #[test]
+    fn test_merge_protocol_state_update_delete_update() {
+        let attr = "reserve1".to_owned();
+        let delta = |updated: Option<u64>, deleted: bool| ProtocolComponentStateDelta {
+            component_id: "State1".to_owned(),
+            updated_attributes: updated
+                .map(|v| (attr.clone(), Bytes::from(v).lpad(32, 0)))
+                .into_iter()
+                .collect(),
+            deleted_attributes: if deleted {
+                HashSet::from([attr.clone()])
+            } else {
+                HashSet::new()
+            },
+        };
+        let mut state = delta(Some(1), false);
+
+        state.merge(delta(None, true)).unwrap();
+        assert!(state.updated_attributes.is_empty());
+        assert_eq!(state.deleted_attributes, HashSet::from([attr.clone()]));
+
+        state
+            .merge(delta(Some(2), false))
+            .unwrap();
+        assert_eq!(
+            state.updated_attributes,
+            HashMap::from([(attr.clone(), Bytes::from(2u64).lpad(32, 0))])
+        );
+        assert!(state.deleted_attributes.is_empty());
+    }
+
+    #[test]
+    fn test_protocol_component_serde_round_trip() {
+        let component = ProtocolComponent::new(
+            "0x6b175474e89094c44da98b954eedeac495271d0f",
+            "uniswap_v2",
+            "Pool",
+            Chain::Ethereum,
+            vec![Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f")],
+            vec![Bytes::from("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2")],
+            HashMap::from([("fee".to_owned(), Bytes::from(3000u64).lpad(32, 0))]),
+            ChangeType::Creation,
+            Bytes::from(HASH_256_1),
+            "2020-01-01T01:00:00".parse().unwrap(),
+        );
+
+        let json = serde_json::to_value(&component).unwrap();
+        let res: ProtocolComponent = serde_json::from_value(json.clone()).unwrap();
+
+        assert_eq!(json["creation_tx"], HASH_256_1);
+        assert_eq!(json["tokens"][0], "0x6b175474e89094c44da98b954eedeac495271d0f");
+        assert_eq!(json["static_attributes"]["fee"], format!("0x{:0>64x}", 3000));
+        assert_eq!(res, component);
+    }
+
     fn protocol_state_with_tx() -> ProtocolChangesWithTx {
         let state_1 = create_state("State1".to_owned());
         let state_2 = create_state("State2".to_owned());
@@ -1,6 +1,6 @@
 use std::collections::HashMap;
 
-use crate::{models::Chain, traits::TokenOwnerFinding, Bytes};
+use crate::{models::Chain, storage::StorageError, traits::TokenOwnerFinding, Bytes};
 use serde::{Deserialize, Serialize};
 
 use super::{Address, Balance};
@@ -49,6 +49,114 @@ impl CurrencyToken {
             quality,
         }
     }
+
+    /// Returns the highest recorded transfer cost, ignoring missing measurements.
+    pub fn max_gas(&self) -> Option<TransferCost> {
+        self.gas.iter().flatten().max().copied()
+    }
+
+    /// Returns the average recorded transfer cost, ignoring missing measurements.
+    pub fn avg_gas(&self) -> Option<f64> {
+        let measured: Vec<_> = self.gas.iter().flatten().collect();
+        if measured.is_empty() {
+            return None;
+        }
+        Some(
+            measured
+                .iter()
+                .map(|g| **g as f64)
+                .sum::<f64>() /
+                measured.len() as f64,
+        )
+    }
+
+    /// Formats a raw token amount as a fixed-point decimal string using the token's decimals.
+    ///
+    /// E.g. a raw amount of `1500000000000000000` for a token with 18 decimals is formatted as
+    /// `"1.500000000000000000"`.
+    pub fn format_amount(&self, raw: &Balance) -> String {
+        let digits = be_bytes_to_decimal(raw);
+        let decimals = self.decimals as usize;
+        if decimals == 0 {
+            return digits;
+        }
+        let padded = format!("{:0>width$}", digits, width = decimals + 1);
+        let (integer, fraction) = padded.split_at(padded.len() - decimals);
+        format!("{}.{}", integer, fraction)
+    }
+
+    /// Parses a human readable decimal amount into a raw 32 byte token amount.
+    ///
+    /// This is the inverse of [`CurrencyToken::format_amount`]. Errors if the amount is not a
+    /// valid decimal number, has more fractional digits than the token's decimals or does not
+    /// fit into 32 bytes.
+    pub fn parse_amount(&self, human: &str) -> Result<Balance, StorageError> {
+        let invalid = |reason: &str| {
+            StorageError::DecodeError(format!(
+                "Invalid {} amount {:?}: {}",
+                self.symbol, human, reason
+            ))
+        };
+        let (integer, fraction) = human
+            .trim()
+            .split_once('.')
+            .unwrap_or((human.trim(), ""));
+        if integer.is_empty() ||
+            !integer
+                .chars()
+                .chain(fraction.chars())
+                .all(|c| c.is_ascii_digit())
+        {
+            return Err(invalid("not a decimal number"));
+        }
+        let decimals = self.decimals as usize;
+        if fraction.len() > decimals {
+            return Err(invalid(&format!("more than {} fractional digits", decimals)));
+        }
+        let digits = format!("{}{:0<width$}", integer, fraction, width = decimals);
+        decimal_to_be_bytes(&digits)
+            .map(|raw| Bytes::from(raw.to_vec()))
+            .ok_or_else(|| invalid("exceeds 32 bytes"))
+    }
+}
+
+/// Converts a big endian unsigned integer into its decimal representation.
+fn be_bytes_to_decimal(bytes: &[u8]) -> String {
+    let mut value = bytes.to_vec();
+    let mut digits = Vec::new();
+    while value.iter().any(|b| *b != 0) {
+        let mut remainder = 0u32;
+        for byte in value.iter_mut() {
+            let acc = (remainder << 8) | *byte as u32;
+            *byte = (acc / 10) as u8;
+            remainder = acc % 10;
+        }
+        digits.push(b'0' + remainder as u8);
+    }
+    if digits.is_empty() {
+        return "0".to_string();
+    }
+    digits.reverse();
+    String::from_utf8(digits).expect("decimal digits are valid utf8")
+}
+
+/// Converts a string of decimal digits into a 32 byte big endian unsigned integer.
+///
+/// Returns `None` if the value does not fit into 32 bytes.
+fn decimal_to_be_bytes(digits: &str) -> Option<[u8; 32]> {
+    let mut value = [0u8; 32];
+    for digit in digits.bytes() {
+        let mut carry = (digit - b'0') as u32;
+        for byte in value.iter_mut().rev() {
+            let acc = *byte as u32 * 10 + carry;
+            *byte = acc as u8;
+            carry = acc >> 8;
+        }
+        if carry != 0 {
+            return None;
+        }
+    }
+    Some(value)
 }
 
 /// Represents the quality of a token.
@@ -109,3 +217,86 @@ impl TokenOwnerFinding for TokenOwnerStore {
         Ok(self.values.get(&token).cloned())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rstest::rstest;
+
+    use super::*;
+
+    fn token(decimals: u32) -> CurrencyToken {
+        CurrencyToken::new(
+            &Bytes::from("0xC02aaA39b223FE8D0A0e5C4F27eAD9083C756Cc2"),
+            "WETH",
+            decimals,
+            0,
+            &[],
+            Chain::Ethereum,
+            100,
+        )
+    }
+
+    #[rstest]
+    #[case::partial(vec![Some(64), None], Some(64), Some(64.0))]
+    #[case::multiple(vec![Some(64), None, Some(32)], Some(64), Some(48.0))]
+    #[case::unmeasured(vec![None, None], None, None)]
+    #[case::empty(vec![], None, None)]
+    fn test_gas_aggregation(
+        #[case] gas: Vec<Option<u64>>,
+        #[case] exp_max: Option<u64>,
+        #[case] exp_avg: Option<f64>,
+    ) {
+        let mut token = token(18);
+        token.gas = gas;
+
+        assert_eq!(token.max_gas(), exp_max);
+        assert_eq!(token.avg_gas(), exp_avg);
+    }
+
+    #[rstest]
+    #[case::one_and_a_half(18, "0x14d1120d7b160000", "1.500000000000000000")]
+    #[case::one_wei(18, "0x01", "0.000000000000000001")]
+    #[case::zero(18, "0x", "0.000000000000000000")]
+    #[case::zero_decimals(0, "0x2a", "42")]
+    #[case::max(
+        18,
+        "0xffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff",
+        "115792089237316195423570985008687907853269984665640564039457.584007913129639935"
+    )]
+    fn test_format_parse_amount_round_trip(
+        #[case] decimals: u32,
+        #[case] raw: &str,
+        #[case] human: &str,
+    ) {
+        let token = token(decimals);
+        let raw = Bytes::from(raw);
+
+        let formatted = token.format_amount(&raw);
+        let parsed = token.parse_amount(&formatted).unwrap();
+
+        assert_eq!(formatted, human);
+        assert_eq!(parsed, raw.lpad(32, 0));
+    }
+
+    #[test]
+    fn test_parse_amount_short_fraction() {
+        let res = token(18).parse_amount("1.5").unwrap();
+
+        assert_eq!(res, Bytes::from("0x14d1120d7b160000").lpad(32, 0));
+    }
+
+    #[rstest]
+    #[case::too_many_fractional_digits(18, "1.0000000000000000001")]
+    #[case::fraction_without_decimals(0, "1.5")]
+    #[case::not_a_number(18, "1.5e18")]
+    #[case::empty(18, "")]
+    #[case::overflow(
+        0,
+        "115792089237316195423570985008687907853269984665640564039457584007913129639936"
+    )]
+    fn test_parse_amount_invalid(#[case] decimals: u32, #[case] human: &str) {
+        let res = token(decimals).parse_amount(human);
+
+        assert!(matches!(res, Err(StorageError::DecodeError(_))));
+    }
+}
@@ -192,6 +192,95 @@ pub mod hex_hashmap_key_value {
     }
 }
 
+/// serde functions for handling contract slot maps with 32 byte keys and optional values
+///
+/// Keys and values are encoded as 0x prefixed 32 byte hex strings. Deleted slots (`None`
+/// values) are encoded as `null`. Deserialization rejects keys and values that are not exactly
+/// 32 bytes long.
+pub mod hex_hashmap_slots {
+    use std::collections::HashMap;
+
+    use serde::{de, ser::SerializeMap, Deserialize, Deserializer, Serializer};
+
+    use crate::Bytes;
+
+    use super::decode_hex_with_prefix;
+
+    pub fn serialize<S>(x: &HashMap<Bytes, Option<Bytes>>, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut map = s.serialize_map(Some(x.len()))?;
+        for (k, v) in x.iter() {
+            map.serialize_entry(&format!("{:#x}", k), &v.as_ref().map(|v| format!("{:#x}", v)))?;
+        }
+        map.end()
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<HashMap<Bytes, Option<Bytes>>, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let interim = HashMap::<String, Option<String>>::deserialize(d)?;
+        interim
+            .into_iter()
+            .map(|(k, v)| {
+                let k = decode_word::<D::Error>(&k, "slot key")?;
+                let v = v
+                    .map(|v| decode_word::<D::Error>(&v, "slot value"))
+                    .transpose()?;
+                Ok((k, v))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()
+    }
+
+    fn decode_word<E: de::Error>(value: &str, what: &str) -> Result<Bytes, E> {
+        let word = decode_hex_with_prefix(value).map_err(|e| E::custom(e.to_string()))?;
+        if word.len() != 32 {
+            return Err(E::custom(format!(
+                "Invalid {what} length: expected 32 bytes, got {}",
+                word.len()
+            )));
+        }
+        Ok(word.into())
+    }
+}
+
+/// serde functions for handling a [`ChangeType`](crate::models::ChangeType) as a lowercase
+/// string
+///
+/// Serializes to `"update"`, `"deletion"` or `"creation"`. Deserialization ignores case, so the
+/// capitalized variant names written by the derived impl are still accepted.
+pub mod change_type_lowercase {
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    use crate::models::ChangeType;
+
+    pub fn serialize<S>(x: &ChangeType, s: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        s.serialize_str(match x {
+            ChangeType::Update => "update",
+            ChangeType::Deletion => "deletion",
+            ChangeType::Creation => "creation",
+        })
+    }
+
+    pub fn deserialize<'de, D>(d: D) -> Result<ChangeType, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(d)?;
+        match value.to_ascii_lowercase().as_str() {
+            "update" => Ok(ChangeType::Update),
+            "deletion" => Ok(ChangeType::Deletion),
+            "creation" => Ok(ChangeType::Creation),
+            _ => Err(de::Error::custom(format!("Unknown change type: {value}"))),
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
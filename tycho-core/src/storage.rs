@@ -3,6 +3,7 @@ use std::{collections::HashMap, fmt::Display};
 
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::{
@@ -12,7 +13,7 @@ use crate::{
         blockchain::{Block, Transaction},
         protocol::ComponentBalance,
         Address, BlockHash, Chain, ComponentId, ContractId, ExtractionState, PaginationParams,
-        TxHash,
+        StoreKey, TxHash,
     },
     Bytes,
 };
@@ -56,8 +57,12 @@ pub enum StorageError {
     NoRelatedEntity(String, String, String),
     #[error("DecodeError: {0}")]
     DecodeError(String),
+    #[error("EncodeError: {0}")]
+    EncodeError(String),
     #[error("Unexpected storage error: {0}")]
     Unexpected(String),
+    #[error("Transient storage error: {0}")]
+    Transient(String),
     #[error("Currently unsupported operation: {0}")]
     Unsupported(String),
     #[error("Write cache unexpectedly dropped notification channel!")]
@@ -248,6 +253,18 @@ impl Version {
     }
 }
 
+/// Position within the contract change feed of a chain.
+///
+/// Identifies the last returned change by the time it became valid, the contract it belongs to
+/// and the changed slot. Balance and code changes are positioned before all slots of the same
+/// contract and block, using an empty slot.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeCursor {
+    pub valid_from: NaiveDateTime,
+    pub address: Address,
+    pub slot: StoreKey,
+}
+
 // Helper type to retrieve entities with their total retrievable count.
 #[derive(Debug)]
 pub struct WithTotal<T> {
@@ -267,6 +284,8 @@ pub trait ProtocolGateway {
     /// - `chain` The chain of the component
     /// - `system` Allows to optionally filter by system.
     /// - `id` Allows to optionally filter by id.
+    /// - `created_after` Allows to optionally return only components created strictly after this
+    ///   timestamp, ordered ascending by their creation time.
     ///
     /// # Returns
     /// Ok, if found else Err
@@ -276,6 +295,7 @@ pub trait ProtocolGateway {
         system: Option<String>,
         ids: Option<&[&str]>,
         min_tvl: Option<f64>,
+        created_after: Option<NaiveDateTime>,
         pagination_params: Option<&PaginationParams>,
     ) -> Result<WithTotal<Vec<models::protocol::ProtocolComponent>>, StorageError>;
 
@@ -638,6 +658,11 @@ pub trait ContractStateGateway {
     ) -> Result<Vec<models::contract::AccountDelta>, StorageError>;
 }
 
+/// The complete storage surface used by the indexer.
+///
+/// Consumers should depend on this trait (e.g. via `Arc<dyn Gateway>`) or on one of its
+/// supertraits rather than on a concrete backend, so the postgres implementation can be swapped
+/// for a mock in unit tests.
 pub trait Gateway:
     ChainGateway
     + ContractStateGateway
@@ -648,3 +673,68 @@ pub trait Gateway:
     + Sync
 {
 }
+
+#[cfg(test)]
+mod test {
+    use std::{str::FromStr, sync::Arc};
+
+    use super::*;
+
+    /// Hand-written stand-in for a storage backend that only knows a single block.
+    struct SingleBlockGateway(Block);
+
+    #[async_trait]
+    impl ChainGateway for SingleBlockGateway {
+        async fn upsert_block(&self, _new: &[Block]) -> Result<(), StorageError> {
+            Err(StorageError::Unsupported("upsert_block".to_string()))
+        }
+
+        async fn get_block(&self, id: &BlockIdentifier) -> Result<Block, StorageError> {
+            match id {
+                BlockIdentifier::Hash(hash) if hash == &self.0.hash => Ok(self.0.clone()),
+                _ => Err(StorageError::NotFound("Block".to_string(), id.to_string())),
+            }
+        }
+
+        async fn upsert_tx(&self, _new: &[Transaction]) -> Result<(), StorageError> {
+            Err(StorageError::Unsupported("upsert_tx".to_string()))
+        }
+
+        async fn get_tx(&self, hash: &TxHash) -> Result<Transaction, StorageError> {
+            Err(StorageError::NotFound("Transaction".to_string(), hash.to_string()))
+        }
+
+        async fn revert_state(&self, _to: &BlockIdentifier) -> Result<(), StorageError> {
+            Err(StorageError::Unsupported("revert_state".to_string()))
+        }
+    }
+
+    /// Code under test only depends on the trait object, not on a concrete backend.
+    async fn block_ts(
+        gw: Arc<dyn ChainGateway + Send + Sync>,
+        hash: &Bytes,
+    ) -> Result<NaiveDateTime, StorageError> {
+        Ok(gw
+            .get_block(&BlockIdentifier::Hash(hash.clone()))
+            .await?
+            .ts)
+    }
+
+    #[tokio::test]
+    async fn test_chain_gateway_mock() {
+        let block = Block::new(
+            1,
+            Chain::Ethereum,
+            Bytes::from_str("0x01").unwrap(),
+            Bytes::from_str("0x00").unwrap(),
+            NaiveDateTime::default(),
+        );
+        let gw: Arc<dyn ChainGateway + Send + Sync> = Arc::new(SingleBlockGateway(block.clone()));
+
+        assert_eq!(block_ts(gw.clone(), &block.hash).await, Ok(block.ts));
+        assert!(matches!(
+            block_ts(gw, &Bytes::from_str("0x02").unwrap()).await,
+            Err(StorageError::NotFound(_, _))
+        ));
+    }
+}
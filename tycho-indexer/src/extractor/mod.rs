@@ -25,11 +25,13 @@ use crate::{
 pub mod chain_state;
 pub mod models;
 pub mod post_processors;
+pub mod proto;
 pub mod protobuf_deserialisation;
 pub mod protocol_cache;
 pub mod protocol_extractor;
 pub mod reorg_buffer;
 pub mod runner;
+pub mod storage_changes;
 pub mod token_analysis_cron;
 mod u256_num;
 
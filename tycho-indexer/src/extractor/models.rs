@@ -1,13 +1,13 @@
 #![allow(deprecated)]
-use std::collections::{HashMap, HashSet};
+use std::collections::{hash_map::Entry, HashMap, HashSet};
 
 use tycho_core::{
     models::{
         blockchain::{Block, BlockAggregatedChanges, BlockScoped, TxWithChanges},
-        contract::AccountChangesWithTx,
+        contract::{AccountChangesWithTx, AccountDelta},
         protocol::{ComponentBalance, ProtocolChangesWithTx, ProtocolComponent},
         token::CurrencyToken,
-        Address, AttrStoreKey, Chain, ComponentId,
+        Address, AttrStoreKey, Chain, ComponentId, StoreKey, StoreVal,
     },
     Bytes,
 };
@@ -340,6 +340,66 @@ impl From<BlockEntityChanges> for BlockChanges {
     }
 }
 
+/// Merges account deltas targeting the same account into a single delta.
+///
+/// Deltas are merged in the order given, so later changes take precedence, see
+/// [`AccountDelta::merge`]. The merged deltas keep the position of the first delta seen for
+/// each account. This avoids storing intermediate states of contracts that changed several
+/// times within a block.
+pub fn dedup_account_updates(updates: Vec<AccountDelta>) -> Vec<AccountDelta> {
+    let mut positions: HashMap<(Chain, Address), usize> = HashMap::with_capacity(updates.len());
+    let mut deduped: Vec<AccountDelta> = Vec::with_capacity(updates.len());
+    for update in updates {
+        match positions.entry((update.chain, update.address.clone())) {
+            Entry::Occupied(e) => deduped[*e.get()]
+                .merge(update)
+                .expect("deltas share chain and address"),
+            Entry::Vacant(e) => {
+                e.insert(deduped.len());
+                deduped.push(update);
+            }
+        }
+    }
+    deduped
+}
+
+/// Removes slot writes that leave a slot at the value it held before the block.
+///
+/// Only the net effect of the updates is considered: if the last write to a slot restores its
+/// `prior` value, all writes to that slot are dropped, even if it held other values in between.
+/// Slots without a known prior value are kept.
+pub fn compact_slot_changes(
+    updates: &mut [AccountDelta],
+    prior: &HashMap<Address, HashMap<StoreKey, StoreVal>>,
+) {
+    let mut net: HashMap<(&Address, &StoreKey), &Option<StoreVal>> = HashMap::new();
+    for update in updates.iter() {
+        for (slot, value) in update.slots.iter() {
+            net.insert((&update.address, slot), value);
+        }
+    }
+    let mut unchanged: HashMap<Address, HashSet<StoreKey>> = HashMap::new();
+    for ((address, slot), value) in net {
+        let restored = prior
+            .get(address)
+            .and_then(|slots| slots.get(slot))
+            .is_some_and(|prior_value| value.as_ref() == Some(prior_value));
+        if restored {
+            unchanged
+                .entry(address.clone())
+                .or_default()
+                .insert(slot.clone());
+        }
+    }
+    for update in updates.iter_mut() {
+        if let Some(slots) = unchanged.get(&update.address) {
+            update
+                .slots
+                .retain(|slot, _| !slots.contains(slot));
+        }
+    }
+}
+
 #[cfg(test)]
 pub mod fixtures {
     use chrono::NaiveDateTime;
@@ -456,6 +516,7 @@ pub mod fixtures {
             change: ChangeType::Creation,
             creation_tx: tx_hash,
             created_at: NaiveDateTime::from_timestamp_opt(1000, 0).unwrap(),
+            linked_component: None,
         }
     }
 
@@ -676,6 +737,7 @@ pub mod fixtures {
                 change: ChangeType::Creation,
                 creation_tx: tx.hash.clone(),
                 created_at: yesterday_midnight(),
+                linked_component: None,
             },
         )]
         .into_iter()
@@ -730,6 +792,7 @@ pub mod fixtures {
 mod test {
     use prost::Message;
     use std::str::FromStr;
+    use tycho_core::models::ChangeType;
 
     use super::*;
 
@@ -859,4 +922,66 @@ mod test {
             )])
         )
     }
+
+    fn account_delta(address: &str, slots: &[(u64, u64)], change: ChangeType) -> AccountDelta {
+        AccountDelta::new(
+            Chain::Ethereum,
+            Bytes::from(address),
+            slots
+                .iter()
+                .map(|(k, v)| (Bytes::from(*k).lpad(32, 0), Some(Bytes::from(*v).lpad(32, 0))))
+                .collect(),
+            None,
+            None,
+            change,
+        )
+    }
+
+    #[test]
+    fn test_dedup_account_updates() {
+        let a = "0x6b175474e89094c44da98b954eedeac495271d0f";
+        let b = "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2";
+        let updates = vec![
+            account_delta(a, &[(1, 1), (2, 2)], ChangeType::Creation),
+            account_delta(b, &[(1, 5)], ChangeType::Update),
+            account_delta(a, &[(1, 3)], ChangeType::Update),
+        ];
+
+        let res = dedup_account_updates(updates);
+
+        assert_eq!(
+            res,
+            vec![
+                account_delta(a, &[(1, 3), (2, 2)], ChangeType::Creation),
+                account_delta(b, &[(1, 5)], ChangeType::Update),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_compact_slot_changes_net_zero() {
+        let a = "0x6b175474e89094c44da98b954eedeac495271d0f";
+        let prior = HashMap::from([(
+            Bytes::from(a),
+            HashMap::from([
+                (Bytes::from(1u64).lpad(32, 0), Bytes::from(7u64).lpad(32, 0)),
+                (Bytes::from(2u64).lpad(32, 0), Bytes::from(7u64).lpad(32, 0)),
+            ]),
+        )]);
+        // slot 1 is changed and reset to its prior value, slot 2 keeps its new value
+        let mut updates = vec![
+            account_delta(a, &[(1, 9), (2, 9)], ChangeType::Update),
+            account_delta(a, &[(1, 7)], ChangeType::Update),
+        ];
+
+        compact_slot_changes(&mut updates, &prior);
+
+        assert_eq!(
+            updates,
+            vec![
+                account_delta(a, &[(2, 9)], ChangeType::Update),
+                account_delta(a, &[], ChangeType::Update),
+            ]
+        );
+    }
 }
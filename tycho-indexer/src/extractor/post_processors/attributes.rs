@@ -150,6 +150,7 @@ mod test {
                         static_attributes: Default::default(),
                         created_at: Default::default(),
                         change: Default::default(),
+                        linked_component: None,
                     },
                 )]),
                 account_deltas: Default::default(),
@@ -282,6 +283,7 @@ mod test {
                         ]),
                         created_at: Default::default(),
                         change: Default::default(),
+                        linked_component: None,
                     },
                 )]),
                 tx: Transaction::new(
@@ -330,6 +332,7 @@ mod test {
                         ]),
                         created_at: Default::default(),
                         change: Default::default(),
+                        linked_component: None,
                     },
                 )]),
                 tx: Transaction::new(
@@ -0,0 +1,133 @@
+//! # Protobuf account updates
+//!
+//! Protobuf messages used to forward account updates to other services, generated from
+//! `proto/tycho/evm/v1/stream.proto`.
+use std::{collections::HashMap, str::FromStr};
+
+use tycho_core::{
+    models::{contract::AccountDelta, Address, Chain, ChangeType},
+    Bytes,
+};
+
+use crate::{extractor::ExtractionError, pb::tycho::evm::v1 as substreams};
+
+pub use crate::pb::tycho::evm::v1::{AccountUpdate, SlotUpdate};
+
+/// Encodes an account delta for forwarding to other services.
+///
+/// Addresses are left padded to 20 bytes, slots and slot values to 32 bytes. Slots are sorted
+/// so equal deltas always encode to the same bytes.
+impl From<AccountDelta> for AccountUpdate {
+    fn from(value: AccountDelta) -> Self {
+        let change: substreams::ChangeType = value.change.into();
+        let mut slots = value
+            .slots
+            .into_iter()
+            .map(|(slot, value)| SlotUpdate {
+                slot: slot.lpad(32, 0).to_vec(),
+                value: value.map(|v| v.lpad(32, 0).to_vec()),
+            })
+            .collect::<Vec<_>>();
+        slots.sort_unstable_by(|a, b| a.slot.cmp(&b.slot));
+        Self {
+            chain: value.chain.to_string(),
+            address: value.address.lpad(20, 0).to_vec(),
+            slots,
+            balance: value.balance.map(|b| b.to_vec()),
+            code: value.code.map(|c| c.to_vec()),
+            change: change.into(),
+        }
+    }
+}
+
+impl TryFrom<AccountUpdate> for AccountDelta {
+    type Error = ExtractionError;
+
+    fn try_from(msg: AccountUpdate) -> Result<Self, Self::Error> {
+        let address = Address::from(msg.address.clone());
+        if address.len() != 20 {
+            return Err(ExtractionError::DecodeError(format!(
+                "Expected 20 byte address, got {address}"
+            )));
+        }
+        let chain = Chain::from_str(&msg.chain).map_err(|_| {
+            ExtractionError::DecodeError(format!(
+                "Unknown chain {} for account {address}",
+                msg.chain
+            ))
+        })?;
+        let change: ChangeType = match msg.change() {
+            substreams::ChangeType::Unspecified => {
+                return Err(ExtractionError::DecodeError(format!(
+                    "Unspecified change type for account {address}"
+                )))
+            }
+            change => change.into(),
+        };
+        let slots = msg
+            .slots
+            .into_iter()
+            .map(|s| {
+                if s.slot.len() != 32 ||
+                    s.value
+                        .as_ref()
+                        .is_some_and(|v| v.len() != 32)
+                {
+                    return Err(ExtractionError::DecodeError(format!(
+                        "Expected 32 byte slots and values for account {address}"
+                    )));
+                }
+                Ok((s.slot.into(), s.value.map(Bytes::from)))
+            })
+            .collect::<Result<HashMap<_, _>, _>>()?;
+
+        Ok(AccountDelta::new(
+            chain,
+            address,
+            slots,
+            msg.balance.map(Bytes::from),
+            msg.code.map(Bytes::from),
+            change,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use prost::Message;
+
+    use super::*;
+
+    #[test]
+    fn test_account_update_proto_round_trip() {
+        let slot = |v: u8| Bytes::from(v).lpad(32, 0);
+        let delta = AccountDelta::new(
+            Chain::Ethereum,
+            Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F"),
+            [(slot(1), Some(slot(2))), (slot(3), None), (slot(4), Some(slot(0)))].into(),
+            Some(Bytes::from("0x64")),
+            Some(Bytes::from("0x1234")),
+            ChangeType::Creation,
+        );
+
+        let encoded = AccountUpdate::from(delta.clone()).encode_to_vec();
+        let decoded = AccountUpdate::decode(encoded.as_slice()).unwrap();
+        let res = AccountDelta::try_from(decoded).unwrap();
+
+        assert_eq!(res, delta);
+    }
+
+    #[test]
+    fn test_account_update_proto_invalid_address() {
+        let msg = AccountUpdate {
+            chain: "ethereum".to_string(),
+            address: vec![1, 2, 3],
+            change: substreams::ChangeType::Update.into(),
+            ..Default::default()
+        };
+
+        let res = AccountDelta::try_from(msg);
+
+        assert!(matches!(res, Err(ExtractionError::DecodeError(_))));
+    }
+}
@@ -189,6 +189,7 @@ impl TryFromMessage for ProtocolComponent {
             change: msg.change().into(),
             creation_tx: tx_hash,
             created_at: creation_ts,
+            linked_component: None,
         })
     }
 }
@@ -206,6 +207,16 @@ impl From<substreams::ChangeType> for ChangeType {
     }
 }
 
+impl From<ChangeType> for substreams::ChangeType {
+    fn from(value: ChangeType) -> Self {
+        match value {
+            ChangeType::Update => substreams::ChangeType::Update,
+            ChangeType::Creation => substreams::ChangeType::Creation,
+            ChangeType::Deletion => substreams::ChangeType::Deletion,
+        }
+    }
+}
+
 impl TryFromMessage for ProtocolComponentStateDelta {
     type Args<'a> = substreams::EntityChanges;
 
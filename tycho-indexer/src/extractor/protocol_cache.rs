@@ -94,7 +94,7 @@ impl ProtocolMemoryCache {
         {
             let mut cached_components = self.components.write().await;
             self.gateway
-                .get_protocol_components(&self.chain, None, None, None, None)
+                .get_protocol_components(&self.chain, None, None, None, None, None)
                 .await?
                 .entity
                 .into_iter()
@@ -236,6 +236,7 @@ impl ProtocolDataCache for ProtocolMemoryCache {
                     ),
                     None,
                     None,
+                    None,
                 )
                 .await?
                 .entity
@@ -396,7 +397,7 @@ mod tests {
         let ret_components = components.clone();
         gateway
             .expect_get_protocol_components()
-            .return_once(move |_, _, _, _, _| {
+            .return_once(move |_, _, _, _, _, _| {
                 Box::pin(async { Ok(WithTotal { entity: ret_components, total: Some(10) }) })
             });
 
@@ -427,7 +428,7 @@ mod tests {
             });
         gateway
             .expect_get_protocol_components()
-            .return_once(|_, _, _, _, _| {
+            .return_once(|_, _, _, _, _, _| {
                 Box::pin(async { Ok(WithTotal { entity: components(), total: Some(10) }) })
             });
         gateway
@@ -2091,6 +2091,7 @@ mod test_serial_db {
                         static_attributes: Default::default(),
                         created_at: Default::default(),
                         change: Default::default(),
+                        linked_component: None,
                     },
                 )]),
                 account_deltas: HashMap::new(),
@@ -2148,6 +2149,7 @@ mod test_serial_db {
                             change: Default::default(),
                             creation_tx: VM_TX_HASH_0.parse().unwrap(),
                             created_at: Default::default(),
+                            linked_component: None,
                         },
                     )]),
                     [(
@@ -2267,6 +2269,7 @@ mod test_serial_db {
                 static_attributes: Default::default(),
                 created_at: Default::default(),
                 change: Default::default(),
+                linked_component: None,
             }];
 
             gw.advance(&msg, "cursor@500", false)
@@ -2281,6 +2284,7 @@ mod test_serial_db {
                     Some([NATIVE_CREATED_CONTRACT].as_slice()),
                     None,
                     None,
+                    None,
                 )
                 .await
                 .expect("test successfully inserted native contract")
@@ -2321,7 +2325,7 @@ mod test_serial_db {
             assert_eq!(tokens.len(), 2);
 
             let protocol_components = cached_gw
-                .get_protocol_components(&Chain::Ethereum, None, None, None, None)
+                .get_protocol_components(&Chain::Ethereum, None, None, None, None, None)
                 .await
                 .unwrap()
                 .entity;
@@ -2479,6 +2483,7 @@ mod test_serial_db {
                         change: ChangeType::Creation,
                         creation_tx: Bytes::from_str("0x000000000000000000000000000000000000000000000000000000000000c351").unwrap(),
                         created_at: NaiveDateTime::from_timestamp_opt(base_ts + 5000, 0).unwrap(),
+                        linked_component: None,
                     }),
                 ]),
                 deleted_protocol_components: HashMap::from([
@@ -2496,6 +2501,7 @@ mod test_serial_db {
                         change: ChangeType::Deletion,
                         creation_tx: Bytes::from_str("0x0000000000000000000000000000000000000000000000000000000000009c41").unwrap(),
                         created_at: NaiveDateTime::from_timestamp_opt(base_ts + 4000, 0).unwrap(),
+                        linked_component: None,
                     }),
                 ]),
                 component_balances: HashMap::from([
@@ -2676,6 +2682,7 @@ mod test_serial_db {
                         change: ChangeType::Deletion,
                         creation_tx: Bytes::from_str("0x0000000000000000000000000000000000000000000000000000000000009c41").unwrap(),
                         created_at: NaiveDateTime::from_timestamp_opt(base_ts + 4000, 0).unwrap(),
+                        linked_component: None,
                     }),
                 ]),
                 component_balances: HashMap::from([
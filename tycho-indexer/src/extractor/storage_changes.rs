@@ -0,0 +1,187 @@
+//! # Substreams storage changes
+//!
+//! Decodes the storage changes emitted by our Substreams modules into account deltas.
+//!
+//! The payload is a sequence of length-delimited `tycho.evm.v1.ContractChange` messages, each
+//! decoded exactly like the contract changes of a block, see
+//! [`TryFromMessage`](crate::extractor::protobuf_deserialisation::TryFromMessage). An empty
+//! `balance` or `code` means the field did not change.
+//!
+//! Changes of the same account are merged in the order they appear in, so later changes
+//! overwrite balances, code and slots of earlier ones.
+use std::collections::{hash_map::Entry, HashMap};
+
+use prost::Message;
+use tycho_core::models::{contract::AccountDelta, Address, Chain};
+
+use crate::{
+    extractor::{protobuf_deserialisation::TryFromMessage, ExtractionError},
+    pb::tycho::evm::v1 as substreams,
+};
+
+/// Decodes Substreams storage changes into one delta per changed account.
+///
+/// Deltas are returned in the order their accounts first appear in `raw`. A change that only
+/// touches the balance yields a delta with empty slots.
+pub fn decode_storage_changes(
+    mut raw: &[u8],
+    chain: Chain,
+) -> Result<Vec<AccountDelta>, ExtractionError> {
+    let mut deltas: Vec<AccountDelta> = Vec::new();
+    let mut positions: HashMap<Address, usize> = HashMap::new();
+
+    while !raw.is_empty() {
+        let msg = substreams::ContractChange::decode_length_delimited(&mut raw)?;
+        let delta = decode_contract_change(msg, chain)?;
+
+        match positions.entry(delta.address.clone()) {
+            Entry::Occupied(e) => deltas[*e.get()]
+                .merge(delta)
+                .map_err(ExtractionError::MergeError)?,
+            Entry::Vacant(e) => {
+                e.insert(deltas.len());
+                deltas.push(delta);
+            }
+        }
+    }
+    Ok(deltas)
+}
+
+fn decode_contract_change(
+    msg: substreams::ContractChange,
+    chain: Chain,
+) -> Result<AccountDelta, ExtractionError> {
+    if msg.change() == substreams::ChangeType::Unspecified {
+        return Err(ExtractionError::DecodeError(format!(
+            "Unspecified change type for account 0x{}",
+            hex::encode(&msg.address)
+        )));
+    }
+    AccountDelta::try_from_message((msg, chain))
+}
+
+#[cfg(test)]
+mod test {
+    use tycho_core::{models::ChangeType, Bytes};
+
+    use super::*;
+
+    const ADDRESS_A: [u8; 20] = [0xaa; 20];
+    const ADDRESS_B: [u8; 20] = [0xbb; 20];
+
+    fn word(value: u8) -> Vec<u8> {
+        let mut word = vec![0; 32];
+        word[31] = value;
+        word
+    }
+
+    fn record(
+        address: &[u8],
+        change: substreams::ChangeType,
+        balance: Option<u8>,
+        slot: Option<(u8, Option<u8>)>,
+    ) -> Vec<u8> {
+        substreams::ContractChange {
+            address: address.to_vec(),
+            balance: balance.map(word).unwrap_or_default(),
+            code: vec![],
+            slots: slot
+                .into_iter()
+                .map(|(key, value)| substreams::ContractSlot {
+                    slot: word(key),
+                    value: value.map(word).unwrap_or_default(),
+                })
+                .collect(),
+            change: change.into(),
+            token_balances: vec![],
+        }
+        .encode_length_delimited_to_vec()
+    }
+
+    #[test]
+    fn test_decode_storage_changes() {
+        let raw = [
+            record(&ADDRESS_A, substreams::ChangeType::Creation, None, Some((1, Some(10)))),
+            record(&ADDRESS_B, substreams::ChangeType::Update, Some(100), None),
+            record(&ADDRESS_A, substreams::ChangeType::Update, Some(5), Some((1, Some(11)))),
+            record(&ADDRESS_A, substreams::ChangeType::Update, None, Some((2, Some(20)))),
+        ]
+        .concat();
+
+        let res = decode_storage_changes(&raw, Chain::Ethereum).unwrap();
+
+        assert_eq!(
+            res,
+            vec![
+                AccountDelta::new(
+                    Chain::Ethereum,
+                    Bytes::from(ADDRESS_A.to_vec()),
+                    [
+                        (Bytes::from(word(1)), Some(Bytes::from(word(11)))),
+                        (Bytes::from(word(2)), Some(Bytes::from(word(20)))),
+                    ]
+                    .into(),
+                    Some(Bytes::from(word(5))),
+                    None,
+                    ChangeType::Creation,
+                ),
+                AccountDelta::new(
+                    Chain::Ethereum,
+                    Bytes::from(ADDRESS_B.to_vec()),
+                    HashMap::new(),
+                    Some(Bytes::from(word(100))),
+                    None,
+                    ChangeType::Update,
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_decode_storage_changes_deleted_slot() {
+        let raw = [
+            record(&ADDRESS_A, substreams::ChangeType::Update, None, Some((1, Some(10)))),
+            record(&ADDRESS_A, substreams::ChangeType::Update, None, Some((1, None))),
+        ]
+        .concat();
+
+        let res = decode_storage_changes(&raw, Chain::Ethereum).unwrap();
+
+        assert_eq!(res.len(), 1);
+        // same as `AccountDelta::try_from_message`, the empty value is kept
+        assert_eq!(res[0].slots, [(Bytes::from(word(1)), Some(Bytes::new()))].into());
+    }
+
+    #[test]
+    fn test_decode_storage_changes_deletion() {
+        let raw = [
+            record(&ADDRESS_A, substreams::ChangeType::Update, None, Some((1, Some(10)))),
+            record(&ADDRESS_A, substreams::ChangeType::Deletion, None, None),
+        ]
+        .concat();
+
+        let res = decode_storage_changes(&raw, Chain::Ethereum).unwrap();
+
+        assert_eq!(res.len(), 1);
+        assert_eq!(res[0].change, ChangeType::Deletion);
+    }
+
+    #[test]
+    fn test_decode_storage_changes_truncated() {
+        let mut raw = record(&ADDRESS_A, substreams::ChangeType::Update, None, Some((1, Some(10))));
+        raw.truncate(raw.len() - 1);
+
+        let res = decode_storage_changes(&raw, Chain::Ethereum);
+
+        assert!(matches!(res, Err(ExtractionError::ProtobufError(_))));
+    }
+
+    #[test]
+    fn test_decode_storage_changes_unspecified_change() {
+        let raw = record(&ADDRESS_A, substreams::ChangeType::Unspecified, Some(1), None);
+
+        let res = decode_storage_changes(&raw, Chain::Ethereum);
+
+        assert!(matches!(res, Err(ExtractionError::DecodeError(_))));
+    }
+}
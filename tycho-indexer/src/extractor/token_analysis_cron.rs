@@ -80,7 +80,7 @@ async fn analyze_batch(
         .map(|(cid, _)| cid.as_str())
         .collect::<Vec<_>>();
     let components = gw
-        .get_protocol_components(&chain, None, Some(&component_ids), None, None)
+        .get_protocol_components(&chain, None, Some(&component_ids), None, None, None)
         .await?
         .entity
         .into_iter()
@@ -271,7 +271,7 @@ mod test {
                 })
             });
         gw.expect_get_protocol_components()
-            .returning(|_, _, _, _, _| {
+            .returning(|_, _, _, _, _, _| {
                 Box::pin(async move {
                     Ok(WithTotal {
                         entity: vec![ProtocolComponent::new(
@@ -315,6 +315,42 @@ pub struct BlockEntityChanges {
     #[prost(message, repeated, tag="2")]
     pub changes: ::prost::alloc::vec::Vec<TransactionEntityChanges>,
 }
+// This file contains proto definitions used to forward account updates to other services.
+
+/// A change to a single storage slot.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct SlotUpdate {
+    /// The 32 byte storage slot.
+    #[prost(bytes="vec", tag="1")]
+    pub slot: ::prost::alloc::vec::Vec<u8>,
+    /// The new 32 byte value of the slot, unset if the slot was deleted.
+    #[prost(bytes="vec", optional, tag="2")]
+    pub value: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+}
+/// Changes made to a single account's state.
+#[allow(clippy::derive_partial_eq_without_eq)]
+#[derive(Clone, PartialEq, ::prost::Message)]
+pub struct AccountUpdate {
+    /// The chain the account lives on, e.g. "ethereum".
+    #[prost(string, tag="1")]
+    pub chain: ::prost::alloc::string::String,
+    /// The 20 byte address of the account.
+    #[prost(bytes="vec", tag="2")]
+    pub address: ::prost::alloc::vec::Vec<u8>,
+    /// The changes to this account's slots.
+    #[prost(message, repeated, tag="3")]
+    pub slots: ::prost::alloc::vec::Vec<SlotUpdate>,
+    /// The new balance of the account, unset indicates no change.
+    #[prost(bytes="vec", optional, tag="4")]
+    pub balance: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// The new code of the account, unset indicates no change.
+    #[prost(bytes="vec", optional, tag="5")]
+    pub code: ::core::option::Option<::prost::alloc::vec::Vec<u8>>,
+    /// Whether this is an update, a creation or a deletion.
+    #[prost(enumeration="ChangeType", tag="6")]
+    pub change: i32,
+}
 // WARNING: DEPRECATED. Please use common.proto's TransactionChanges and BlockChanges instead.
 // This file contains proto definitions specific to the VM integration.
 
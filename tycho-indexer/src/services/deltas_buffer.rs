@@ -544,6 +544,7 @@ mod test {
                     change: ChangeType::Creation,
                     creation_tx: Bytes::new(),
                     created_at: "2020-01-01T00:00:00".parse().unwrap(),
+                    linked_component: None,
                 },
             )]
             .into_iter()
@@ -611,6 +612,7 @@ mod test {
                         change: ChangeType::Creation,
                         creation_tx: Bytes::new(),
                         created_at: "2020-01-01T00:00:00".parse().unwrap(),
+                        linked_component: None,
                     },
                 ),
                 (
@@ -626,6 +628,7 @@ mod test {
                         change: ChangeType::Creation,
                         creation_tx: Bytes::new(),
                         created_at: "2020-01-01T00:00:00".parse().unwrap(),
+                        linked_component: None,
                     },
                 ),
             ]
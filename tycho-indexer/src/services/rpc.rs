@@ -656,6 +656,7 @@ where
                 Some(system),
                 ids_slice,
                 request.tvl_gt,
+                None,
                 Some(&pagination_params),
             )
             .await
@@ -1373,7 +1374,7 @@ mod tests {
             .clone_from(&unsorted_tokens);
         let mock_response = Ok(WithTotal { entity: vec![mock_res], total: Some(1) });
         gw.expect_get_protocol_components()
-            .return_once(|_, _, _, _, _| Box::pin(async move { mock_response }));
+            .return_once(|_, _, _, _, _, _| Box::pin(async move { mock_response }));
 
         let mut mock_buffer = MockPendingDeltas::new();
         let buf_expected = ProtocolComponent::new(
@@ -1441,7 +1442,7 @@ mod tests {
             .returning({
                 let mock_response: Result<(i64, Vec<ProtocolComponent>), StorageError> =
                     Ok((1, vec![expected.clone()]));
-                move |_, _, _, _, _| {
+                move |_, _, _, _, _, _| {
                     let mock_response_clone = match &mock_response {
                         Ok((num, components)) => {
                             Ok(WithTotal { entity: components.clone(), total: Some(*num) })
@@ -160,6 +160,7 @@ mock! {
             system: Option<String>,
             ids: Option<&'life2 [&'life3 str]>,
             min_tvl: Option<f64>,
+            created_after: Option<NaiveDateTime>,
             pagination_params: Option<&'life4 PaginationParams>,
         ) -> ::core::pin::Pin<
             Box<
@@ -0,0 +1,60 @@
+//! Benchmarks the in-memory part of the slot delta retrieval: turning the raw rows returned by
+//! Postgres into per contract stores.
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, BenchmarkId, Criterion};
+use tycho_core::{
+    models::{StoreKey, StoreVal},
+    Bytes,
+};
+use tycho_storage::postgres::group_slot_rows;
+
+const CONTRACTS: i64 = 100;
+
+/// Builds `n` rows spread evenly over [`CONTRACTS`] contracts, as raw column values.
+///
+/// Rows are ordered by contract, like the slot delta queries return them.
+fn raw_rows(n: usize) -> Vec<(i64, Vec<u8>, Option<Vec<u8>>)> {
+    (0..n)
+        .map(|i| {
+            let word = |v: usize| {
+                let mut buf = vec![0u8; 32];
+                buf[24..].copy_from_slice(&(v as u64).to_be_bytes());
+                buf
+            };
+            let value = if i % 10 == 0 { None } else { Some(word(i * 7)) };
+            (i as i64 * CONTRACTS / n as i64, word(i), value)
+        })
+        .collect()
+}
+
+/// Decodes raw rows the same way the Postgres `Bytea` deserialization does.
+fn decode_rows(
+    rows: Vec<(i64, Vec<u8>, Option<Vec<u8>>)>,
+) -> Vec<(i64, StoreKey, Option<StoreVal>)> {
+    rows.into_iter()
+        .map(|(cid, key, value)| (cid, Bytes::from(key), value.map(Bytes::from)))
+        .collect()
+}
+
+fn bench_slots_delta(c: &mut Criterion) {
+    let mut group = c.benchmark_group("slots_delta");
+    for n in [1_000, 10_000, 100_000] {
+        group.bench_with_input(BenchmarkId::new("decode", n), &n, |b, &n| {
+            b.iter_batched(
+                || raw_rows(n),
+                |rows| black_box(decode_rows(rows)),
+                BatchSize::LargeInput,
+            )
+        });
+        group.bench_with_input(BenchmarkId::new("decode_and_group", n), &n, |b, &n| {
+            b.iter_batched(
+                || raw_rows(n),
+                |rows| black_box(group_slot_rows(decode_rows(rows))),
+                BatchSize::LargeInput,
+            )
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_slots_delta);
+criterion_main!(benches);
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS update_modtime_cursor ON "cursor";
+
+DROP TABLE IF EXISTS "cursor";
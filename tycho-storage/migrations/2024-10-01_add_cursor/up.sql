@@ -0,0 +1,22 @@
+-- Last processed block per chain and protocol system. Lets indexers resume
+--	where they stopped after a restart.
+CREATE TABLE IF NOT EXISTS "cursor"(
+    "id" bigserial PRIMARY KEY,
+    -- Cursors are scoped to a specific chain.
+    "chain_id" bigint REFERENCES "chain"(id) NOT NULL,
+    -- The protocol system whose progress is tracked.
+    "protocol_system_id" bigint REFERENCES protocol_system(id) ON DELETE CASCADE NOT NULL,
+    -- The last fully processed block. Reverting this block removes the cursor.
+    "block_id" bigint REFERENCES block(id) ON DELETE CASCADE NOT NULL,
+    -- Timestamp this entry was inserted into this table.
+    "inserted_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Timestamp this entry was last modified.
+    "modified_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- only allow a single cursor per chain and protocol system.
+    UNIQUE (chain_id, protocol_system_id)
+);
+
+CREATE TRIGGER update_modtime_cursor
+    BEFORE UPDATE ON "cursor"
+    FOR EACH ROW
+    EXECUTE PROCEDURE update_modified_column();
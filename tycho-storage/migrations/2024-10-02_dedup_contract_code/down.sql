@@ -0,0 +1,19 @@
+DROP INDEX IF EXISTS idx_contract_code_hash;
+
+ALTER TABLE contract_code
+    DROP CONSTRAINT IF EXISTS contract_code_hash_fkey,
+    ADD COLUMN code bytea;
+
+UPDATE
+    contract_code cc
+SET
+    code = c.bytecode
+FROM
+    "code" c
+WHERE
+    c.hash = cc.hash;
+
+ALTER TABLE contract_code
+    ALTER COLUMN code SET NOT NULL;
+
+DROP TABLE IF EXISTS "code";
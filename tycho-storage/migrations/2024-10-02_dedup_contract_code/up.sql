@@ -0,0 +1,49 @@
+-- Contract code addressed by its hash. Many contracts share the exact same
+--	bytecode (e.g. pools deployed by a factory), so each distinct code is
+--	stored only once and referenced from contract_code.
+CREATE TABLE IF NOT EXISTS "code"(
+    -- The keccak256 hash of the code.
+    "hash" bytea PRIMARY KEY,
+    -- The code optimised for the system using it, e.g. revm.
+    "bytecode" bytea NOT NULL,
+    -- Timestamp this entry was inserted into this table.
+    "inserted_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Stored hashes were provided by callers and may not match the code. A hash shared by differing
+--	codes can't be deduplicated without losing one of them, so the migration aborts instead.
+DO $$
+DECLARE
+    conflicting_hash bytea;
+BEGIN
+    SELECT
+        hash INTO conflicting_hash
+    FROM
+        contract_code
+    GROUP BY
+        hash
+    HAVING
+        COUNT(DISTINCT code) > 1
+    LIMIT 1;
+    IF FOUND THEN
+        RAISE EXCEPTION 'contract_code hash 0x% is shared by differing codes',
+            encode(conflicting_hash, 'hex');
+    END IF;
+END
+$$;
+
+-- Rows sharing a hash are now guaranteed to hold the same code.
+INSERT INTO "code"(hash, bytecode)
+SELECT DISTINCT ON (hash)
+    hash,
+    code
+FROM
+    contract_code
+ON CONFLICT (hash)
+    DO NOTHING;
+
+ALTER TABLE contract_code
+    DROP COLUMN code,
+    ADD CONSTRAINT contract_code_hash_fkey FOREIGN KEY (hash) REFERENCES "code"(hash);
+
+CREATE INDEX IF NOT EXISTS idx_contract_code_hash ON contract_code(hash);
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS update_modtime_protocol_component_link ON protocol_component_link;
+
+DROP TABLE IF EXISTS protocol_component_link;
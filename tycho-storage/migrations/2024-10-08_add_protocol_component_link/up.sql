@@ -0,0 +1,20 @@
+-- Links a protocol component to its counterpart on another chain, e.g. the
+--	legs of a bridge. A component references at most one counterpart.
+CREATE TABLE IF NOT EXISTS protocol_component_link(
+    -- The component holding the reference.
+    "protocol_component_id" bigint REFERENCES protocol_component(id) ON DELETE CASCADE PRIMARY KEY,
+    -- The counterpart component, usually on a different chain.
+    "linked_component_id" bigint REFERENCES protocol_component(id) ON DELETE CASCADE NOT NULL,
+    -- Timestamp this entry was inserted into this table.
+    "inserted_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Timestamp this entry was last modified.
+    "modified_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+CREATE INDEX IF NOT EXISTS idx_protocol_component_link_linked_component_id ON
+    protocol_component_link(linked_component_id);
+
+CREATE TRIGGER update_modtime_protocol_component_link
+    BEFORE UPDATE ON protocol_component_link
+    FOR EACH ROW
+    EXECUTE PROCEDURE update_modified_column();
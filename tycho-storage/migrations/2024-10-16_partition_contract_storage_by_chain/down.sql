@@ -0,0 +1,81 @@
+-- This file should undo anything in `up.sql`
+ALTER TABLE contract_storage RENAME TO contract_storage_chain;
+
+ALTER TABLE contract_storage_default RENAME TO contract_storage_chain_default;
+
+ALTER INDEX idx_contract_storage_account_id RENAME TO idx_contract_storage_chain_account_id;
+
+ALTER INDEX idx_contract_storage_account_id_slot_valid_to RENAME TO
+    idx_contract_storage_chain_account_id_slot_valid_to;
+
+ALTER INDEX idx_contract_storage_modify_tx RENAME TO idx_contract_storage_chain_modify_tx;
+
+DELETE FROM partman.part_config_sub
+WHERE sub_parent = 'public.contract_storage';
+
+DELETE FROM partman.part_config
+WHERE parent_table LIKE 'public.contract_storage%';
+
+DROP TABLE IF EXISTS partman.template_public_contract_storage;
+
+DROP TABLE IF EXISTS partman.template_public_contract_storage_chain;
+
+CREATE TABLE contract_storage(
+    slot bytea NOT NULL,
+    value bytea NULL,
+    previous_value bytea NULL,
+    account_id int8 NOT NULL,
+    modify_tx int8 NOT NULL,
+    ordinal int8 NOT NULL,
+    valid_from timestamptz NOT NULL,
+    valid_to timestamptz NOT NULL,
+    inserted_ts timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    modified_ts timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+)
+PARTITION BY RANGE (valid_to);
+
+SELECT
+    partman.create_parent(p_parent_table := 'public.contract_storage', p_control :=
+	'valid_to', p_interval := '1 day', p_type := 'range', p_premake := 7,
+	p_start_partition := (CURRENT_DATE - INTERVAL '1 month')::text,
+	p_default_table := TRUE, p_automatic_maintenance := 'on');
+
+UPDATE partman.part_config
+SET retention = '1 month',
+    retention_keep_table = false
+WHERE parent_table = 'public.contract_storage';
+
+INSERT INTO contract_storage
+SELECT
+    slot,
+    value,
+    previous_value,
+    account_id,
+    modify_tx,
+    ordinal,
+    valid_from,
+    valid_to,
+    inserted_ts,
+    modified_ts
+FROM
+    contract_storage_chain;
+
+DROP TABLE contract_storage_chain;
+
+CREATE INDEX idx_contract_storage_account_id ON public.contract_storage USING btree(account_id);
+
+CREATE INDEX idx_contract_storage_account_id_slot_valid_to ON public.contract_storage
+    USING btree(account_id, slot, valid_to);
+
+CREATE INDEX idx_contract_storage_modify_tx ON public.contract_storage USING btree(modify_tx);
+
+ALTER TABLE public.contract_storage
+    ADD CONSTRAINT contract_storage_account_id_fkey FOREIGN KEY (account_id) REFERENCES
+	account(id) ON DELETE CASCADE;
+
+ALTER TABLE public.contract_storage
+    ADD CONSTRAINT contract_storage_modify_tx_fkey FOREIGN KEY (modify_tx) REFERENCES
+	"transaction"(id) ON DELETE CASCADE;
+
+ALTER TABLE contract_storage_default
+    ADD CONSTRAINT contract_storage_default_unique_pk UNIQUE (account_id, slot);
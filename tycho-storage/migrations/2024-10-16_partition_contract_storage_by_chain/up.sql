@@ -0,0 +1,134 @@
+-- Partition contract_storage by chain. Each chain gets its own partition which is in turn
+--	partitioned by valid_to, exactly like contract_storage was before, so storage queries
+--	of one chain only ever scan that chain's partitions.
+ALTER TABLE contract_storage RENAME TO contract_storage_old;
+
+-- The new table gets default partitions with the same names.
+ALTER TABLE contract_storage_default RENAME TO contract_storage_old_default;
+
+-- Stop pg_partman from maintaining the old table, it is dropped once its rows are moved.
+DELETE FROM partman.part_config
+WHERE parent_table = 'public.contract_storage';
+
+DROP TABLE IF EXISTS partman.template_public_contract_storage;
+
+CREATE TABLE contract_storage(
+    slot bytea NOT NULL,
+    value bytea NULL,
+    previous_value bytea NULL,
+    account_id int8 NOT NULL,
+    -- The chain of the account, copied from account.chain_id. Used as partition key.
+    chain_id int8 NOT NULL,
+    modify_tx int8 NOT NULL,
+    ordinal int8 NOT NULL,
+    valid_from timestamptz NOT NULL,
+    valid_to timestamptz NOT NULL,
+    inserted_ts timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    modified_ts timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP
+)
+PARTITION BY LIST (chain_id);
+
+-- One partition per chain id, rows of chains without a partition end up in the default
+--	partition.
+SELECT
+    partman.create_parent(p_parent_table := 'public.contract_storage', p_control :=
+	'chain_id', p_interval := '1', p_type := 'list', p_premake := 10,
+	p_start_partition := '1', p_default_table := TRUE, p_automatic_maintenance := 'on');
+
+-- The current version of a slot has to be unique. Unique indexes can't be put on the parent
+--	table without the partition keys, so pg_partman puts them on each child through the
+--	template table. Only current versions are covered, daily partitions keep many versions
+--	of the same slot.
+CREATE UNIQUE INDEX template_public_contract_storage_unique_latest ON
+    partman.template_public_contract_storage(account_id, slot)
+WHERE
+    valid_to = '262142-12-31 23:59:59.999999+00';
+
+CREATE TABLE partman.template_public_contract_storage_chain(
+    LIKE public.contract_storage
+);
+
+CREATE UNIQUE INDEX template_public_contract_storage_chain_unique_latest ON
+    partman.template_public_contract_storage_chain(account_id, slot)
+WHERE
+    valid_to = '262142-12-31 23:59:59.999999+00';
+
+-- Each chain partition is partitioned by valid_to, the current version of a slot ends up in
+--	the default sub-partition of its chain.
+SELECT
+    partman.create_sub_parent(p_top_parent := 'public.contract_storage', p_control :=
+	'valid_to', p_interval := '1 day', p_type := 'range', p_premake := 7,
+	p_start_partition := (CURRENT_DATE - INTERVAL '1 month')::text,
+	p_default_table := TRUE, p_declarative_check := 'yes', p_template_table :=
+	'partman.template_public_contract_storage_chain');
+
+-- Tables created by the calls above may predate their template's indexes, so the default
+--	partitions get theirs explicitly. Default partitions of chains added later get them from
+--	the template.
+DO $$
+DECLARE
+    v_default text;
+BEGIN
+    FOR v_default IN
+    SELECT
+        'contract_storage_default'
+    UNION ALL
+    SELECT
+        partition_tablename || '_default'
+    FROM
+        partman.show_partitions('public.contract_storage') LOOP
+            EXECUTE format('CREATE UNIQUE INDEX IF NOT EXISTS %I ON public.%I (account_id, slot)
+		    WHERE valid_to = %L', v_default || '_unique_latest', v_default,
+		    '262142-12-31 23:59:59.999999+00');
+        END LOOP;
+END
+$$;
+
+UPDATE partman.part_config_sub
+SET sub_retention = '1 month',
+    sub_retention_keep_table = false
+WHERE sub_parent = 'public.contract_storage';
+
+UPDATE partman.part_config
+SET retention = '1 month',
+    retention_keep_table = false
+WHERE parent_table LIKE 'public.contract_storage_p%';
+
+INSERT INTO contract_storage(slot, value, previous_value, account_id, chain_id, modify_tx,
+    ordinal, valid_from, valid_to, inserted_ts, modified_ts)
+SELECT
+    contract_storage_old.slot,
+    contract_storage_old.value,
+    contract_storage_old.previous_value,
+    contract_storage_old.account_id,
+    account.chain_id,
+    contract_storage_old.modify_tx,
+    contract_storage_old.ordinal,
+    contract_storage_old.valid_from,
+    contract_storage_old.valid_to,
+    contract_storage_old.inserted_ts,
+    contract_storage_old.modified_ts
+FROM
+    contract_storage_old
+    JOIN account ON account.id = contract_storage_old.account_id;
+
+DROP TABLE contract_storage_old;
+
+CREATE INDEX idx_contract_storage_account_id ON public.contract_storage USING btree(account_id);
+
+CREATE INDEX idx_contract_storage_account_id_slot_valid_to ON public.contract_storage
+    USING btree(account_id, slot, valid_to);
+
+CREATE INDEX idx_contract_storage_modify_tx ON public.contract_storage USING btree(modify_tx);
+
+ALTER TABLE public.contract_storage
+    ADD CONSTRAINT contract_storage_account_id_fkey FOREIGN KEY (account_id) REFERENCES
+	account(id) ON DELETE CASCADE;
+
+ALTER TABLE public.contract_storage
+    ADD CONSTRAINT contract_storage_chain_id_fkey FOREIGN KEY (chain_id) REFERENCES
+	chain(id);
+
+ALTER TABLE public.contract_storage
+    ADD CONSTRAINT contract_storage_modify_tx_fkey FOREIGN KEY (modify_tx) REFERENCES
+	"transaction"(id) ON DELETE CASCADE;
@@ -0,0 +1,3 @@
+DROP TRIGGER IF EXISTS update_modtime_contract_storage_current ON contract_storage_current;
+
+DROP TABLE IF EXISTS contract_storage_current;
@@ -0,0 +1,51 @@
+-- The current value of every live contract storage slot. Kept up to date by the slot
+--	writer next to the versioned contract_storage table, so current state can be read
+--	without reconstructing it from versions. Deleted slots have no row.
+CREATE TABLE IF NOT EXISTS contract_storage_current(
+    -- The account the slot belongs to.
+    "account_id" bigint REFERENCES account(id) ON DELETE CASCADE NOT NULL,
+    -- The chain of the account, copied from account.chain_id.
+    "chain_id" bigint REFERENCES chain(id) NOT NULL,
+    -- The storage slot.
+    "slot" bytea NOT NULL,
+    -- The current value of the slot.
+    "value" bytea NOT NULL,
+    -- The transaction that set the current value.
+    "modify_tx" bigint REFERENCES "transaction"(id) ON DELETE CASCADE NOT NULL,
+    -- The index of modify_tx within its block.
+    "ordinal" bigint NOT NULL,
+    -- The timestamp of the block that set the current value.
+    "valid_from" timestamptz NOT NULL,
+    -- Timestamp this entry was inserted into this table.
+    "inserted_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    -- Timestamp this entry was last modified.
+    "modified_ts" timestamptz NOT NULL DEFAULT CURRENT_TIMESTAMP,
+    PRIMARY KEY (account_id, slot)
+);
+
+CREATE INDEX IF NOT EXISTS idx_contract_storage_current_chain_id ON
+    contract_storage_current(chain_id);
+
+CREATE INDEX IF NOT EXISTS idx_contract_storage_current_modify_tx ON
+    contract_storage_current(modify_tx);
+
+CREATE TRIGGER update_modtime_contract_storage_current
+    BEFORE UPDATE ON contract_storage_current
+    FOR EACH ROW
+    EXECUTE PROCEDURE update_modified_column();
+
+INSERT INTO contract_storage_current(account_id, chain_id, slot, value, modify_tx, ordinal,
+    valid_from)
+SELECT
+    account_id,
+    chain_id,
+    slot,
+    value,
+    modify_tx,
+    ordinal,
+    valid_from
+FROM
+    contract_storage
+WHERE
+    valid_to = '262142-12-31 23:59:59.999999+00'
+    AND value IS NOT NULL;
@@ -3,4 +3,6 @@
 extern crate pretty_assertions;
 #[macro_use]
 extern crate lazy_static;
+#[cfg(feature = "testing")]
+pub mod memory;
 pub mod postgres;
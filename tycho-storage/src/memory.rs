@@ -0,0 +1,313 @@
+//! # In-memory storage for tests
+//!
+//! A `HashMap` backed stand-in for the postgres gateway, meant for downstream crates that want
+//! to exercise logic such as reorg handling without a live database.
+//!
+//! Only the read paths are mirrored and versioning is simplified: each slot change is recorded
+//! with the timestamp of the block it happened in, changes within the same block are applied in
+//! insertion order. Deltas honour the direction between the start and target version just like
+//! the postgres gateway does.
+//!
+//! Like the postgres gateway, blocks carry a canonical flag. Block numbers resolve to the
+//! canonical block at that height, and [`InMemoryGateway::revert_to_block`] marks every later
+//! block as non canonical and drops their slot changes.
+use std::collections::HashMap;
+
+use chrono::NaiveDateTime;
+use tycho_core::{
+    models::{
+        blockchain::Block, AccountToContractStore, Address, BlockHash, Chain, ContractStore,
+        StoreKey, StoreVal,
+    },
+    storage::{BlockIdentifier, BlockOrTimestamp, StorageError},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+struct SlotChange {
+    ts: NaiveDateTime,
+    slot: StoreKey,
+    value: Option<StoreVal>,
+}
+
+#[derive(Debug, Clone)]
+struct StoredBlock {
+    block: Block,
+    main: bool,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct InMemoryGateway {
+    blocks: HashMap<BlockHash, StoredBlock>,
+    slot_changes: HashMap<(Chain, Address), Vec<SlotChange>>,
+}
+
+impl InMemoryGateway {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Stores a block as canonical, replacing any block with the same hash.
+    ///
+    /// A canonical block of the same chain at the same height is marked as non canonical.
+    pub fn upsert_block(&mut self, block: Block) {
+        for stored in self.blocks.values_mut() {
+            if stored.block.chain == block.chain && stored.block.number == block.number {
+                stored.main = false;
+            }
+        }
+        self.blocks
+            .insert(block.hash.clone(), StoredBlock { block, main: true });
+    }
+
+    /// Retrieves a block.
+    ///
+    /// Hashes resolve to any stored block, numbers and `Latest` only to canonical blocks.
+    pub fn get_block(&self, id: &BlockIdentifier) -> Result<Block, StorageError> {
+        let block = match id {
+            BlockIdentifier::Hash(hash) => self.blocks.get(hash),
+            BlockIdentifier::Number((chain, number)) => self
+                .canonical_blocks(chain)
+                .find(|b| b.block.number as i64 == *number),
+            BlockIdentifier::Latest(chain) => self
+                .canonical_blocks(chain)
+                .max_by_key(|b| (b.block.number, b.block.ts)),
+        };
+        block
+            .map(|b| b.block.clone())
+            .ok_or_else(|| StorageError::NotFound("Block".to_string(), id.to_string()))
+    }
+
+    /// Reverts the chain of `to` back to it.
+    ///
+    /// Every block of the chain above `to` is marked as non canonical and all slot changes of
+    /// the chain after the timestamp of `to` are dropped. Returns `NotFound` if `to` is not
+    /// stored.
+    pub fn revert_to_block(&mut self, to: &BlockIdentifier) -> Result<(), StorageError> {
+        let target = self.get_block(to)?;
+        for stored in self.blocks.values_mut() {
+            if stored.block.chain == target.chain && stored.block.number > target.number {
+                stored.main = false;
+            }
+        }
+        for ((chain, _), changes) in self.slot_changes.iter_mut() {
+            if *chain == target.chain {
+                changes.retain(|c| c.ts <= target.ts);
+            }
+        }
+        Ok(())
+    }
+
+    fn canonical_blocks<'a>(&'a self, chain: &'a Chain) -> impl Iterator<Item = &'a StoredBlock> {
+        self.blocks
+            .values()
+            .filter(move |b| b.main && b.block.chain == *chain)
+    }
+
+    /// Records slot changes of a contract that happened in `block`.
+    ///
+    /// A `None` value marks the slot as deleted.
+    pub fn upsert_slots(
+        &mut self,
+        block: &Block,
+        address: &Address,
+        slots: ContractStore,
+    ) -> Result<(), StorageError> {
+        let block = self.get_block(&BlockIdentifier::Hash(block.hash.clone()))?;
+        let changes = self
+            .slot_changes
+            .entry((block.chain, address.clone()))
+            .or_default();
+        changes.extend(
+            slots
+                .into_iter()
+                .map(|(slot, value)| SlotChange { ts: block.ts, slot, value }),
+        );
+        // stable sort keeps insertion order for changes within the same block
+        changes.sort_by_key(|c| c.ts);
+        Ok(())
+    }
+
+    /// Retrieves the slot updates needed to move from `start_version` to `target_version`.
+    ///
+    /// Going forward, the latest value within the range is returned for each changed slot.
+    /// Going backward, the value each changed slot had at the target version is returned,
+    /// `None` if the slot did not exist yet.
+    pub fn get_slots_delta(
+        &self,
+        chain: &Chain,
+        start_version: &BlockOrTimestamp,
+        target_version: &BlockOrTimestamp,
+    ) -> Result<AccountToContractStore, StorageError> {
+        let start_ts = self.version_ts(start_version)?;
+        let target_ts = self.version_ts(target_version)?;
+        let (lower, upper) =
+            if start_ts <= target_ts { (start_ts, target_ts) } else { (target_ts, start_ts) };
+
+        let mut result = AccountToContractStore::new();
+        for ((change_chain, address), changes) in self.slot_changes.iter() {
+            if change_chain != chain {
+                continue;
+            }
+            let store: ContractStore = changes
+                .iter()
+                .filter(|c| c.ts > lower && c.ts <= upper)
+                .map(|c| {
+                    let value = if start_ts <= target_ts {
+                        latest_value(changes, &c.slot, &upper)
+                    } else {
+                        latest_value(changes, &c.slot, &lower)
+                    };
+                    (c.slot.clone(), value)
+                })
+                .collect();
+            if !store.is_empty() {
+                result.insert(address.clone(), store);
+            }
+        }
+        Ok(result)
+    }
+
+    fn version_ts(&self, version: &BlockOrTimestamp) -> Result<NaiveDateTime, StorageError> {
+        match version {
+            BlockOrTimestamp::Block(id) => Ok(self.get_block(id)?.ts),
+            BlockOrTimestamp::Timestamp(ts) => Ok(*ts),
+        }
+    }
+}
+
+/// Returns the value of `slot` as of `ts`, `None` if it was never set up to then.
+fn latest_value(changes: &[SlotChange], slot: &StoreKey, ts: &NaiveDateTime) -> Option<StoreVal> {
+    changes
+        .iter()
+        .rev()
+        .find(|c| &c.slot == slot && &c.ts <= ts)
+        .and_then(|c| c.value.clone())
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use tycho_core::Bytes;
+
+    use super::*;
+
+    fn block(number: u64) -> Block {
+        Block::new(
+            number,
+            Chain::Ethereum,
+            Bytes::from(number).lpad(32, 0),
+            Bytes::from(number - 1).lpad(32, 0),
+            NaiveDateTime::default() + Duration::from_secs(12 * number),
+        )
+    }
+
+    fn store(data: &[(u8, Option<u8>)]) -> ContractStore {
+        data.iter()
+            .map(|(k, v)| (Bytes::from(*k).lpad(32, 0), v.map(|v| Bytes::from(v).lpad(32, 0))))
+            .collect()
+    }
+
+    fn setup() -> InMemoryGateway {
+        let mut gw = InMemoryGateway::new();
+        let address = Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F");
+        for number in 1..=3 {
+            gw.upsert_block(block(number));
+        }
+        gw.upsert_slots(&block(1), &address, store(&[(0, Some(1)), (1, Some(5))]))
+            .unwrap();
+        gw.upsert_slots(&block(2), &address, store(&[(0, Some(2)), (2, Some(7))]))
+            .unwrap();
+        gw.upsert_slots(&block(3), &address, store(&[(0, Some(3)), (1, None)]))
+            .unwrap();
+        gw
+    }
+
+    fn version(number: i64) -> BlockOrTimestamp {
+        BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, number)))
+    }
+
+    #[test]
+    fn test_get_block() {
+        let gw = setup();
+
+        assert_eq!(gw.get_block(&BlockIdentifier::Latest(Chain::Ethereum)), Ok(block(3)));
+        assert_eq!(gw.get_block(&BlockIdentifier::Hash(block(2).hash)), Ok(block(2)));
+        assert!(matches!(
+            gw.get_block(&BlockIdentifier::Number((Chain::Ethereum, 4))),
+            Err(StorageError::NotFound(_, _))
+        ));
+    }
+
+    #[test]
+    fn test_get_block_by_number_resolves_canonical_block() {
+        let mut gw = setup();
+        let mut fork = block(3);
+        fork.hash = Bytes::from(33u64).lpad(32, 0);
+
+        gw.upsert_block(fork.clone());
+
+        assert_eq!(gw.get_block(&BlockIdentifier::Number((Chain::Ethereum, 3))), Ok(fork.clone()));
+        assert_eq!(gw.get_block(&BlockIdentifier::Latest(Chain::Ethereum)), Ok(fork));
+        assert_eq!(gw.get_block(&BlockIdentifier::Hash(block(3).hash)), Ok(block(3)));
+    }
+
+    #[test]
+    fn test_revert_to_block() {
+        let mut gw = setup();
+        let address = Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F");
+
+        gw.revert_to_block(&BlockIdentifier::Hash(block(2).hash))
+            .unwrap();
+
+        assert_eq!(gw.get_block(&BlockIdentifier::Latest(Chain::Ethereum)), Ok(block(2)));
+        assert!(matches!(
+            gw.get_block(&BlockIdentifier::Number((Chain::Ethereum, 3))),
+            Err(StorageError::NotFound(_, _))
+        ));
+        assert_eq!(gw.get_block(&BlockIdentifier::Hash(block(3).hash)), Ok(block(3)));
+        let res = gw
+            .get_slots_delta(
+                &Chain::Ethereum,
+                &version(1),
+                &BlockOrTimestamp::Timestamp(block(3).ts),
+            )
+            .unwrap();
+        assert_eq!(res, [(address, store(&[(0, Some(2)), (2, Some(7))]))].into());
+    }
+
+    #[test]
+    fn test_get_slots_delta_forward() {
+        let gw = setup();
+        let address = Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F");
+
+        let res = gw
+            .get_slots_delta(&Chain::Ethereum, &version(1), &version(3))
+            .unwrap();
+
+        assert_eq!(res, [(address, store(&[(0, Some(3)), (1, None), (2, Some(7))]))].into());
+    }
+
+    #[test]
+    fn test_get_slots_delta_backward() {
+        let gw = setup();
+        let address = Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F");
+
+        let res = gw
+            .get_slots_delta(&Chain::Ethereum, &version(3), &version(1))
+            .unwrap();
+
+        assert_eq!(res, [(address, store(&[(0, Some(1)), (1, Some(5)), (2, None)]))].into());
+    }
+
+    #[test]
+    fn test_get_slots_delta_other_chain() {
+        let gw = setup();
+
+        let res = gw
+            .get_slots_delta(&Chain::Arbitrum, &version(1), &version(3))
+            .unwrap();
+
+        assert!(res.is_empty());
+    }
+}
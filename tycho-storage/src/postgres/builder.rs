@@ -1,8 +1,13 @@
 use crate::{
     postgres,
-    postgres::{cache::CachedGateway, PostgresGateway},
+    postgres::{
+        cache::CachedGateway, retry::RetryPolicy, DeltaQueryStrategy, GatewayMetrics,
+        PostgresGateway,
+    },
 };
 use chrono::NaiveDateTime;
+use diesel_async::{pooled_connection::deadpool::Pool, AsyncPgConnection};
+use std::{num::NonZeroUsize, sync::Arc};
 use tokio::{sync::mpsc, task::JoinHandle};
 use tycho_core::{models::Chain, storage::StorageError};
 
@@ -12,6 +17,14 @@ pub struct GatewayBuilder {
     protocol_systems: Vec<String>,
     retention_horizon: NaiveDateTime,
     chains: Vec<Chain>,
+    retry_policy: RetryPolicy,
+    metrics: Option<Arc<dyn GatewayMetrics>>,
+    verify_code_hash: bool,
+    block_ts_cache_size: Option<NonZeroUsize>,
+    delta_query_strategy: DeltaQueryStrategy,
+    auto_register_systems: bool,
+    auto_create_missing_tokens: bool,
+    prepared_statement_cache_size: Option<usize>,
 }
 
 impl GatewayBuilder {
@@ -34,12 +47,87 @@ impl GatewayBuilder {
         self
     }
 
+    pub fn set_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    pub fn set_metrics(mut self, metrics: Arc<dyn GatewayMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Verify the hash of contract code whenever it is read, see
+    /// `PostgresGateway::with_code_hash_verification`.
+    pub fn set_verify_code_hash(mut self, verify: bool) -> Self {
+        self.verify_code_hash = verify;
+        self
+    }
+
+    /// Number of resolved block timestamps the gateway caches, per hash and per number.
+    /// Defaults to `postgres::DEFAULT_BLOCK_TS_CACHE_SIZE`.
+    pub fn set_block_ts_cache_size(mut self, size: NonZeroUsize) -> Self {
+        self.block_ts_cache_size = Some(size);
+        self
+    }
+
+    /// Selects the query used to retrieve slot deltas, defaults to
+    /// `DeltaQueryStrategy::DistinctOn`.
+    pub fn set_delta_query_strategy(mut self, strategy: DeltaQueryStrategy) -> Self {
+        self.delta_query_strategy = strategy;
+        self
+    }
+
+    /// Register unknown protocol systems when writing their components, see
+    /// `PostgresGateway::with_auto_register_systems`.
+    pub fn set_auto_register_systems(mut self, auto_register: bool) -> Self {
+        self.auto_register_systems = auto_register;
+        self
+    }
+
+    /// Store placeholders for unknown tokens when writing components that hold them, see
+    /// `PostgresGateway::with_auto_create_missing_tokens`.
+    pub fn set_auto_create_missing_tokens(mut self, auto_create: bool) -> Self {
+        self.auto_create_missing_tokens = auto_create;
+        self
+    }
+
+    /// Maximum number of prepared statements a pooled connection may hold before it is
+    /// replaced, unbounded by default. See `postgres::build_pool`.
+    pub fn set_prepared_statement_cache_size(mut self, size: usize) -> Self {
+        self.prepared_statement_cache_size = Some(size);
+        self
+    }
+
+    /// Creates the postgres gateway, attaching the configured metrics sink if any.
+    async fn build_inner_gateway(
+        &self,
+        pool: Pool<AsyncPgConnection>,
+    ) -> Result<PostgresGateway, StorageError> {
+        let mut gw = PostgresGateway::new(pool, self.retention_horizon)
+            .await?
+            .with_code_hash_verification(self.verify_code_hash)
+            .with_delta_query_strategy(self.delta_query_strategy)
+            .with_auto_register_systems(self.auto_register_systems)
+            .with_auto_create_missing_tokens(self.auto_create_missing_tokens);
+        if let Some(size) = self.block_ts_cache_size {
+            gw = gw.with_block_ts_cache_size(size);
+        }
+        Ok(match self.metrics.clone() {
+            Some(metrics) => gw.with_metrics(metrics),
+            None => gw,
+        })
+    }
+
     pub async fn build(self) -> Result<(CachedGateway, JoinHandle<()>), StorageError> {
-        let pool = postgres::connect(&self.database_url).await?;
+        let pool =
+            postgres::connect(&self.database_url, self.prepared_statement_cache_size).await?;
         postgres::ensure_chains(&self.chains, pool.clone()).await;
         postgres::ensure_protocol_systems(&self.protocol_systems, pool.clone()).await;
 
-        let inner_gw = PostgresGateway::new(pool.clone(), self.retention_horizon).await?;
+        let inner_gw = self
+            .build_inner_gateway(pool.clone())
+            .await?;
         let (tx, rx) = mpsc::channel(10);
         let chain = self
             .chains
@@ -55,17 +143,22 @@ impl GatewayBuilder {
         .await;
         let handle = write_executor.run();
 
-        let cached_gw = CachedGateway::new(tx, pool.clone(), inner_gw.clone());
+        let cached_gw = CachedGateway::new(tx, pool.clone(), inner_gw.clone())
+            .with_retry_policy(self.retry_policy);
         Ok((cached_gw, handle))
     }
 
     pub async fn build_gw(self) -> Result<CachedGateway, StorageError> {
-        let pool = postgres::connect(&self.database_url).await?;
+        let pool =
+            postgres::connect(&self.database_url, self.prepared_statement_cache_size).await?;
 
-        let inner_gw = PostgresGateway::new(pool.clone(), self.retention_horizon).await?;
+        let inner_gw = self
+            .build_inner_gateway(pool.clone())
+            .await?;
         let (tx, _) = mpsc::channel(10);
 
-        let cached_gw = CachedGateway::new(tx, pool.clone(), inner_gw.clone());
+        let cached_gw = CachedGateway::new(tx, pool.clone(), inner_gw.clone())
+            .with_retry_policy(self.retry_policy);
         Ok(cached_gw)
     }
 }
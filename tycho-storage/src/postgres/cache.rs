@@ -3,11 +3,13 @@ use std::{collections::HashMap, num::NonZeroUsize, sync::Arc};
 use async_trait::async_trait;
 use chrono::NaiveDateTime;
 use diesel_async::{
-    pooled_connection::deadpool::Pool, scoped_futures::ScopedFutureExt, AsyncConnection,
-    AsyncPgConnection,
+    pooled_connection::deadpool::{Object, Pool},
+    scoped_futures::ScopedFutureExt,
+    AsyncConnection, AsyncPgConnection,
 };
 use lru::LruCache;
 use tokio::{
+    io::{AsyncRead, AsyncWrite},
     sync::{mpsc, oneshot, Mutex},
     task::JoinHandle,
 };
@@ -16,24 +18,27 @@ use tracing::{debug, info, info_span, instrument, trace, Instrument};
 use tycho_core::{
     models::{
         self,
-        blockchain::{Block, Transaction},
+        blockchain::{Block, ReorgEvent, Transaction},
         contract::{Account, AccountDelta},
         protocol::{
             ComponentBalance, ProtocolComponent, ProtocolComponentState,
             ProtocolComponentStateDelta,
         },
         token::CurrencyToken,
-        Address, Chain, ComponentId, ContractId, ExtractionState, PaginationParams, ProtocolType,
-        TxHash,
+        AccountToContractStore, Address, BlockHash, Chain, ComponentId, ContractId, ContractStore,
+        ExtractionState, OrderedContractStore, PaginationParams, ProtocolType, StoreKey, TxHash,
     },
     storage::{
-        BlockIdentifier, BlockOrTimestamp, ChainGateway, ContractStateGateway,
+        BlockIdentifier, BlockOrTimestamp, ChainGateway, ChangeCursor, ContractStateGateway,
         ExtractionStateGateway, Gateway, ProtocolGateway, StorageError, Version, WithTotal,
     },
     Bytes,
 };
 
-use super::{PostgresError, PostgresGateway};
+use super::{
+    retry::{with_retry, RetryPolicy},
+    PostgresError, PostgresGateway,
+};
 
 /// Represents different types of database write operations.
 #[derive(PartialEq, Clone, Debug)]
@@ -459,6 +464,7 @@ pub struct CachedGateway {
     pool: Pool<AsyncPgConnection>,
     state_gateway: PostgresGateway,
     lru_cache: Arc<Mutex<DeltasCache>>,
+    retry_policy: RetryPolicy,
 }
 
 impl Clone for CachedGateway {
@@ -470,6 +476,7 @@ impl Clone for CachedGateway {
             pool: self.pool.clone(),
             state_gateway: self.state_gateway.clone(),
             lru_cache: self.lru_cache.clone(),
+            retry_policy: self.retry_policy,
         }
     }
 }
@@ -563,9 +570,27 @@ impl CachedGateway {
             pool,
             state_gateway,
             lru_cache: Arc::new(Mutex::new(LruCache::new(NonZeroUsize::new(5).unwrap()))),
+            retry_policy: RetryPolicy::default(),
         }
     }
 
+    /// Sets the policy used to retry read queries failing with transient errors.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry_policy = policy;
+        self
+    }
+
+    /// Checks out a connection from the pool.
+    ///
+    /// Failing to do so is considered transient: the pool might just be exhausted or the
+    /// database briefly unreachable.
+    async fn get_connection(&self) -> Result<Object<AsyncPgConnection>, StorageError> {
+        self.pool
+            .get()
+            .await
+            .map_err(|e| StorageError::Transient(format!("Failed to retrieve connection: {e}")))
+    }
+
     pub async fn get_delta(
         &self,
         chain: &Chain,
@@ -620,6 +645,692 @@ impl CachedGateway {
 
         Ok((accounts_delta, protocol_delta, balance_deltas))
     }
+
+    /// Retrieves the protocol components on `chain` holding all of `tokens`, optionally
+    /// restricted to a protocol system.
+    pub async fn get_protocol_components_by_tokens(
+        &self,
+        chain: &Chain,
+        system: Option<&str>,
+        tokens: &[Address],
+    ) -> Result<Vec<ProtocolComponent>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_protocol_components_by_tokens(chain, system, tokens, &mut conn)
+            .await
+    }
+
+    /// Retrieves protocol components by external id, preserving the order of `external_ids`
+    /// and omitting ids that are not found.
+    pub async fn get_protocol_components_by_ids(
+        &self,
+        chain: &Chain,
+        external_ids: &[String],
+    ) -> Result<Vec<ProtocolComponent>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_protocol_components_by_ids(chain, external_ids, &mut conn)
+            .await
+    }
+
+    /// Retrieves all transactions of a block ordered by their index.
+    pub async fn get_transactions(
+        &self,
+        block_id: &BlockIdentifier,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_transactions(block_id, &mut conn)
+            .await
+    }
+
+    /// Counts transactions per block over an inclusive block range.
+    pub async fn count_transactions(
+        &self,
+        chain: &Chain,
+        start: &BlockIdentifier,
+        end: &BlockIdentifier,
+    ) -> Result<Vec<(i64, i64)>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .count_transactions(chain, start, end, &mut conn)
+            .await
+    }
+
+    /// Retrieves the latest stored block of a chain, optionally restricted to canonical blocks.
+    pub async fn get_latest_block(
+        &self,
+        chain: &Chain,
+        main_only: bool,
+    ) -> Result<Block, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_latest_block(chain, main_only, &mut conn)
+            .await
+    }
+
+    /// Retrieves the highest canonical block with at least `min_confirmations` confirmations.
+    pub async fn get_finalized_block(
+        &self,
+        chain: &Chain,
+        min_confirmations: u64,
+    ) -> Result<Block, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_finalized_block(chain, min_confirmations, &mut conn)
+            .await
+    }
+
+    /// Computes the common ancestor of two chain heads and the depth of the reorg.
+    pub async fn reorg_depth(
+        &self,
+        chain: &Chain,
+        old_head: &BlockHash,
+        new_head: &BlockHash,
+    ) -> Result<(BlockHash, u64), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .reorg_depth(chain, old_head, new_head, &mut conn)
+            .await
+    }
+
+    /// Finds contract storage slots with overlapping validity ranges.
+    pub async fn check_storage_consistency(
+        &self,
+        chain: &Chain,
+    ) -> Result<Vec<(Address, StoreKey)>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .check_storage_consistency(chain, &mut conn)
+            .await
+    }
+
+    /// Retrieves slot changes between two versions, restricted to the given contracts and slots.
+    pub async fn get_slots_delta_filtered(
+        &self,
+        chain: &Chain,
+        contracts: &[Address],
+        slots: &[StoreKey],
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+    ) -> Result<AccountToContractStore, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_slots_delta_filtered(
+                chain,
+                contracts,
+                slots,
+                start_version,
+                target_version,
+                &mut conn,
+            )
+            .await
+    }
+
+    /// Returns the query plan of the slot delta query between two versions.
+    pub async fn explain_slots_delta(
+        &self,
+        chain: &Chain,
+        start_version_ts: &NaiveDateTime,
+        target_version_ts: &NaiveDateTime,
+    ) -> Result<String, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .explain_slots_delta(chain, start_version_ts, target_version_ts, &mut conn)
+            .await
+    }
+
+    /// Stores all changes of a block atomically without using the write cache.
+    pub async fn apply_block_changes(
+        &self,
+        block: &Block,
+        txns: &[Transaction],
+        account_deltas: &[(TxHash, AccountDelta)],
+        state_deltas: &[(TxHash, ProtocolComponentStateDelta)],
+        balances: &[ComponentBalance],
+    ) -> Result<(), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .apply_block_changes(block, txns, account_deltas, state_deltas, balances, &mut conn)
+            .await
+    }
+
+    /// Retrieves the last block processed for a protocol system, `None` on the first run.
+    pub async fn get_cursor(
+        &self,
+        chain: &Chain,
+        protocol_system: &str,
+    ) -> Result<Option<BlockIdentifier>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_cursor(chain, protocol_system, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Sets the last block processed for a protocol system without using the write cache.
+    ///
+    /// The block must already be persisted, so pending writes should be flushed first.
+    pub async fn set_cursor(
+        &self,
+        chain: &Chain,
+        protocol_system: &str,
+        block: &BlockIdentifier,
+    ) -> Result<(), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .set_cursor(chain, protocol_system, block, &mut conn)
+            .await
+    }
+
+    /// Retrieves the latest token balances of the given components as of `version`.
+    pub async fn get_component_balances(
+        &self,
+        external_ids: &[String],
+        chain: &Chain,
+        version: Option<&BlockOrTimestamp>,
+    ) -> Result<HashMap<String, HashMap<Bytes, ComponentBalance>>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_component_balances(external_ids, chain, version, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Reverts the protocol states of a protocol system to block `to` without using the write
+    /// cache.
+    pub async fn revert_protocol_state(
+        &self,
+        chain: &Chain,
+        protocol_system: &str,
+        to: &BlockIdentifier,
+    ) -> Result<(), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .revert_protocol_state(chain, protocol_system, to, &mut conn)
+            .await
+    }
+
+    /// Reverts all contract and protocol state of a chain to block `to` without using the write
+    /// cache. Later blocks are kept but marked as non canonical.
+    pub async fn revert_to_block(
+        &self,
+        chain: &Chain,
+        to: &BlockIdentifier,
+    ) -> Result<ReorgEvent, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .revert_to_block(chain, to, &mut conn)
+            .await
+    }
+
+    /// Exports all contracts of a chain as newline-delimited JSON into `writer`.
+    ///
+    /// Not retried, since a failure may occur after part of the export was already written.
+    pub async fn export_contracts<W: AsyncWrite + Unpin + Send>(
+        &self,
+        chain: &Chain,
+        version: Option<&BlockOrTimestamp>,
+        writer: &mut W,
+    ) -> Result<usize, StorageError> {
+        let mut conn = self.get_connection().await?;
+        self.state_gateway
+            .export_contracts(chain, version, writer, &mut conn)
+            .await
+    }
+
+    /// Imports contracts from newline-delimited JSON read from `reader`.
+    pub async fn import_contracts<R: AsyncRead + Unpin + Send>(
+        &self,
+        chain: &Chain,
+        reader: &mut R,
+    ) -> Result<usize, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .import_contracts(chain, reader, &mut conn)
+            .await
+    }
+
+    /// Exports all tokens of a chain as CSV into `writer`.
+    ///
+    /// Not retried, since a failure may occur after part of the export was already written.
+    pub async fn export_tokens_csv<W: AsyncWrite + Unpin + Send>(
+        &self,
+        chain: Chain,
+        writer: &mut W,
+    ) -> Result<usize, StorageError> {
+        let mut conn = self.get_connection().await?;
+        self.state_gateway
+            .export_tokens_csv(chain, writer, &mut conn)
+            .await
+    }
+
+    /// Retrieves a page of contract changes of a chain after block `since`, grouped by
+    /// contract, together with the cursor to continue from.
+    pub async fn get_changes_since(
+        &self,
+        chain: &Chain,
+        since: &BlockIdentifier,
+        cursor: Option<&ChangeCursor>,
+        limit: usize,
+    ) -> Result<(Vec<AccountDelta>, Option<ChangeCursor>), StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_changes_since(chain, since, cursor, limit, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Counts the storage changes per contract between two versions, busiest contracts first.
+    pub async fn contract_change_stats(
+        &self,
+        chain: &Chain,
+        start_version: &BlockOrTimestamp,
+        target_version: &BlockOrTimestamp,
+    ) -> Result<Vec<(Address, u64)>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .contract_change_stats(chain, start_version, target_version, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Retrieves the slot changes of all contracts between two versions, keyed by address.
+    ///
+    /// Resolves contract addresses in a separate query, which is cheapest if few contracts
+    /// changed. Prefer [`CachedGateway::get_slots_delta_joined`] for large deltas.
+    pub async fn get_slots_delta_by_address(
+        &self,
+        chain: &Chain,
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+    ) -> Result<AccountToContractStore, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_slots_delta_by_address(chain, start_version, target_version, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Retrieves the slot changes of all contracts between two versions, sorted by address and
+    /// slot.
+    pub async fn get_slots_delta_ordered(
+        &self,
+        chain: &Chain,
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+    ) -> Result<Vec<(Address, OrderedContractStore)>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_slots_delta_ordered(chain, start_version, target_version, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Retrieves the slot changes of all contracts between two versions, keyed by address,
+    /// selecting the addresses within a single query.
+    pub async fn get_slots_delta_joined(
+        &self,
+        chain: &Chain,
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+    ) -> Result<AccountToContractStore, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_slots_delta_joined(chain, start_version, target_version, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Resolves many versions to timestamps, looking up referenced blocks in a single query.
+    pub async fn versions_to_ts(
+        &self,
+        versions: &[BlockOrTimestamp],
+    ) -> Result<Vec<NaiveDateTime>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .versions_to_ts(versions, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Deletes outdated contract storage versions that expired before `before`, keeping the
+    /// latest version of every slot.
+    pub async fn prune_storage(
+        &self,
+        chain: &Chain,
+        before: NaiveDateTime,
+    ) -> Result<usize, StorageError> {
+        let mut conn = self.get_connection().await?;
+        self.state_gateway
+            .prune_storage(chain, before, &mut conn)
+            .await
+    }
+
+    /// Retrieves the current storage of a single contract from the current slot snapshot.
+    pub async fn get_current_slots(
+        &self,
+        chain: &Chain,
+        address: &Address,
+    ) -> Result<ContractStore, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_current_slots(chain, address, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Regenerates the current slot snapshot of a chain from the versioned contract storage.
+    pub async fn rebuild_current_snapshot(&self, chain: &Chain) -> Result<usize, StorageError> {
+        let mut conn = self.get_connection().await?;
+        self.state_gateway
+            .rebuild_current_snapshot(chain, &mut conn)
+            .await
+    }
+
+    /// Resolves a protocol system name to its database id.
+    pub async fn get_protocol_system_id(&self, name: &str) -> Result<i64, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_protocol_system_id(name, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Resolves protocol system names to their database ids, in the order of `names`.
+    pub async fn get_protocol_system_ids(&self, names: &[&str]) -> Result<Vec<i64>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_protocol_system_ids(names, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Retrieves a single protocol component, resolving its creation transaction hash.
+    pub async fn get_protocol_component(
+        &self,
+        chain: &Chain,
+        external_id: &str,
+    ) -> Result<ProtocolComponent, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_protocol_component(chain, external_id, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Retrieves the protocol components created in a block, ordered by external id.
+    pub async fn get_components_created_in_block(
+        &self,
+        block: &BlockIdentifier,
+    ) -> Result<Vec<ProtocolComponent>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_components_created_in_block(block, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Links a component to its counterpart on another chain, replacing any previous link.
+    pub async fn link_component(
+        &self,
+        chain: &Chain,
+        external_id: &str,
+        linked: (&Chain, &str),
+    ) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+        self.state_gateway
+            .link_component(chain, external_id, linked, &mut conn)
+            .await
+    }
+
+    /// Resolves the chain and external id of the counterpart a component is linked to.
+    pub async fn get_linked_component(
+        &self,
+        chain: &Chain,
+        external_id: &str,
+    ) -> Result<Option<(Chain, ComponentId)>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_linked_component(chain, external_id, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Retrieves the addresses of the contracts a component is linked to, ordered by address.
+    pub async fn get_component_contracts(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+    ) -> Result<Vec<Address>, StorageError> {
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_component_contracts(external_id, chain, &mut conn)
+                .await
+        })
+        .await
+    }
+
+    /// Replaces the set of tokens held by a component without using the write cache.
+    pub async fn set_component_tokens(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        tokens: &[Address],
+    ) -> Result<(), StorageError> {
+        let mut conn = self.get_connection().await?;
+        self.state_gateway
+            .set_component_tokens(external_id, chain, tokens, &mut conn)
+            .await
+    }
+
+    /// Updates the quality of already stored tokens without using the write cache.
+    pub async fn update_token_qualities(
+        &self,
+        updates: &[(Address, u32)],
+        chain: &Chain,
+    ) -> Result<usize, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .update_token_qualities(updates, chain, &mut conn)
+            .await
+    }
+
+    /// Retrieves all tokens of a chain whose quality is one of `qualities`, ordered by symbol.
+    pub async fn get_tokens_by_quality(
+        &self,
+        chain: Chain,
+        qualities: &[u32],
+    ) -> Result<Vec<CurrencyToken>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_tokens_by_quality(chain, qualities, &mut conn)
+            .await
+    }
+
+    /// Retrieves a single token by its address.
+    pub async fn get_token(
+        &self,
+        chain: Chain,
+        address: &Address,
+    ) -> Result<CurrencyToken, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_token(chain, address, &mut conn)
+            .await
+    }
+
+    /// Retrieves all stored protocol types, optionally validating their attribute schemas.
+    pub async fn get_protocol_types(
+        &self,
+        validate_schema: bool,
+    ) -> Result<Vec<ProtocolType>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .get_protocol_types(validate_schema, &mut conn)
+            .await
+    }
+
+    /// Registers a protocol type, updating it in place if it already exists, and returns its id.
+    pub async fn insert_protocol_type(&self, pt: &ProtocolType) -> Result<i64, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .insert_protocol_type(pt, &mut conn)
+            .await
+    }
+
+    /// Retrieves all protocol components with a static attribute `key` set to `value`.
+    pub async fn find_components_by_attribute(
+        &self,
+        chain: &Chain,
+        key: &str,
+        value: &Bytes,
+    ) -> Result<Vec<ProtocolComponent>, StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .find_components_by_attribute(chain, key, value, &mut conn)
+            .await
+    }
+
+    /// Merges `patch` into the static attributes of a protocol component.
+    pub async fn update_component_attributes(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        patch: &HashMap<String, Bytes>,
+    ) -> Result<(), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .update_component_attributes(external_id, chain, patch, &mut conn)
+            .await
+    }
+
+    /// Soft-deletes a protocol component, see [`PostgresGateway::delete_protocol_component`].
+    pub async fn delete_protocol_component(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        deletion_tx: i64,
+        ts: NaiveDateTime,
+    ) -> Result<(), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .delete_protocol_component(external_id, chain, deletion_tx, ts, &mut conn)
+            .await
+    }
+
+    /// Restores a soft-deleted protocol component.
+    pub async fn restore_protocol_component(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+    ) -> Result<(), StorageError> {
+        let mut conn =
+            self.pool.get().await.map_err(|e| {
+                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
+            })?;
+        self.state_gateway
+            .restore_protocol_component(external_id, chain, &mut conn)
+            .await
+    }
 }
 
 #[async_trait]
@@ -653,13 +1364,13 @@ impl ChainGateway for CachedGateway {
 
     #[instrument(skip_all)]
     async fn get_block(&self, id: &BlockIdentifier) -> Result<Block, StorageError> {
-        let mut conn =
-            self.pool.get().await.map_err(|e| {
-                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
-            })?;
-        self.state_gateway
-            .get_block(id, &mut conn)
-            .await
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_block(id, &mut conn)
+                .await
+        })
+        .await
     }
 
     async fn upsert_tx(&self, new: &[Transaction]) -> Result<(), StorageError> {
@@ -670,13 +1381,13 @@ impl ChainGateway for CachedGateway {
 
     #[instrument(skip_all)]
     async fn get_tx(&self, hash: &TxHash) -> Result<Transaction, StorageError> {
-        let mut conn =
-            self.pool.get().await.map_err(|e| {
-                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
-            })?;
-        self.state_gateway
-            .get_tx(hash, &mut conn)
-            .await
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_tx(hash, &mut conn)
+                .await
+        })
+        .await
     }
 
     #[instrument(skip_all)]
@@ -700,13 +1411,13 @@ impl ContractStateGateway for CachedGateway {
         version: Option<&Version>,
         include_slots: bool,
     ) -> Result<Account, StorageError> {
-        let mut conn =
-            self.pool.get().await.map_err(|e| {
-                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
-            })?;
-        self.state_gateway
-            .get_contract(id, version, include_slots, &mut conn)
-            .await
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_contract(id, version, include_slots, &mut conn)
+                .await
+        })
+        .await
     }
 
     #[instrument(skip_all)]
@@ -718,13 +1429,20 @@ impl ContractStateGateway for CachedGateway {
         include_slots: bool,
         pagination_params: Option<&PaginationParams>,
     ) -> Result<WithTotal<Vec<Account>>, StorageError> {
-        let mut conn =
-            self.pool.get().await.map_err(|e| {
-                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
-            })?;
-        self.state_gateway
-            .get_contracts(chain, addresses, version, include_slots, pagination_params, &mut conn)
-            .await
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_contracts(
+                    chain,
+                    addresses,
+                    version,
+                    include_slots,
+                    pagination_params,
+                    &mut conn,
+                )
+                .await
+        })
+        .await
     }
 
     #[instrument(skip_all)]
@@ -759,13 +1477,13 @@ impl ContractStateGateway for CachedGateway {
         start_version: Option<&BlockOrTimestamp>,
         end_version: &BlockOrTimestamp,
     ) -> Result<Vec<AccountDelta>, StorageError> {
-        let mut conn =
-            self.pool.get().await.map_err(|e| {
-                StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
-            })?;
-        self.state_gateway
-            .get_accounts_delta(chain, start_version, end_version, &mut conn)
-            .await
+        with_retry(&self.retry_policy, || async move {
+            let mut conn = self.get_connection().await?;
+            self.state_gateway
+                .get_accounts_delta(chain, start_version, end_version, &mut conn)
+                .await
+        })
+        .await
     }
 }
 
@@ -778,6 +1496,7 @@ impl ProtocolGateway for CachedGateway {
         system: Option<String>,
         ids: Option<&[&str]>,
         min_tvl: Option<f64>,
+        created_after: Option<NaiveDateTime>,
         pagination_params: Option<&PaginationParams>,
     ) -> Result<WithTotal<Vec<ProtocolComponent>>, StorageError> {
         let mut conn =
@@ -785,7 +1504,16 @@ impl ProtocolGateway for CachedGateway {
                 StorageError::Unexpected(format!("Failed to retrieve connection: {e}"))
             })?;
         self.state_gateway
-            .get_protocol_components(chain, system, ids, min_tvl, pagination_params, &mut conn)
+            .get_protocol_components(
+                chain,
+                system,
+                ids,
+                min_tvl,
+                created_after,
+                false,
+                pagination_params,
+                &mut conn,
+            )
             .await
     }
 
@@ -1146,6 +1874,7 @@ mod test_serial_db {
                 creation_tx: tx_1.hash.clone(),
                 static_attributes: Default::default(),
                 created_at: Default::default(),
+                linked_component: None,
             };
             let component_balance = models::protocol::ComponentBalance {
                 token: usdc_address.clone(),
@@ -1359,6 +2088,48 @@ mod test_serial_db {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_concurrent_accounts_delta() {
+        run_against_db(|connection_pool| async move {
+            let mut connection = connection_pool
+                .get()
+                .await
+                .expect("Failed to get a connection from the pool");
+            setup_data(&mut connection).await;
+            let gateway: PostgresGateway = PostgresGateway::from_connection(&mut connection).await;
+            let (tx, _rx) = mpsc::channel(10);
+            let cached_gw = CachedGateway::new(tx, connection_pool.clone(), gateway);
+            let start = BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2)));
+            let end = BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 1)));
+            let mut exp = cached_gw
+                .get_accounts_delta(&Chain::Ethereum, Some(&start), &end)
+                .await
+                .expect("Failed to fetch deltas");
+            exp.sort_by(|a, b| a.address.cmp(&b.address));
+
+            let handles = (0..4)
+                .map(|_| {
+                    let gw = cached_gw.clone();
+                    let (start, end) = (start.clone(), end.clone());
+                    tokio::spawn(async move {
+                        gw.get_accounts_delta(&Chain::Ethereum, Some(&start), &end)
+                            .await
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for handle in handles {
+                let mut res = handle
+                    .await
+                    .expect("Task failed")
+                    .expect("Failed to fetch deltas");
+                res.sort_by(|a, b| a.address.cmp(&b.address));
+                assert_eq!(res, exp);
+            }
+        })
+        .await;
+    }
+
     fn get_sample_block(version: usize) -> models::blockchain::Block {
         let ts1 = yesterday_one_am();
         let ts2 = ts1 + Duration::from_secs(3600);
@@ -1,12 +1,25 @@
-use super::{orm, schema, storage_error_from_diesel, PostgresError, PostgresGateway, MAX_TS};
+use super::{
+    contract::revert_contract_storage,
+    maybe_lookup_block_ts, orm,
+    protocol::{revert_component_balances, revert_protocol_states},
+    schema, storage_error_from_diesel, PostgresError, PostgresGateway, MAX_TS,
+};
+use chrono::NaiveDateTime;
 use diesel::prelude::*;
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
 use itertools::Itertools;
 use std::collections::HashMap;
 use tracing::{instrument, warn};
 use tycho_core::{
-    models::{blockchain::*, BlockHash, TxHash},
-    storage::{BlockIdentifier, StorageError},
+    models::{
+        blockchain::*,
+        contract::AccountDelta,
+        protocol::{ComponentBalance, ProtocolComponentStateDelta},
+        Address, BlockHash, Chain, TxHash,
+    },
+    storage::{BlockIdentifier, BlockOrTimestamp, StorageError},
     Bytes,
 };
 
@@ -17,10 +30,25 @@ impl PostgresGateway {
         blocks: &[Block],
         conn: &mut AsyncPgConnection,
     ) -> Result<(), StorageError> {
+        self.insert_blocks(blocks, conn)
+            .await
+            .map(|_| ())
+    }
+
+    /// Inserts blocks in a single batch, skipping blocks that already exist.
+    ///
+    /// Blocks with an already known hash (e.g. inserted again during a reorg) are ignored.
+    /// Returns the ids of the newly inserted blocks only.
+    #[instrument(skip_all)]
+    pub async fn insert_blocks(
+        &self,
+        blocks: &[Block],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<i64>, StorageError> {
         use super::schema::block::dsl::*;
         if blocks.is_empty() {
             warn!("Upsert blocks called with empty blocks!");
-            return Ok(());
+            return Ok(Vec::new());
         }
         let block_chain_id = self.get_chain_id(&blocks[0].chain);
         let new_blocks = blocks
@@ -38,8 +66,10 @@ impl PostgresGateway {
         // assumes that block with the same hash will not appear with different values
         diesel::insert_into(block)
             .values(&new_blocks)
-            .on_conflict_do_nothing()
-            .execute(conn)
+            .on_conflict(hash)
+            .do_nothing()
+            .returning(id)
+            .get_results(conn)
             .await
             .map_err(|err| {
                 storage_error_from_diesel(
@@ -48,8 +78,8 @@ impl PostgresGateway {
                     &format!("Batch: {} and {} more", &new_blocks[0].hash, new_blocks.len() - 1),
                     None,
                 )
-            })?;
-        Ok(())
+                .into()
+            })
     }
 
     #[instrument(skip_all)]
@@ -58,26 +88,228 @@ impl PostgresGateway {
         block_id: &BlockIdentifier,
         conn: &mut AsyncPgConnection,
     ) -> Result<Block, StorageError> {
-        // taking a reference here is necessary, to not move block_id
-        // so it can be used in the map_err closure later on. It would
-        // be better if BlockIdentifier was copy though (complicates lifetimes).
-        let mut orm_block = match &block_id {
-            BlockIdentifier::Number((chain, number)) => {
-                orm::Block::by_number(*chain, *number, conn).await
+        let orm_block = orm::Block::by_id(block_id, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &block_id.to_string(), None))?;
+        Ok(self.block_from_orm(orm_block))
+    }
+
+    /// Resolves many versions to timestamps at once.
+    ///
+    /// All blocks referenced by hash or number are looked up in a single query, timestamps are
+    /// passed through as is. `Latest` identifiers are resolved one by one, as each requires its
+    /// own ordered lookup. If several blocks share a number, the canonical one is used.
+    ///
+    /// # Returns
+    /// The timestamps in the order of `versions`. Fails with `NotFound` if any referenced
+    /// block is not stored.
+    #[instrument(skip_all)]
+    pub async fn versions_to_ts(
+        &self,
+        versions: &[BlockOrTimestamp],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<NaiveDateTime>, StorageError> {
+        let mut hashes = Vec::new();
+        let mut numbers = Vec::new();
+        let mut chain_ids = Vec::new();
+        for version in versions {
+            match version {
+                BlockOrTimestamp::Block(BlockIdentifier::Hash(hash)) => hashes.push(hash),
+                BlockOrTimestamp::Block(BlockIdentifier::Number((chain, number))) => {
+                    numbers.push(*number);
+                    chain_ids.push(self.get_chain_id(chain));
+                }
+                _ => {}
             }
+        }
+
+        let mut by_hash = HashMap::new();
+        let mut by_number = HashMap::new();
+        if !hashes.is_empty() || !numbers.is_empty() {
+            let blocks = schema::block::table
+                .filter(
+                    schema::block::hash
+                        .eq_any(&hashes)
+                        .or(schema::block::number
+                            .eq_any(&numbers)
+                            .and(schema::block::chain_id.eq_any(&chain_ids))),
+                )
+                .select((
+                    schema::block::chain_id,
+                    schema::block::number,
+                    schema::block::hash,
+                    schema::block::main,
+                    schema::block::ts,
+                ))
+                .get_results::<(i64, i64, BlockHash, bool, NaiveDateTime)>(conn)
+                .await
+                .map_err(PostgresError::from)?;
+            for (chain_id, number, hash, main, ts) in blocks {
+                if main || !by_number.contains_key(&(chain_id, number)) {
+                    by_number.insert((chain_id, number), ts);
+                }
+                by_hash.insert(hash, ts);
+            }
+        }
+
+        let mut result = Vec::with_capacity(versions.len());
+        for version in versions {
+            let ts = match version {
+                BlockOrTimestamp::Block(id @ BlockIdentifier::Hash(hash)) => by_hash
+                    .get(hash)
+                    .copied()
+                    .ok_or_else(|| StorageError::NotFound("Block".to_string(), id.to_string()))?,
+                BlockOrTimestamp::Block(id @ BlockIdentifier::Number((chain, number))) => by_number
+                    .get(&(self.get_chain_id(chain), *number))
+                    .copied()
+                    .ok_or_else(|| StorageError::NotFound("Block".to_string(), id.to_string()))?,
+                BlockOrTimestamp::Block(BlockIdentifier::Latest(_)) => {
+                    maybe_lookup_block_ts(version, conn).await?
+                }
+                BlockOrTimestamp::Timestamp(ts) => *ts,
+            };
+            result.push(ts);
+        }
+        Ok(result)
+    }
+
+    /// Retrieves the block with the highest number stored for a chain.
+    ///
+    /// If `main_only` is set, only blocks on the canonical chain are considered. This matters
+    /// during reorgs, where the highest block might not be canonical yet.
+    #[instrument(skip_all)]
+    pub async fn get_latest_block(
+        &self,
+        chain: &Chain,
+        main_only: bool,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Block, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let mut query = schema::block::table
+            .filter(schema::block::chain_id.eq(chain_id))
+            .into_boxed();
+        if main_only {
+            query = query.filter(schema::block::main.eq(true));
+        }
+        let orm_block = query
+            .order_by(schema::block::number.desc())
+            .select(orm::Block::as_select())
+            .first::<orm::Block>(conn)
+            .await
+            .map_err(|err| {
+                storage_error_from_diesel(err, "Block", &format!("Latest({})", chain), None)
+            })?;
+        Ok(self.block_from_orm(orm_block))
+    }
+
+    /// Retrieves the highest canonical block that is at least `min_confirmations` blocks
+    /// below the latest canonical block of a chain.
+    ///
+    /// Returns `NotFound` if the chain does not have enough blocks to satisfy the requested
+    /// confirmation depth.
+    #[instrument(skip_all)]
+    pub async fn get_finalized_block(
+        &self,
+        chain: &Chain,
+        min_confirmations: u64,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Block, StorageError> {
+        let head = self
+            .get_latest_block(chain, true, conn)
+            .await?;
+        let not_found = || {
+            StorageError::NotFound(
+                "Block".to_string(),
+                format!("Finalized({}, {} confirmations)", chain, min_confirmations),
+            )
+        };
+        let max_number = head
+            .number
+            .checked_sub(min_confirmations)
+            .ok_or_else(not_found)?;
+        let orm_block = schema::block::table
+            .filter(schema::block::chain_id.eq(self.get_chain_id(chain)))
+            .filter(schema::block::main.eq(true))
+            .filter(schema::block::number.le(max_number as i64))
+            .order_by(schema::block::number.desc())
+            .select(orm::Block::as_select())
+            .first::<orm::Block>(conn)
+            .await
+            .optional()
+            .map_err(PostgresError::from)?
+            .ok_or_else(not_found)?;
+        Ok(self.block_from_orm(orm_block))
+    }
 
-            BlockIdentifier::Hash(block_hash) => orm::Block::by_hash(block_hash, conn).await,
-            BlockIdentifier::Latest(chain) => orm::Block::most_recent(*chain, conn).await,
+    /// Computes the common ancestor of two chain heads and the depth of the reorg.
+    ///
+    /// Walks the `parent_hash` links of both heads until they meet. Returns the hash of the
+    /// common ancestor and the number of blocks on the old fork after it. Errors if the two
+    /// heads do not converge within the stored history.
+    #[instrument(skip_all)]
+    pub async fn reorg_depth(
+        &self,
+        chain: &Chain,
+        old_head: &BlockHash,
+        new_head: &BlockHash,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(BlockHash, u64), StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let mut old = Self::chain_block_by_hash(chain_id, old_head, conn).await?;
+        let mut new = Self::chain_block_by_hash(chain_id, new_head, conn).await?;
+        let mut depth = 0;
+        while old.hash != new.hash {
+            if old.number >= new.number {
+                old = Self::parent_block(chain_id, &old, old_head, new_head, conn).await?;
+                depth += 1;
+            } else {
+                new = Self::parent_block(chain_id, &new, old_head, new_head, conn).await?;
+            }
         }
-        .map_err(|err| storage_error_from_diesel(err, "Block", &block_id.to_string(), None))?;
+        Ok((old.hash, depth))
+    }
+
+    async fn chain_block_by_hash(
+        chain_id: i64,
+        hash: &BlockHash,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<orm::Block, StorageError> {
+        Ok(schema::block::table
+            .filter(schema::block::chain_id.eq(chain_id))
+            .filter(schema::block::hash.eq(hash))
+            .select(orm::Block::as_select())
+            .first::<orm::Block>(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &hash.to_string(), None))?)
+    }
+
+    async fn parent_block(
+        chain_id: i64,
+        block: &orm::Block,
+        old_head: &BlockHash,
+        new_head: &BlockHash,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<orm::Block, StorageError> {
+        Self::chain_block_by_hash(chain_id, &block.parent_hash, conn)
+            .await
+            .map_err(|err| match err {
+                StorageError::NotFound(_, _) => StorageError::Unexpected(format!(
+                    "Heads {} and {} do not converge within stored history",
+                    old_head, new_head
+                )),
+                err => err,
+            })
+    }
+
+    fn block_from_orm(&self, mut orm_block: orm::Block) -> Block {
         let chain = self.get_chain(&orm_block.chain_id);
-        Ok(Block::new(
+        Block::new(
             orm_block.number as u64,
             chain,
             std::mem::take(&mut orm_block.hash),
             std::mem::take(&mut orm_block.parent_hash),
             orm_block.ts,
-        ))
+        )
     }
 
     #[instrument(skip_all)]
@@ -129,6 +361,7 @@ impl PostgresGateway {
                     hash: new.hash.clone(),
                     block_id: bid,
                     from: new.from.clone(),
+                    // empty for contract creations, the zero address is a valid recipient
                     to: new.to.clone().unwrap_or_default(),
                     index: new.index as i64,
                 })
@@ -152,6 +385,73 @@ impl PostgresGateway {
         Ok(())
     }
 
+    /// Stores all changes of a block atomically.
+    ///
+    /// Inserts the block, its transactions, account deltas, protocol state deltas and component
+    /// balances within a single database transaction. If any of the writes fails, all of them
+    /// are rolled back and the database is left untouched.
+    ///
+    /// Deltas are linked to the transaction identified by the accompanying hash, which must
+    /// either be part of `txns` or already be stored.
+    ///
+    /// Applying the same block again is a no-op, which allows replaying blocks during reorgs:
+    /// the block and its transactions are only inserted if not yet present, and account, state
+    /// and balance changes already stored for the same transaction are skipped. Slot changes
+    /// additionally only create new versions if they actually change the value.
+    #[instrument(skip_all, fields(block = %block.hash))]
+    pub async fn apply_block_changes(
+        &self,
+        block: &Block,
+        txns: &[Transaction],
+        account_deltas: &[(TxHash, AccountDelta)],
+        state_deltas: &[(TxHash, ProtocolComponentStateDelta)],
+        balances: &[ComponentBalance],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        conn.transaction(|conn| {
+            async move {
+                self.upsert_block(std::slice::from_ref(block), conn)
+                    .await?;
+                if !txns.is_empty() {
+                    self.upsert_tx(txns, conn).await?;
+                }
+                if !account_deltas.is_empty() {
+                    let mut account_deltas = account_deltas.to_vec();
+                    self.prune_applied_account_changes(&block.chain, &mut account_deltas, conn)
+                        .await?;
+                    let account_deltas = account_deltas
+                        .iter()
+                        .map(|(tx, delta)| (tx.clone(), delta))
+                        .collect::<Vec<_>>();
+                    self.update_contracts(&block.chain, &account_deltas, conn)
+                        .await?;
+                }
+                if !state_deltas.is_empty() {
+                    let mut state_deltas = state_deltas.to_vec();
+                    self.prune_applied_state_changes(&block.chain, &mut state_deltas, conn)
+                        .await?;
+                    let state_deltas = state_deltas
+                        .iter()
+                        .map(|(tx, delta)| (tx.clone(), delta))
+                        .collect::<Vec<_>>();
+                    self.update_protocol_states(&block.chain, &state_deltas, conn)
+                        .await?;
+                }
+                let mut balances = balances.to_vec();
+                self.prune_applied_component_balances(&block.chain, &mut balances, conn)
+                    .await?;
+                if !balances.is_empty() {
+                    self.add_component_balances(&balances, &block.chain, conn)
+                        .await?;
+                }
+                Ok::<(), PostgresError>(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+        Ok(())
+    }
+
     #[instrument(skip_all)]
     pub async fn get_tx(
         &self,
@@ -164,20 +464,83 @@ impl PostgresGateway {
             .select((orm::Transaction::as_select(), schema::block::hash))
             .first::<(orm::Transaction, BlockHash)>(conn)
             .await
-            .map(|(mut orm_tx, block_hash)| {
-                Ok(Transaction {
-                    hash: std::mem::take(&mut orm_tx.hash),
-                    block_hash,
-                    from: std::mem::take(&mut orm_tx.from),
-                    to: Some(std::mem::take(&mut orm_tx.to)),
-                    index: orm_tx.index as u64,
-                })
-            })
+            .map(|(orm_tx, block_hash)| transaction_from_orm(orm_tx, block_hash))
             .map_err(|err| {
                 storage_error_from_diesel(err, "Transaction", &hex::encode(hash), None)
             })?
     }
 
+    /// Retrieves all transactions of a block ordered by their index.
+    ///
+    /// Returns an empty vec if the block exists but contains no transactions.
+    #[instrument(skip_all)]
+    pub async fn get_transactions(
+        &self,
+        block_id: &BlockIdentifier,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Transaction>, StorageError> {
+        let block = orm::Block::by_id(block_id, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &block_id.to_string(), None))?;
+        schema::transaction::table
+            .filter(schema::transaction::block_id.eq(block.id))
+            .order_by(schema::transaction::index.asc())
+            .select(orm::Transaction::as_select())
+            .get_results::<orm::Transaction>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .map(|orm_tx| transaction_from_orm(orm_tx, block.hash.clone()))
+            .collect()
+    }
+
+    /// Counts transactions per block over an inclusive block range.
+    ///
+    /// Returns `(block_number, tx_count)` ordered by block number. Only transactions of canonical
+    /// blocks are counted, blocks within the range without any transactions are reported with a
+    /// count of 0.
+    #[instrument(skip_all)]
+    pub async fn count_transactions(
+        &self,
+        chain: &Chain,
+        start: &BlockIdentifier,
+        end: &BlockIdentifier,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<(i64, i64)>, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start_block = orm::Block::by_id(start, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &start.to_string(), None))?;
+        let end_block = orm::Block::by_id(end, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &end.to_string(), None))?;
+
+        let counts = schema::transaction::table
+            .inner_join(schema::block::table)
+            .filter(schema::block::chain_id.eq(chain_id))
+            .filter(schema::block::main.eq(true))
+            .filter(schema::block::number.between(start_block.number, end_block.number))
+            .group_by(schema::block::number)
+            .select((schema::block::number, diesel::dsl::count(schema::transaction::id)))
+            .get_results::<(i64, i64)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        Ok((start_block.number..=end_block.number)
+            .map(|number| {
+                (
+                    number,
+                    counts
+                        .get(&number)
+                        .copied()
+                        .unwrap_or(0),
+                )
+            })
+            .collect())
+    }
+
     pub async fn revert_state(
         &self,
         to: &BlockIdentifier,
@@ -207,13 +570,7 @@ impl PostgresGateway {
 
         // Any versioned table's rows, which have `valid_to` set to "> block.ts"
         // need, to be updated to be valid again (thus, valid_to = NULL).
-        diesel::update(
-            schema::contract_storage::table.filter(schema::contract_storage::valid_to.gt(block.ts)),
-        )
-        .set(schema::contract_storage::valid_to.eq(MAX_TS))
-        .execute(conn)
-        .await
-        .map_err(PostgresError::from)?;
+        revert_contract_storage(block.chain_id, block.ts, conn).await?;
 
         diesel::update(
             schema::account_balance::table.filter(schema::account_balance::valid_to.gt(block.ts)),
@@ -256,8 +613,199 @@ impl PostgresGateway {
         .await
         .map_err(PostgresError::from)?;
 
+        self.clear_block_ts_cache();
         Ok(())
     }
+
+    /// Reverts contract storage, balances and code, component balances and protocol states of
+    /// `chain` to the state at block `to`, all within a single transaction.
+    ///
+    /// Unlike [`Self::revert_state`] no blocks are deleted: all blocks after `to` are kept but
+    /// marked as non canonical. Versions created after the target block are removed and the
+    /// versions that were valid at the target block become valid again. Account and component
+    /// deletions after the target block are reverted as well.
+    ///
+    /// Returns a [`ReorgEvent`] listing the blocks and contracts that were reverted, or
+    /// `NotFound` if `to` is not a block of `chain`.
+    pub async fn revert_to_block(
+        &self,
+        chain: &Chain,
+        to: &BlockIdentifier,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<ReorgEvent, StorageError> {
+        let block = orm::Block::by_id(to, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &to.to_string(), None))?;
+        let chain_id = self.get_chain_id(chain);
+        if block.chain_id != chain_id {
+            return Err(StorageError::NotFound("Block".to_string(), format!("{to} on {chain}")));
+        }
+        let accounts = || {
+            schema::account::table
+                .filter(schema::account::chain_id.eq(chain_id))
+                .select(schema::account::id)
+        };
+        let event = conn
+            .transaction(|conn| {
+                async move {
+                    let reverted_blocks = schema::block::table
+                        .filter(schema::block::chain_id.eq(chain_id))
+                        .filter(schema::block::number.gt(block.number))
+                        .filter(schema::block::main.eq(true))
+                        .order_by(schema::block::number)
+                        .select(schema::block::hash)
+                        .get_results::<BlockHash>(conn)
+                        .await?;
+                    let mut reverted_contracts = schema::contract_storage::table
+                        .inner_join(schema::account::table)
+                        .filter(schema::contract_storage::chain_id.eq(chain_id))
+                        .filter(schema::contract_storage::valid_from.gt(block.ts))
+                        .select(schema::account::address)
+                        .distinct()
+                        .get_results::<Address>(conn)
+                        .await?;
+                    reverted_contracts.extend(
+                        schema::account_balance::table
+                            .inner_join(schema::account::table)
+                            .filter(schema::account::chain_id.eq(chain_id))
+                            .filter(schema::account_balance::valid_from.gt(block.ts))
+                            .select(schema::account::address)
+                            .distinct()
+                            .get_results::<Address>(conn)
+                            .await?,
+                    );
+                    reverted_contracts.extend(
+                        schema::contract_code::table
+                            .inner_join(schema::account::table)
+                            .filter(schema::account::chain_id.eq(chain_id))
+                            .filter(schema::contract_code::valid_from.gt(block.ts))
+                            .select(schema::account::address)
+                            .distinct()
+                            .get_results::<Address>(conn)
+                            .await?,
+                    );
+                    reverted_contracts.extend(
+                        schema::account::table
+                            .filter(schema::account::chain_id.eq(chain_id))
+                            .filter(schema::account::deleted_at.gt(block.ts))
+                            .filter(schema::account::deleted_at.lt(MAX_TS))
+                            .select(schema::account::address)
+                            .get_results::<Address>(conn)
+                            .await?,
+                    );
+                    reverted_contracts.sort_unstable();
+                    reverted_contracts.dedup();
+
+                    // contract storage and its current slot snapshot
+                    revert_contract_storage(chain_id, block.ts, conn).await?;
+
+                    // contract balances, the current version has no valid_to
+                    diesel::delete(
+                        schema::account_balance::table
+                            .filter(schema::account_balance::account_id.eq_any(accounts()))
+                            .filter(schema::account_balance::valid_from.gt(block.ts)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    diesel::update(
+                        schema::account_balance::table
+                            .filter(schema::account_balance::account_id.eq_any(accounts()))
+                            .filter(schema::account_balance::valid_to.gt(block.ts)),
+                    )
+                    .set(schema::account_balance::valid_to.eq(None::<chrono::NaiveDateTime>))
+                    .execute(conn)
+                    .await?;
+
+                    // contract code, the current version has no valid_to
+                    diesel::delete(
+                        schema::contract_code::table
+                            .filter(schema::contract_code::account_id.eq_any(accounts()))
+                            .filter(schema::contract_code::valid_from.gt(block.ts)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    diesel::update(
+                        schema::contract_code::table
+                            .filter(schema::contract_code::account_id.eq_any(accounts()))
+                            .filter(schema::contract_code::valid_to.gt(block.ts)),
+                    )
+                    .set(schema::contract_code::valid_to.eq(None::<chrono::NaiveDateTime>))
+                    .execute(conn)
+                    .await?;
+
+                    // reverted deletions are reset to MAX_TS, like in `revert_state`
+                    diesel::update(
+                        schema::account::table
+                            .filter(schema::account::chain_id.eq(chain_id))
+                            .filter(schema::account::deleted_at.gt(block.ts)),
+                    )
+                    .set(schema::account::deleted_at.eq(MAX_TS))
+                    .execute(conn)
+                    .await?;
+                    diesel::update(
+                        schema::protocol_component::table
+                            .filter(schema::protocol_component::chain_id.eq(chain_id))
+                            .filter(schema::protocol_component::deleted_at.gt(block.ts)),
+                    )
+                    .set(schema::protocol_component::deleted_at.eq(MAX_TS))
+                    .execute(conn)
+                    .await?;
+
+                    // component balances and protocol states
+                    let component_ids = schema::protocol_component::table
+                        .filter(schema::protocol_component::chain_id.eq(chain_id))
+                        .select(schema::protocol_component::id)
+                        .get_results::<i64>(conn)
+                        .await?;
+                    revert_component_balances(&component_ids, block.ts, conn).await?;
+                    revert_protocol_states(&component_ids, block.ts, conn).await?;
+
+                    diesel::update(
+                        schema::block::table
+                            .filter(schema::block::chain_id.eq(chain_id))
+                            .filter(schema::block::number.gt(block.number)),
+                    )
+                    .set(schema::block::main.eq(false))
+                    .execute(conn)
+                    .await?;
+
+                    Ok::<ReorgEvent, PostgresError>(ReorgEvent {
+                        chain: *chain,
+                        common_ancestor: block.hash,
+                        reverted_blocks,
+                        reverted_contracts,
+                    })
+                }
+                .scope_boxed()
+            })
+            .await?;
+        self.clear_block_ts_cache();
+        Ok(event)
+    }
+}
+
+/// Builds a `Transaction` from its db representation.
+///
+/// Transaction indices are block-local and can never be negative, a negative index
+/// signals a corrupt row and is reported as a `DecodeError`.
+fn transaction_from_orm(
+    mut orm_tx: orm::Transaction,
+    block_hash: BlockHash,
+) -> Result<Transaction, StorageError> {
+    let index = u64::try_from(orm_tx.index).map_err(|_| {
+        StorageError::DecodeError(format!(
+            "Transaction {} has invalid index {}",
+            orm_tx.hash, orm_tx.index
+        ))
+    })?;
+    Ok(Transaction {
+        hash: std::mem::take(&mut orm_tx.hash),
+        block_hash,
+        from: std::mem::take(&mut orm_tx.from),
+        // contract creations are stored with an empty recipient, see `upsert_tx`
+        to: Some(std::mem::take(&mut orm_tx.to)).filter(|to| !to.is_empty()),
+        index,
+    })
 }
 
 #[cfg(test)]
@@ -267,8 +815,9 @@ mod test {
         db_fixtures::{yesterday_midnight, yesterday_one_am},
     };
     use diesel_async::AsyncConnection;
+    use rstest::rstest;
     use std::{str::FromStr, time::Duration};
-    use tycho_core::models::Chain;
+    use tycho_core::models::{Chain, ChangeType};
 
     use super::*;
 
@@ -309,6 +858,21 @@ mod test {
         )
     }
 
+    #[rstest]
+    #[case::ethereum(Chain::Ethereum)]
+    #[case::arbitrum(Chain::Arbitrum)]
+    #[case::base(Chain::Base)]
+    #[tokio::test]
+    async fn test_chain_from_id(#[case] chain: Chain) {
+        let mut conn = setup_db().await;
+        let chain_id = db_fixtures::insert_chain(&mut conn, &chain.to_string()).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        assert_eq!(gw.get_chain_id(&chain), chain_id);
+        assert_eq!(gw.chain_from_id(chain_id), Some(chain));
+        assert_eq!(gw.chain_from_id(chain_id + 1), None);
+    }
+
     #[tokio::test]
     async fn test_get_block_latest() {
         let mut conn = setup_db().await;
@@ -341,6 +905,205 @@ mod test {
         assert_eq!(block, exp);
     }
 
+    #[tokio::test]
+    async fn test_get_block_by_hash() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let exp = block("0xb495a1d7e6663152ae92708da4843337b958146015a2802f4193a410044698c9");
+        let block_id = BlockIdentifier::Hash(exp.hash.clone());
+
+        let block = gw
+            .get_block(&block_id, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(block, exp);
+    }
+
+    #[tokio::test]
+    async fn test_versions_to_ts() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let ts = yesterday_one_am() + Duration::from_secs(60);
+        let versions = [
+            BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2))),
+            BlockOrTimestamp::Timestamp(ts),
+            BlockOrTimestamp::Block(BlockIdentifier::Hash(Bytes::from(
+                "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6",
+            ))),
+        ];
+
+        let res = gw
+            .versions_to_ts(&versions, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(res, vec![yesterday_one_am(), ts, yesterday_midnight()]);
+    }
+
+    #[tokio::test]
+    async fn test_versions_to_ts_not_found() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let versions = [
+            BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2))),
+            BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 99))),
+        ];
+
+        let res = gw
+            .versions_to_ts(&versions, &mut conn)
+            .await;
+
+        assert!(matches!(res, Err(StorageError::NotFound(_, _))));
+    }
+
+    #[rstest]
+    #[case::hash(BlockIdentifier::Hash(Bytes::from("0xbadbabe0")))]
+    #[case::number(BlockIdentifier::Number((Chain::Ethereum, 99)))]
+    #[tokio::test]
+    async fn test_get_block_not_found(#[case] block_id: BlockIdentifier) {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw.get_block(&block_id, &mut conn).await;
+
+        assert!(matches!(res, Err(StorageError::NotFound(_, _))));
+    }
+
+    #[rstest]
+    #[case::main_only(true, "0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6")]
+    #[case::any(false, "0xb495a1d7e6663152ae92708da4843337b958146015a2802f4193a410044698c9")]
+    #[tokio::test]
+    async fn test_get_latest_block(#[case] main_only: bool, #[case] exp_hash: &str) {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        diesel::update(schema::block::table.filter(schema::block::number.eq(2)))
+            .set(schema::block::main.eq(false))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let res = gw
+            .get_latest_block(&Chain::Ethereum, main_only, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(res.hash, Bytes::from(exp_hash));
+    }
+
+    #[tokio::test]
+    async fn test_get_latest_block_not_found() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        db_fixtures::insert_chain(&mut conn, "starknet").await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_latest_block(&Chain::Starknet, false, &mut conn)
+            .await;
+
+        assert!(matches!(res, Err(StorageError::NotFound(_, _))));
+    }
+
+    #[rstest]
+    #[case::head_minus_three(3, Ok(2))]
+    #[case::head(0, Ok(5))]
+    #[case::too_deep(5, Err(()))]
+    #[tokio::test]
+    async fn test_get_finalized_block(
+        #[case] min_confirmations: u64,
+        #[case] exp: Result<u64, ()>,
+    ) {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let blocks = (3..=5)
+            .map(|number| {
+                let mut b =
+                    block("0xbadbabe000000000000000000000000000000000000000000000000000000000");
+                b.number = number;
+                b.hash = Bytes::from(vec![number as u8; 32]);
+                b
+            })
+            .collect::<Vec<_>>();
+        gw.upsert_block(&blocks, &mut conn)
+            .await
+            .unwrap();
+
+        let res = gw
+            .get_finalized_block(&Chain::Ethereum, min_confirmations, &mut conn)
+            .await;
+
+        match exp {
+            Ok(number) => assert_eq!(res.unwrap().number, number),
+            Err(()) => assert!(matches!(res, Err(StorageError::NotFound(_, _)))),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reorg_depth() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let ancestor =
+            Bytes::from("0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6");
+        let old_2 =
+            Bytes::from("0xb495a1d7e6663152ae92708da4843337b958146015a2802f4193a410044698c9");
+        let fork_block = |number: u64, hash: u8, parent: Bytes| {
+            Block::new(
+                number,
+                Chain::Ethereum,
+                Bytes::from(vec![hash; 32]),
+                parent,
+                yesterday_one_am(),
+            )
+        };
+        let old_3 = fork_block(3, 3, old_2);
+        let new_2 = fork_block(2, 12, ancestor.clone());
+        let new_3 = fork_block(3, 13, new_2.hash.clone());
+        let new_4 = fork_block(4, 14, new_3.hash.clone());
+        gw.upsert_block(&[old_3.clone(), new_2, new_3, new_4.clone()], &mut conn)
+            .await
+            .unwrap();
+
+        let res = gw
+            .reorg_depth(&Chain::Ethereum, &old_3.hash, &new_4.hash, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(res, (ancestor, 2));
+    }
+
+    #[tokio::test]
+    async fn test_reorg_depth_no_common_ancestor() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let old_head =
+            Bytes::from("0xb495a1d7e6663152ae92708da4843337b958146015a2802f4193a410044698c9");
+        let orphan = Block::new(
+            2,
+            Chain::Ethereum,
+            Bytes::from(vec![2u8; 32]),
+            Bytes::from(vec![1u8; 32]),
+            yesterday_one_am(),
+        );
+        gw.upsert_block(std::slice::from_ref(&orphan), &mut conn)
+            .await
+            .unwrap();
+
+        let res = gw
+            .reorg_depth(&Chain::Ethereum, &old_head, &orphan.hash, &mut conn)
+            .await;
+
+        assert!(matches!(res, Err(StorageError::Unexpected(_))));
+    }
+
     #[tokio::test]
     async fn test_add_block() {
         let mut conn = setup_db().await;
@@ -359,6 +1122,26 @@ mod test {
         assert_eq!(retrieved_block, block);
     }
 
+    #[tokio::test]
+    async fn test_insert_blocks_twice() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let block = block("0xbadbabe000000000000000000000000000000000000000000000000000000000");
+
+        let first = gw
+            .insert_blocks(std::slice::from_ref(&block), &mut conn)
+            .await
+            .unwrap();
+        let second = gw
+            .insert_blocks(&[block], &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert!(second.is_empty());
+    }
+
     #[tokio::test]
     async fn test_upsert_block() {
         let mut conn = setup_db().await;
@@ -395,6 +1178,431 @@ mod test {
         }
     }
 
+    fn new_block_changes() -> (Block, Transaction) {
+        let block = Block::new(
+            3,
+            Chain::Ethereum,
+            Bytes::from("0x3d6122660cc824376f11ee842f83addc3525e2dd6756b9bcf0affa6aa88cf741"),
+            Bytes::from("0xb495a1d7e6663152ae92708da4843337b958146015a2802f4193a410044698c9"),
+            yesterday_one_am() + Duration::from_secs(3600),
+        );
+        let tx = Transaction {
+            hash: Bytes::from("0x94e3c5bb85c8cdd6d5d6a5f7bdf8f12ae8b4f7ec6d5f6aa3d6b1ba6af3b3c00d"),
+            block_hash: block.hash.clone(),
+            from: Bytes::from("0x4648451b5f87ff8f0f7d622bd40574bb97e25980"),
+            to: Some(Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f")),
+            index: 1,
+        };
+        (block, tx)
+    }
+
+    #[tokio::test]
+    async fn test_apply_block_changes() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let (block, tx) = new_block_changes();
+
+        gw.apply_block_changes(&block, std::slice::from_ref(&tx), &[], &[], &[], &mut conn)
+            .await
+            .unwrap();
+
+        let stored_block = gw
+            .get_block(&BlockIdentifier::Hash(block.hash.clone()), &mut conn)
+            .await
+            .unwrap();
+        let stored_tx = gw
+            .get_tx(&tx.hash, &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(stored_block, block);
+        assert_eq!(stored_tx, tx);
+    }
+
+    #[tokio::test]
+    async fn test_apply_block_changes_rolls_back() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let (block, tx) = new_block_changes();
+        // references a token that is not stored
+        let balance = ComponentBalance::new(
+            Bytes::from("0x0000000000000000000000000000000000000bad"),
+            Bytes::from(100u64).lpad(32, 0),
+            100.0,
+            tx.hash.clone(),
+            "state1",
+        );
+
+        let res = gw
+            .apply_block_changes(&block, std::slice::from_ref(&tx), &[], &[], &[balance], &mut conn)
+            .await;
+
+        assert!(matches!(res, Err(StorageError::NotFound(entity, _)) if entity == "Token"));
+        let stored_block = gw
+            .get_block(&BlockIdentifier::Hash(block.hash.clone()), &mut conn)
+            .await;
+        let stored_tx = gw.get_tx(&tx.hash, &mut conn).await;
+        assert!(matches!(stored_block, Err(StorageError::NotFound(_, _))));
+        assert!(matches!(stored_tx, Err(StorageError::NotFound(_, _))));
+    }
+
+    #[tokio::test]
+    async fn test_apply_block_changes_twice() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let chain_id = EVMGateway::from_connection(&mut conn)
+            .await
+            .get_chain_id(&Chain::Ethereum);
+        let tx_id = schema::transaction::table
+            .select(schema::transaction::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        let address = Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f");
+        let account_id = db_fixtures::insert_account(
+            &mut conn,
+            "6b175474e89094c44da98b954eedeac495271d0f",
+            "account0",
+            chain_id,
+            None,
+        )
+        .await;
+        let system_id = db_fixtures::insert_protocol_system(&mut conn, "ambient".to_owned()).await;
+        let type_id = db_fixtures::insert_protocol_type(&mut conn, "Pool", None, None, None).await;
+        let (_, weth_id) = db_fixtures::insert_token(
+            &mut conn,
+            chain_id,
+            "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "WETH",
+            18,
+            None,
+        )
+        .await;
+        let component_id = db_fixtures::insert_protocol_component(
+            &mut conn,
+            "pool",
+            chain_id,
+            system_id,
+            type_id,
+            tx_id,
+            Some(vec![weth_id]),
+            None,
+        )
+        .await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let (block, tx) = new_block_changes();
+        let word = |v: u64| Bytes::from(v).lpad(32, 0);
+        let delta = AccountDelta::new(
+            Chain::Ethereum,
+            address,
+            [(word(1), Some(word(10)))]
+                .into_iter()
+                .collect(),
+            Some(word(100)),
+            Some(Bytes::from("0x1234")),
+            ChangeType::Update,
+        );
+        let state_delta = ProtocolComponentStateDelta::new(
+            "pool",
+            [("reserve".to_owned(), word(1))].into(),
+            Default::default(),
+        );
+        let balance = ComponentBalance::new(
+            Bytes::from("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+            word(1),
+            1.0,
+            tx.hash.clone(),
+            "pool",
+        );
+        let account_deltas = [(tx.hash.clone(), delta)];
+        let state_deltas = [(tx.hash.clone(), state_delta)];
+
+        for _ in 0..2 {
+            gw.apply_block_changes(
+                &block,
+                std::slice::from_ref(&tx),
+                &account_deltas,
+                &state_deltas,
+                std::slice::from_ref(&balance),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+        }
+
+        let n_blocks: i64 = schema::block::table
+            .filter(schema::block::hash.eq(&block.hash))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let n_txns: i64 = schema::transaction::table
+            .filter(schema::transaction::hash.eq(&tx.hash))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let n_slots: i64 = schema::contract_storage::table
+            .filter(schema::contract_storage::account_id.eq(account_id))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let n_balances: i64 = schema::account_balance::table
+            .filter(schema::account_balance::account_id.eq(account_id))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let n_code: i64 = schema::contract_code::table
+            .filter(schema::contract_code::account_id.eq(account_id))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let n_states: i64 = schema::protocol_state::table
+            .filter(schema::protocol_state::protocol_component_id.eq(component_id))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let n_component_balances: i64 = schema::component_balance::table
+            .filter(schema::component_balance::protocol_component_id.eq(component_id))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!((n_blocks, n_txns, n_slots), (1, 1, 1));
+        assert_eq!((n_balances, n_code, n_states, n_component_balances), (1, 1, 1, 1));
+    }
+
+    #[tokio::test]
+    async fn test_revert_to_block() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let chain_id = EVMGateway::from_connection(&mut conn)
+            .await
+            .get_chain_id(&Chain::Ethereum);
+        let tx_id = schema::transaction::table
+            .select(schema::transaction::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        let account_id = db_fixtures::insert_account(
+            &mut conn,
+            "6b175474e89094c44da98b954eedeac495271d0f",
+            "account0",
+            chain_id,
+            None,
+        )
+        .await;
+        let system_id = db_fixtures::insert_protocol_system(&mut conn, "ambient".to_owned()).await;
+        let type_id = db_fixtures::insert_protocol_type(&mut conn, "Pool", None, None, None).await;
+        let (_, weth_id) = db_fixtures::insert_token(
+            &mut conn,
+            chain_id,
+            "c02aaa39b223fe8d0a0e5c4f27ead9083c756cc2",
+            "WETH",
+            18,
+            None,
+        )
+        .await;
+        let component_id = db_fixtures::insert_protocol_component(
+            &mut conn,
+            "pool",
+            chain_id,
+            system_id,
+            type_id,
+            tx_id,
+            Some(vec![weth_id]),
+            None,
+        )
+        .await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let (block_a, tx_a) = new_block_changes();
+        let block_b = Block::new(
+            4,
+            Chain::Ethereum,
+            Bytes::from("0x6c6e5a3e7c7f6c1d16e2b1f0d5b8c1f6a1e0d9c8b7a6f5e4d3c2b1a09f8e7d6c"),
+            block_a.hash.clone(),
+            block_a.ts + Duration::from_secs(12),
+        );
+        let tx_b = Transaction {
+            hash: Bytes::from("0x2d3a4b5c6d7e8f9a0b1c2d3e4f5a6b7c8d9e0f1a2b3c4d5e6f7a8b9c0d1e2f3a"),
+            block_hash: block_b.hash.clone(),
+            ..tx_a.clone()
+        };
+        let word = |v: u64| Bytes::from(v).lpad(32, 0);
+        for (block, tx, value) in [(&block_a, &tx_a, 1), (&block_b, &tx_b, 2)] {
+            let account_delta = AccountDelta::new(
+                Chain::Ethereum,
+                Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f"),
+                [(word(1), Some(word(10 * value))), (word(value + 1), Some(word(value)))]
+                    .into_iter()
+                    .collect(),
+                Some(word(100 * value)),
+                Some(Bytes::from(vec![value as u8; 4])),
+                ChangeType::Update,
+            );
+            let state_delta = ProtocolComponentStateDelta::new(
+                "pool",
+                [("reserve".to_owned(), word(value))].into(),
+                Default::default(),
+            );
+            let balance = ComponentBalance::new(
+                Bytes::from("0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2"),
+                word(value),
+                value as f64,
+                tx.hash.clone(),
+                "pool",
+            );
+            gw.apply_block_changes(
+                block,
+                std::slice::from_ref(tx),
+                &[(tx.hash.clone(), account_delta)],
+                &[(tx.hash.clone(), state_delta)],
+                &[balance],
+                &mut conn,
+            )
+            .await
+            .unwrap();
+        }
+        diesel::update(schema::account::table.filter(schema::account::id.eq(account_id)))
+            .set(schema::account::deleted_at.eq(block_b.ts))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        diesel::update(
+            schema::protocol_component::table
+                .filter(schema::protocol_component::id.eq(component_id)),
+        )
+        .set(schema::protocol_component::deleted_at.eq(block_b.ts))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+
+        let event = gw
+            .revert_to_block(
+                &Chain::Ethereum,
+                &BlockIdentifier::Hash(block_a.hash.clone()),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        let slots: HashMap<Bytes, Option<Bytes>> = schema::contract_storage::table
+            .filter(schema::contract_storage::account_id.eq(account_id))
+            .filter(schema::contract_storage::valid_to.eq(MAX_TS))
+            .select((schema::contract_storage::slot, schema::contract_storage::value))
+            .get_results(&mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+        let current_slots: HashMap<Bytes, Option<Bytes>> = schema::contract_storage_current::table
+            .filter(schema::contract_storage_current::account_id.eq(account_id))
+            .select((
+                schema::contract_storage_current::slot,
+                schema::contract_storage_current::value.nullable(),
+            ))
+            .get_results(&mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .collect();
+        let account_balance: Bytes = schema::account_balance::table
+            .filter(schema::account_balance::account_id.eq(account_id))
+            .filter(schema::account_balance::valid_to.is_null())
+            .select(schema::account_balance::balance)
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let component_balance: Bytes = schema::component_balance::table
+            .filter(schema::component_balance::protocol_component_id.eq(component_id))
+            .filter(schema::component_balance::valid_to.eq(MAX_TS))
+            .select(schema::component_balance::new_balance)
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let reserve: Bytes = schema::protocol_state::table
+            .filter(schema::protocol_state::protocol_component_id.eq(component_id))
+            .filter(schema::protocol_state::valid_to.eq(MAX_TS))
+            .select(schema::protocol_state::attribute_value)
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let code: Bytes = schema::contract_code::table
+            .inner_join(schema::code::table)
+            .filter(schema::contract_code::account_id.eq(account_id))
+            .filter(schema::contract_code::valid_to.is_null())
+            .select(schema::code::bytecode)
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let account_deleted_at: Option<NaiveDateTime> = schema::account::table
+            .filter(schema::account::id.eq(account_id))
+            .select(schema::account::deleted_at)
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let component_deleted_at: Option<NaiveDateTime> = schema::protocol_component::table
+            .filter(schema::protocol_component::id.eq(component_id))
+            .select(schema::protocol_component::deleted_at)
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        let canonical: Vec<(i64, bool)> = schema::block::table
+            .filter(schema::block::number.ge(3))
+            .order_by(schema::block::number)
+            .select((schema::block::number, schema::block::main))
+            .get_results(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            slots,
+            [(word(1), Some(word(10))), (word(2), Some(word(1)))]
+                .into_iter()
+                .collect()
+        );
+        assert_eq!(current_slots, slots);
+        assert_eq!(account_balance, word(100));
+        assert_eq!(component_balance, word(1));
+        assert_eq!(reserve, word(1));
+        assert_eq!(code, Bytes::from(vec![1u8; 4]));
+        assert_eq!(account_deleted_at, Some(MAX_TS));
+        assert_eq!(component_deleted_at, Some(MAX_TS));
+        assert_eq!(canonical, vec![(3, true), (4, false)]);
+        assert_eq!(
+            event,
+            ReorgEvent {
+                chain: Chain::Ethereum,
+                common_ancestor: block_a.hash.clone(),
+                reverted_blocks: vec![block_b.hash.clone()],
+                reverted_contracts: vec![Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f")],
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn test_revert_to_block_other_chain() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        db_fixtures::insert_chain(&mut conn, "arbitrum").await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .revert_to_block(
+                &Chain::Arbitrum,
+                &BlockIdentifier::Number((Chain::Ethereum, 1)),
+                &mut conn,
+            )
+            .await;
+
+        assert!(matches!(res, Err(StorageError::NotFound(_, _))));
+    }
+
     #[tokio::test]
     async fn test_get_tx() {
         let mut conn = setup_db().await;
@@ -431,6 +1639,117 @@ mod test {
         assert_eq!(tx, retrieved_tx);
     }
 
+    #[tokio::test]
+    async fn test_get_transactions() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let block_hash =
+            Bytes::from("0x88e96d4537bea4d9c05d12549907b32561d3bf31f45aae734cdc119f13406cb6");
+        let mut tx =
+            transaction("0xbadbabe000000000000000000000000000000000000000000000000000000000");
+        tx.block_hash = block_hash.clone();
+        tx.index = 0;
+        gw.upsert_tx(&[tx.clone()], &mut conn)
+            .await
+            .unwrap();
+        let exp = vec![
+            tx,
+            transaction("0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945"),
+        ];
+
+        let res = gw
+            .get_transactions(&BlockIdentifier::Hash(block_hash), &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(res, exp);
+    }
+
+    #[tokio::test]
+    async fn test_get_transactions_empty_block() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let block_hash =
+            Bytes::from("0xb495a1d7e6663152ae92708da4843337b958146015a2802f4193a410044698c9");
+
+        let res = gw
+            .get_transactions(&BlockIdentifier::Hash(block_hash), &mut conn)
+            .await
+            .unwrap();
+
+        assert!(res.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_count_transactions() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let mut tx =
+            transaction("0xbadbabe000000000000000000000000000000000000000000000000000000000");
+        tx.index = 0;
+        gw.upsert_tx(&[tx], &mut conn)
+            .await
+            .unwrap();
+
+        let res = gw
+            .count_transactions(
+                &Chain::Ethereum,
+                &BlockIdentifier::Number((Chain::Ethereum, 1)),
+                &BlockIdentifier::Number((Chain::Ethereum, 2)),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res, vec![(1, 2), (2, 0)]);
+    }
+
+    #[tokio::test]
+    async fn test_count_transactions_skips_forked_blocks() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let forked_block_id = diesel::insert_into(schema::block::table)
+            .values((
+                schema::block::hash.eq(Bytes::from(
+                    "0xf00d000000000000000000000000000000000000000000000000000000000000",
+                )),
+                schema::block::parent_hash.eq(Bytes::zero(32)),
+                schema::block::number.eq(1),
+                schema::block::ts.eq(yesterday_midnight()),
+                schema::block::chain_id.eq(gw.get_chain_id(&Chain::Ethereum)),
+                schema::block::main.eq(false),
+            ))
+            .returning(schema::block::id)
+            .get_result::<i64>(&mut conn)
+            .await
+            .unwrap();
+        db_fixtures::insert_txns(
+            &mut conn,
+            &[(
+                forked_block_id,
+                0,
+                "0xf00d000000000000000000000000000000000000000000000000000000000001",
+            )],
+        )
+        .await;
+
+        let res = gw
+            .count_transactions(
+                &Chain::Ethereum,
+                &BlockIdentifier::Number((Chain::Ethereum, 1)),
+                &BlockIdentifier::Number((Chain::Ethereum, 2)),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res, vec![(1, 1), (2, 0)]);
+    }
+
     #[tokio::test]
     async fn test_upsert_tx() {
         let mut conn = setup_db().await;
@@ -606,4 +1925,52 @@ mod test {
             .unwrap();
         assert_eq!(c1.len(), 0);
     }
+
+    fn orm_tx(index: i64) -> orm::Transaction {
+        let ts = chrono::NaiveDateTime::default();
+        orm::Transaction {
+            id: 1,
+            hash: Bytes::from("0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945"),
+            block_id: 1,
+            from: Bytes::from("0x4648451b5f87ff8f0f7d622bd40574bb97e25980"),
+            to: Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f"),
+            index,
+            inserted_ts: ts,
+            modified_ts: ts,
+        }
+    }
+
+    #[test]
+    fn test_transaction_from_orm() {
+        let block_hash = Bytes::from(vec![1u8; 32]);
+
+        let res = transaction_from_orm(orm_tx(1), block_hash.clone()).unwrap();
+
+        assert_eq!(res.index, 1);
+        assert_eq!(res.block_hash, block_hash);
+    }
+
+    #[test]
+    fn test_transaction_from_orm_contract_creation() {
+        let block_hash = Bytes::from(vec![1u8; 32]);
+        let mut creation = orm_tx(1);
+        creation.to = Bytes::default();
+        let mut zero_recipient = orm_tx(1);
+        zero_recipient.to = Bytes::zero(20);
+
+        let creation = transaction_from_orm(creation, block_hash.clone()).unwrap();
+        let zero_recipient = transaction_from_orm(zero_recipient, block_hash).unwrap();
+
+        assert!(creation.is_contract_creation());
+        assert_eq!(creation.to, None);
+        assert!(!zero_recipient.is_contract_creation());
+        assert_eq!(zero_recipient.to, Some(Bytes::zero(20)));
+    }
+
+    #[test]
+    fn test_transaction_from_orm_negative_index() {
+        let res = transaction_from_orm(orm_tx(-1), Bytes::from(vec![1u8; 32]));
+
+        assert!(matches!(res, Err(StorageError::DecodeError(_))));
+    }
 }
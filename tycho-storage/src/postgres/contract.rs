@@ -1,26 +1,55 @@
 use super::{
-    maybe_lookup_block_ts, maybe_lookup_version_ts, orm, schema, storage_error_from_diesel,
+    maybe_lookup_version_ts, orm, schema, storage_error_from_diesel,
     versioning::{apply_partitioned_versioning, apply_versioning, VersioningEntry},
-    PostgresError, PostgresGateway, WithOrdinal, WithTxHash, MAX_TS,
+    DeltaQueryStrategy, PostgresError, PostgresGateway, WithOrdinal, WithTxHash, MAX_TS,
 };
 use chrono::{NaiveDateTime, Utc};
 use diesel::{
+    dsl::InnerJoinQuerySource,
+    expression::{is_aggregate, ValidGrouping},
+    pg::Pg,
     prelude::*,
+    query_builder::{AstPass, BoxedSqlQuery, Query, QueryFragment, QueryId, SqlQuery},
+    sql_query,
+    sql_types::{Array, BigInt, Bool, Bytea, Nullable, Text, Timestamptz},
     upsert::{excluded, on_constraint},
 };
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
-use std::collections::{hash_map::Entry, HashMap, HashSet};
-use tracing::{debug, error, instrument, Level};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
+use std::{
+    collections::{hash_map::Entry, HashMap, HashSet},
+    time::Instant,
+};
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tracing::{debug, error, instrument, Level, Span};
 use tycho_core::{
     keccak256,
     models::{
         self, contract::AccountDelta, AccountToContractStore, Address, Balance, Chain, ChangeType,
-        Code, ContractId, ContractStore, PaginationParams, StoreKey, StoreVal, TxHash,
+        Code, CodeHash, ContractId, ContractStore, OrderedContractStore, PaginationParams,
+        StoreKey, StoreVal, TxHash,
+    },
+    storage::{
+        BlockIdentifier, BlockOrTimestamp, ChangeCursor, StorageError, Version, VersionKind,
+        WithTotal,
     },
-    storage::{BlockOrTimestamp, StorageError, Version, WithTotal},
     Bytes,
 };
 
+/// Number of accounts fetched per batch by [`PostgresGateway::export_contracts`].
+const EXPORT_BATCH_SIZE: i64 = 1000;
+
+/// A single change to a contract, as read by [`PostgresGateway::get_changes_since`].
+enum ContractChange {
+    Slot(StoreKey, Option<StoreVal>),
+    Balance(Balance),
+    Code(Code),
+}
+
+/// A single slot write: `(modify_tx, address, slot, value)`.
+type SlotRow = (i64, Address, StoreKey, Option<StoreVal>);
+
 struct CreatedOrDeleted<T> {
     /// Accounts that were created (and deltas are equal to their updates)
     created: HashSet<Address>,
@@ -136,6 +165,7 @@ impl PostgresGateway {
 
             contract_code
                 .inner_join(schema::transaction::table)
+                .inner_join(schema::code::table)
                 .filter(account_id.eq_any(changed_account_ids))
                 .filter(valid_from.le(target_version_ts))
                 .filter(
@@ -143,7 +173,7 @@ impl PostgresGateway {
                         .gt(target_version_ts)
                         .or(valid_to.is_null()),
                 )
-                .select((account_id, code))
+                .select((account_id, schema::code::bytecode))
                 .order_by((account_id, valid_from.desc(), schema::transaction::index.desc()))
                 .distinct_on(account_id)
                 .get_results::<(i64, Code)>(conn)
@@ -163,6 +193,7 @@ impl PostgresGateway {
 
             contract_code
                 .inner_join(schema::transaction::table)
+                .inner_join(schema::code::table)
                 .filter(account_id.eq_any(changed_account_ids))
                 .filter(valid_from.le(target_version_ts))
                 .filter(
@@ -170,7 +201,7 @@ impl PostgresGateway {
                         .gt(target_version_ts)
                         .or(valid_to.is_null()),
                 )
-                .select((account_id, code))
+                .select((account_id, schema::code::bytecode))
                 .order_by((account_id, valid_from.asc(), schema::transaction::index.asc()))
                 .distinct_on(account_id)
                 .get_results::<(i64, Code)>(conn)
@@ -191,7 +222,11 @@ impl PostgresGateway {
     /// This method returns a mapping from each account id to a `ContractStore`.
     /// The returned store entries indicate the updates needed to reach the specified target
     /// version.
-    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    #[instrument(
+        level = Level::DEBUG,
+        skip(self, conn),
+        fields(chain = %self.get_chain(&chain_id), direction)
+    )]
     async fn get_slots_delta(
         &self,
         chain_id: i64,
@@ -199,83 +234,108 @@ impl PostgresGateway {
         target_version_ts: &NaiveDateTime,
         conn: &mut AsyncPgConnection,
     ) -> Result<HashMap<i64, ContractStore>, StorageError> {
+        let direction = if start_version_ts <= target_version_ts { "forward" } else { "backward" };
+        Span::current().record("direction", direction);
+        let start = Instant::now();
+        let changed_values = self
+            .get_slots_delta_rows(chain_id, None, start_version_ts, target_version_ts, conn)
+            .await?;
+        self.record_query("slots_delta", start, changed_values.len());
+        debug!(rows = changed_values.len(), "Retrieved changed slots");
+
+        Ok(group_slot_rows(changed_values))
+    }
+
+    /// Retrieves the changed slot rows between two versions.
+    ///
+    /// If `filter` is given, only rows of the listed contract addresses and slots are
+    /// considered, which allows the database to skip most of the storage table.
+    ///
+    /// # Returns
+    /// A row per changed slot as `(account_id, slot, value)`, with value being the one needed
+    /// to reach the target version.
+    async fn get_slots_delta_rows(
+        &self,
+        chain_id: i64,
+        filter: Option<(&[Address], &[StoreKey])>,
+        start_version_ts: &NaiveDateTime,
+        target_version_ts: &NaiveDateTime,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<(i64, StoreKey, Option<StoreVal>)>, StorageError> {
+        if self.delta_query_strategy == DeltaQueryStrategy::WindowFunction {
+            return Ok(slots_delta_window_query(
+                chain_id,
+                filter,
+                start_version_ts,
+                target_version_ts,
+            )
+            .load::<SlotsDeltaRow>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .map(|row| (row.account_id, row.slot, row.value))
+            .collect());
+        }
+        let predicate = slots_delta_predicate(chain_id, filter);
         let changed_values = if start_version_ts <= target_version_ts {
-            // Going forward
-            //                  ]     changes to forward   ]
-            // -----------------|--------------------------|
-            //                start                     target
-            // We query for changes between start and target version. Then sort
-            // these by account and slot by change time in a descending manner
-            // (latest change first). Next we deduplicate by account and slot.
-            // Finally, we select the value column to give us the latest value
-            // within the version range.
-            schema::contract_storage::table
-                .inner_join(schema::account::table.inner_join(schema::chain::table))
-                .filter(schema::chain::id.eq(chain_id))
-                .filter(schema::contract_storage::valid_from.gt(start_version_ts))
-                .filter(schema::contract_storage::valid_from.le(target_version_ts))
-                .order_by((
-                    schema::account::id,
-                    schema::contract_storage::slot,
-                    schema::contract_storage::valid_from.desc(),
-                    schema::contract_storage::ordinal.desc(),
-                ))
-                .select((
-                    schema::account::id,
-                    schema::contract_storage::slot,
-                    schema::contract_storage::value,
-                ))
-                .distinct_on((schema::account::id, schema::contract_storage::slot))
-                .get_results::<(i64, StoreKey, Option<StoreVal>)>(conn)
-                .await
-                .map_err(PostgresError::from)?
+            slots_delta_forward_query(
+                schema::account::id,
+                predicate,
+                start_version_ts,
+                target_version_ts,
+            )
+            .get_results::<(i64, StoreKey, Option<StoreVal>)>(conn)
+            .await
+            .map_err(PostgresError::from)?
         } else {
-            // Going backwards
-            //                  ]     changes to revert    ]
-            // -----------------|--------------------------|
-            //                target                     start
-            // We query for changes between target and start version. Then sort
-            // these for each account and slot by change time in an ascending
-            // manner. Next, we deduplicate by taking the first row for each
-            // account and slot. Finally we select the previous_value column to
-            // give us the value before this first change within the version
-            // range.
-            schema::contract_storage::table
-                .inner_join(schema::account::table.inner_join(schema::chain::table))
-                .filter(schema::chain::id.eq(chain_id))
-                .filter(schema::contract_storage::valid_from.gt(target_version_ts))
-                .filter(schema::contract_storage::valid_from.le(start_version_ts))
-                .order_by((
-                    schema::account::id.asc(),
-                    schema::contract_storage::slot.asc(),
-                    schema::contract_storage::valid_from.asc(),
-                    schema::contract_storage::ordinal.asc(),
-                ))
-                .select((
-                    schema::account::id,
-                    schema::contract_storage::slot,
-                    schema::contract_storage::previous_value,
-                ))
-                .distinct_on((schema::account::id, schema::contract_storage::slot))
-                .get_results::<(i64, Bytes, Option<Bytes>)>(conn)
-                .await
-                .map_err(PostgresError::from)?
+            slots_delta_backward_query(
+                schema::account::id,
+                predicate,
+                start_version_ts,
+                target_version_ts,
+            )
+            .get_results::<(i64, StoreKey, Option<StoreVal>)>(conn)
+            .await
+            .map_err(PostgresError::from)?
         };
+        Ok(changed_values)
+    }
 
-        let mut result: HashMap<i64, ContractStore> = HashMap::new();
-        for (cid, raw_key, raw_val) in changed_values.into_iter() {
-            match result.entry(cid) {
-                Entry::Occupied(mut e) => {
-                    e.get_mut().insert(raw_key, raw_val);
-                }
-                Entry::Vacant(e) => {
-                    let mut contract_storage = HashMap::new();
-                    contract_storage.insert(raw_key, raw_val);
-                    e.insert(contract_storage);
-                }
-            }
-        }
-        Ok(result)
+    /// Returns the query plan Postgres chooses for the slot delta query between two versions.
+    ///
+    /// Meant for diagnostics: the slot delta query relies on a composite index over
+    /// `(account_id, slot)` of `contract_storage`, if this index is missing or unused, the
+    /// query degrades into sequential scans.
+    pub async fn explain_slots_delta(
+        &self,
+        chain: &Chain,
+        start_version_ts: &NaiveDateTime,
+        target_version_ts: &NaiveDateTime,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<String, StorageError> {
+        let predicate = slots_delta_predicate(self.get_chain_id(chain), None);
+        let plan = if start_version_ts <= target_version_ts {
+            Explain(slots_delta_forward_query(
+                schema::account::id,
+                predicate,
+                start_version_ts,
+                target_version_ts,
+            ))
+            .get_results::<String>(conn)
+            .await
+            .map_err(PostgresError::from)?
+        } else {
+            Explain(slots_delta_backward_query(
+                schema::account::id,
+                predicate,
+                start_version_ts,
+                target_version_ts,
+            ))
+            .get_results::<String>(conn)
+            .await
+            .map_err(PostgresError::from)?
+        };
+        Ok(plan.join("\n"))
     }
 
     /// Fetch deleted or created account deltas
@@ -459,9 +519,20 @@ impl PostgresGateway {
     /// slot modification is associated with the transaction that actually changed the slots.
     ///
     /// # Parameters
-    /// - `slots` A hashmap containing only the changed slots. Grouped first by the transaction
-    ///   database id that contained the changes, then by account address. Slots that were changed
-    ///   to 0 are expected to be included here.
+    /// - `rows` Only the changed slots, as `(modify_tx, address, slot, value)` rows where
+    ///   `modify_tx` is the database id of the transaction that contained the change. Writers feed
+    ///   slot iterators straight in, without grouping them into owned stores first. Slots that were
+    ///   changed to 0 are expected to be included here.
+    ///
+    /// Multiple changes to the same slot are versioned in execution order (block, then
+    /// transaction index). Each stored version uses the index of its modifying transaction as
+    /// `ordinal`, so changes within the same block remain ordered.
+    ///
+    /// The `previous_value` of each new version is derived automatically from the version it
+    /// replaces: either an earlier change within this batch or the currently stored latest value.
+    /// Backward deltas rely on this to restore prior slot values. The replaced version is closed
+    /// out by setting its `valid_to` to the new version's `valid_from`, so there is always at
+    /// most one current version per slot.
     ///
     /// # Returns
     /// An empty `Ok(())` if the operation succeeded. Will raise an error if any
@@ -470,10 +541,13 @@ impl PostgresGateway {
     #[instrument(level = Level::DEBUG, skip_all)]
     async fn upsert_slots(
         &self,
-        slots: HashMap<i64, AccountToContractStore>,
+        rows: Vec<SlotRow>,
         conn: &mut AsyncPgConnection,
     ) -> Result<(), StorageError> {
-        let txns: HashSet<_> = slots.keys().copied().collect();
+        let txns: HashSet<_> = rows
+            .iter()
+            .map(|(modify_tx, ..)| *modify_tx)
+            .collect();
         let tx_ids: HashMap<i64, (i64, NaiveDateTime)> = schema::transaction::table
             .inner_join(schema::block::table)
             .filter(schema::transaction::id.eq_any(txns))
@@ -484,21 +558,21 @@ impl PostgresGateway {
             .into_iter()
             .collect();
         #[allow(clippy::mutable_key_type)]
-        let accounts: HashSet<_> = slots
+        let accounts: HashSet<_> = rows
             .iter()
-            .flat_map(|(_, contract_slots)| contract_slots.keys())
+            .map(|(_, address, ..)| address)
             .collect();
-        let account_ids: HashMap<Bytes, i64> = schema::account::table
+        let account_ids: HashMap<Bytes, (i64, i64)> = schema::account::table
             .filter(schema::account::address.eq_any(accounts))
-            .select((schema::account::address, schema::account::id))
-            .get_results::<(Bytes, i64)>(conn)
+            .select((schema::account::address, (schema::account::id, schema::account::chain_id)))
+            .get_results::<(Bytes, (i64, i64))>(conn)
             .await
             .map_err(PostgresError::from)?
             .into_iter()
             .collect();
 
-        let mut new_entries = Vec::new();
-        for (modify_tx, contract_storage) in slots.iter() {
+        let mut new_entries = Vec::with_capacity(rows.len());
+        for (modify_tx, address, slot, value) in rows.iter() {
             let (tx_index, block_ts) = tx_ids.get(modify_tx).ok_or_else(|| {
                 StorageError::NoRelatedEntity(
                     "Transaction".into(),
@@ -506,33 +580,30 @@ impl PostgresGateway {
                     format!("{}", modify_tx),
                 )
             })?;
-            for (address, storage) in contract_storage.iter() {
-                let account_id = account_ids
-                    .get(address)
-                    .ok_or_else(|| {
-                        StorageError::NoRelatedEntity(
-                            "Account".into(),
-                            "ContractStorage".into(),
-                            hex::encode(address),
-                        )
-                    })?;
-                for (slot, value) in storage.iter() {
-                    new_entries.push(WithOrdinal::new(
-                        VersioningEntry::Update(orm::NewSlot {
-                            slot: slot.clone(),
-                            value: value.clone(),
-                            previous_value: None,
-                            account_id: *account_id,
-                            modify_tx: *modify_tx,
-                            // this is still required for delta queries
-                            ordinal: *tx_index,
-                            valid_from: *block_ts,
-                            valid_to: MAX_TS,
-                        }),
-                        (*account_id, slot, *block_ts, *tx_index),
-                    ))
-                }
-            }
+            let (account_id, chain_id) = account_ids
+                .get(address)
+                .ok_or_else(|| {
+                    StorageError::NoRelatedEntity(
+                        "Account".into(),
+                        "ContractStorage".into(),
+                        hex::encode(address),
+                    )
+                })?;
+            new_entries.push(WithOrdinal::new(
+                VersioningEntry::Update(orm::NewSlot {
+                    slot: slot.clone(),
+                    value: value.clone(),
+                    previous_value: None,
+                    account_id: *account_id,
+                    chain_id: *chain_id,
+                    modify_tx: *modify_tx,
+                    // this is still required for delta queries
+                    ordinal: *tx_index,
+                    valid_from: *block_ts,
+                    valid_to: MAX_TS,
+                }),
+                (*account_id, slot, *block_ts, *tx_index),
+            ))
         }
 
         debug!(n = new_entries.len(), "Inserting slots");
@@ -543,35 +614,22 @@ impl PostgresGateway {
             .collect::<Vec<_>>();
         let (latest, to_archive, _) =
             apply_partitioned_versioning(&sorted, self.retention_horizon, conn).await?;
-        let latest = latest
-            .into_iter()
-            .map(orm::NewSlotLatest::from)
-            .collect::<Vec<_>>();
-
-        for chunk in latest.chunks(1_000) {
-            diesel::insert_into(schema::contract_storage_default::table)
-                .values(chunk)
-                .on_conflict(on_constraint("contract_storage_default_unique_pk"))
-                .do_update()
-                .set((
-                    schema::contract_storage_default::slot
-                        .eq(excluded(schema::contract_storage_default::slot)),
-                    schema::contract_storage_default::value
-                        .eq(excluded(schema::contract_storage_default::value)),
-                    schema::contract_storage_default::previous_value
-                        .eq(excluded(schema::contract_storage_default::previous_value)),
-                    schema::contract_storage_default::account_id
-                        .eq(excluded(schema::contract_storage_default::account_id)),
-                    schema::contract_storage_default::modify_tx
-                        .eq(excluded(schema::contract_storage_default::modify_tx)),
-                    schema::contract_storage_default::ordinal
-                        .eq(excluded(schema::contract_storage_default::ordinal)),
-                    schema::contract_storage_default::valid_from
-                        .eq(excluded(schema::contract_storage_default::valid_from)),
-                ))
-                .execute(conn)
-                .await
-                .map_err(PostgresError::from)?;
+        let mut latest_by_chain: HashMap<i64, Vec<orm::NewSlot>> = HashMap::new();
+        for row in latest {
+            latest_by_chain
+                .entry(row.chain_id)
+                .or_default()
+                .push(row);
+        }
+        for (chain_id, rows) in latest_by_chain {
+            let partition = latest_slots_partition(chain_id, conn).await?;
+            for chunk in rows.chunks(1_000) {
+                upsert_latest_slots_query(&partition, chunk)
+                    .execute(conn)
+                    .await
+                    .map_err(PostgresError::from)?;
+            }
+            update_current_slots(&rows, conn).await?;
         }
 
         for chunk in to_archive.chunks(1_000) {
@@ -614,10 +672,10 @@ impl PostgresGateway {
         let slots = {
             use schema::{account, contract_storage::dsl::*};
 
-            let chain_id = self.get_chain_id(chain);
+            let chain_db_id = self.get_chain_id(chain);
             let mut q = contract_storage
                 .inner_join(account::table)
-                .filter(account::chain_id.eq(chain_id))
+                .filter(chain_id.eq(chain_db_id))
                 .filter(
                     valid_from
                         .le(version_ts)
@@ -644,6 +702,103 @@ impl PostgresGateway {
         Self::construct_account_to_contract_store(slots.into_iter(), accounts)
     }
 
+    /// Retrieves the current storage of a single contract.
+    ///
+    /// Reads the `contract_storage_current` snapshot, which holds one row per live slot and is
+    /// kept up to date by [`Self::upsert_slots`]. This avoids the range scan and deduplication
+    /// [`Self::get_contract_slots`] needs to reconstruct the latest state from the versioned
+    /// table. Deleted slots are not part of the snapshot and thus not returned.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn get_current_slots(
+        &self,
+        chain: &Chain,
+        address: &Address,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<ContractStore, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let account_id = schema::account::table
+            .filter(schema::account::chain_id.eq(chain_id))
+            .filter(schema::account::address.eq(address))
+            .select(schema::account::id)
+            .first::<i64>(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Account", &address.to_string(), None))?;
+        let slots = schema::contract_storage_current::table
+            .filter(schema::contract_storage_current::account_id.eq(account_id))
+            .select((
+                schema::contract_storage_current::slot,
+                schema::contract_storage_current::value,
+            ))
+            .get_results::<(StoreKey, StoreVal)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+        Ok(slots
+            .into_iter()
+            .map(|(slot, value)| (slot, Some(value)))
+            .collect())
+    }
+
+    /// Regenerates the `contract_storage_current` snapshot of a chain from the current
+    /// versions in `contract_storage`.
+    ///
+    /// The snapshot is maintained by [`Self::upsert_slots`] and reverts already, this is meant
+    /// to repair it, e.g. after storage was modified outside of the gateway. Runs within a
+    /// single transaction.
+    ///
+    /// # Returns
+    /// The number of live slots in the regenerated snapshot.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn rebuild_current_snapshot(
+        &self,
+        chain: &Chain,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start = Instant::now();
+        let rebuilt = conn
+            .transaction(|conn| {
+                async move {
+                    diesel::delete(
+                        schema::contract_storage_current::table
+                            .filter(schema::contract_storage_current::chain_id.eq(chain_id)),
+                    )
+                    .execute(conn)
+                    .await?;
+                    schema::contract_storage::table
+                        .filter(schema::contract_storage::chain_id.eq(chain_id))
+                        .filter(schema::contract_storage::valid_to.eq(MAX_TS))
+                        .filter(schema::contract_storage::value.is_not_null())
+                        .select((
+                            schema::contract_storage::account_id,
+                            schema::contract_storage::chain_id,
+                            schema::contract_storage::slot,
+                            schema::contract_storage::value.assume_not_null(),
+                            schema::contract_storage::modify_tx,
+                            schema::contract_storage::ordinal,
+                            schema::contract_storage::valid_from,
+                        ))
+                        .insert_into(schema::contract_storage_current::table)
+                        .into_columns((
+                            schema::contract_storage_current::account_id,
+                            schema::contract_storage_current::chain_id,
+                            schema::contract_storage_current::slot,
+                            schema::contract_storage_current::value,
+                            schema::contract_storage_current::modify_tx,
+                            schema::contract_storage_current::ordinal,
+                            schema::contract_storage_current::valid_from,
+                        ))
+                        .execute(conn)
+                        .await
+                }
+                .scope_boxed()
+            })
+            .await
+            .map_err(PostgresError::from)?;
+        self.record_query("rebuild_current_snapshot", start, rebuilt);
+        debug!(rebuilt, "Rebuilt current slot snapshot");
+        Ok(rebuilt)
+    }
+
     /// Constructs a mapping from address to contract slots
     fn construct_account_to_contract_store(
         slot_values: impl Iterator<Item = (i64, Bytes, Option<Bytes>)>,
@@ -717,8 +872,9 @@ impl PostgresGateway {
                 )
             })?;
 
-        let (code_tx, code_orm) = schema::contract_code::table
+        let (code_tx, code_orm, code) = schema::contract_code::table
             .inner_join(schema::transaction::table)
+            .inner_join(schema::code::table)
             .filter(schema::contract_code::account_id.eq(account_orm.id))
             .filter(schema::contract_code::valid_from.le(version_ts))
             .filter(
@@ -726,13 +882,17 @@ impl PostgresGateway {
                     .gt(Some(version_ts))
                     .or(schema::contract_code::valid_to.is_null()),
             )
-            .select((schema::transaction::hash, orm::ContractCode::as_select()))
+            .select((
+                schema::transaction::hash,
+                orm::ContractCode::as_select(),
+                schema::code::bytecode,
+            ))
             .order_by((
                 schema::contract_code::account_id,
                 schema::contract_code::valid_from.desc(),
                 schema::transaction::index.desc(),
             ))
-            .first::<(Bytes, orm::ContractCode)>(conn)
+            .first::<(Bytes, orm::ContractCode, Code)>(conn)
             .await
             .map_err(|err| {
                 storage_error_from_diesel(
@@ -752,13 +912,16 @@ impl PostgresGateway {
                 .ok(),
             None => None,
         };
+        if self.verify_code_hash {
+            verify_code_hash(&account_orm.address, &code, &code_orm.hash)?;
+        }
         let mut account = models::contract::Account::new(
             id.chain,
             account_orm.address,
             account_orm.title,
             HashMap::new(),
             balance_orm.balance,
-            code_orm.code,
+            code,
             code_orm.hash,
             balance_tx,
             code_tx,
@@ -895,6 +1058,7 @@ impl PostgresGateway {
             use schema::contract_code::dsl::*;
             contract_code
                 .inner_join(schema::transaction::table)
+                .inner_join(schema::code::table)
                 .filter(account_id.eq_any(&account_ids))
                 .filter(valid_from.le(version_ts))
                 .filter(
@@ -903,13 +1067,17 @@ impl PostgresGateway {
                         .or(valid_to.gt(version_ts)),
                 )
                 .order_by((account_id, schema::transaction::index.desc()))
-                .select((orm::ContractCode::as_select(), schema::transaction::hash))
+                .select((
+                    orm::ContractCode::as_select(),
+                    schema::code::bytecode,
+                    schema::transaction::hash,
+                ))
                 .distinct_on(account_id)
-                .get_results::<(orm::ContractCode, Bytes)>(conn)
+                .get_results::<(orm::ContractCode, Code, Bytes)>(conn)
                 .await
                 .map_err(PostgresError::from)?
                 .into_iter()
-                .map(|(entity, tx)| WithTxHash { entity, tx: Some(tx) })
+                .map(|(entity, code, tx)| (WithTxHash { entity, tx: Some(tx) }, code))
                 .collect::<Vec<_>>()
         };
 
@@ -935,7 +1103,7 @@ impl PostgresGateway {
         let res = accounts
             .into_iter()
             .zip(native_balances.into_iter().zip(codes))
-            .map(|(account, (balance, code))| -> Result<models::contract::Account, StorageError> {
+            .map(|(account, (balance, (code, bytecode)))| -> Result<models::contract::Account, StorageError> {
                 if !(account.id == balance.account_id && balance.account_id == code.account_id) {
                     return Err(StorageError::Unexpected(format!(
                         "Identity mismatch - while retrieving entries for account id: {} \
@@ -949,13 +1117,20 @@ impl PostgresGateway {
                 let code_tx = code.tx.clone().unwrap();
                 let creation_tx = account.tx.clone();
 
+                if self.verify_code_hash {
+                    verify_code_hash(
+                        &account.entity.address,
+                        &bytecode,
+                        &code.entity.hash,
+                    )?;
+                }
                 let mut contract = models::contract::Account::new(
                     *chain,
                     account.entity.address.clone(),
                     account.entity.title.clone(),
                     HashMap::new(),
                     balance.entity.balance.clone(),
-                    code.entity.code.clone(),
+                    bytecode,
                     code.entity.hash.clone(),
                     balance_tx,
                     code_tx,
@@ -978,6 +1153,140 @@ impl PostgresGateway {
         Ok(WithTotal { entity: res, total: Some(total_count) })
     }
 
+    /// Exports all contracts of a chain, including their slots, as newline-delimited JSON.
+    ///
+    /// Contracts are fetched in batches of [`EXPORT_BATCH_SIZE`] accounts, paginated by their
+    /// id, so memory use stays bounded regardless of the number of contracts. Each line holds
+    /// one serialized [`models::contract::Account`].
+    ///
+    /// # Returns
+    /// The number of exported contracts.
+    pub async fn export_contracts<W: AsyncWrite + Unpin + Send>(
+        &self,
+        chain: &Chain,
+        version: Option<&BlockOrTimestamp>,
+        writer: &mut W,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, StorageError> {
+        let chain_db_id = self.get_chain_id(chain);
+        // pin the version so all batches observe the same state
+        let version = Version(
+            match version {
+                Some(version) => version.clone(),
+                None => BlockOrTimestamp::Timestamp(Utc::now().naive_utc()),
+            },
+            VersionKind::Last,
+        );
+        let version_ts = maybe_lookup_version_ts(&version, conn).await?;
+
+        let mut last_id = 0;
+        let mut exported = 0;
+        loop {
+            let batch = {
+                use schema::account::dsl::*;
+                account
+                    .filter(chain_id.eq(chain_db_id))
+                    .filter(id.gt(last_id))
+                    .filter(created_at.le(version_ts))
+                    .filter(
+                        deleted_at
+                            .is_null()
+                            .or(deleted_at.gt(version_ts)),
+                    )
+                    .order_by(id)
+                    .limit(EXPORT_BATCH_SIZE)
+                    .select((id, address))
+                    .get_results::<(i64, Address)>(conn)
+                    .await
+                    .map_err(PostgresError::from)?
+            };
+            let Some((batch_last_id, _)) = batch.last() else {
+                break;
+            };
+            last_id = *batch_last_id;
+            let addresses = batch
+                .into_iter()
+                .map(|(_, address)| address)
+                .collect::<Vec<_>>();
+
+            let contracts = self
+                .get_contracts(chain, Some(&addresses), Some(&version), true, None, conn)
+                .await?
+                .entity;
+            for contract in contracts.iter() {
+                let mut line = serde_json::to_vec(contract).map_err(|err| {
+                    StorageError::Unexpected(format!(
+                        "Failed to serialize contract {}: {err}",
+                        contract.address
+                    ))
+                })?;
+                line.push(b'\n');
+                writer
+                    .write_all(&line)
+                    .await
+                    .map_err(|err| {
+                        StorageError::Unexpected(format!("Failed to write contract export: {err}"))
+                    })?;
+            }
+            exported += contracts.len();
+        }
+        writer.flush().await.map_err(|err| {
+            StorageError::Unexpected(format!("Failed to write contract export: {err}"))
+        })?;
+        Ok(exported)
+    }
+
+    /// Imports contracts from newline-delimited JSON, as produced by
+    /// [`PostgresGateway::export_contracts`].
+    ///
+    /// All contracts are inserted within a single transaction, so a malformed or invalid line
+    /// aborts the whole import with a `DecodeError` naming the offending line. The creation
+    /// transactions of the contracts must already be stored.
+    ///
+    /// # Returns
+    /// The number of imported contracts.
+    pub async fn import_contracts<R: AsyncRead + Unpin + Send>(
+        &self,
+        chain: &Chain,
+        reader: &mut R,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, StorageError> {
+        let imported = conn
+            .transaction(|conn| {
+                async move {
+                    let mut lines = BufReader::new(reader).lines();
+                    let mut line_no = 0;
+                    let mut imported = 0;
+                    while let Some(line) = lines.next_line().await.map_err(|err| {
+                        StorageError::Unexpected(format!("Failed to read contract import: {err}"))
+                    })? {
+                        line_no += 1;
+                        if line.trim().is_empty() {
+                            continue;
+                        }
+                        let account = serde_json::from_str::<models::contract::Account>(&line)
+                            .map_err(|err| {
+                                StorageError::DecodeError(format!(
+                                    "Malformed contract on line {line_no}: {err}"
+                                ))
+                            })?;
+                        validate_imported_contract(chain, &account).map_err(|err| {
+                            StorageError::DecodeError(format!(
+                                "Invalid contract on line {line_no}: {err}"
+                            ))
+                        })?;
+                        self.upsert_contract(&account, conn)
+                            .await?;
+                        imported += 1;
+                    }
+                    Ok::<usize, PostgresError>(imported)
+                }
+                .scope_boxed()
+            })
+            .await?;
+        Ok(imported)
+    }
+
     /// Upsert contract
     ///
     /// Inserts a contract or updates it if it already exists. It will not update
@@ -1019,7 +1328,6 @@ impl PostgresGateway {
             deleted_at: None,
             balance: new.native_balance.clone(),
             code: new.code.clone(),
-            code_hash: new.code_hash.clone(),
         };
         let hex_addr = hex::encode(&new.address);
 
@@ -1050,29 +1358,130 @@ impl PostgresGateway {
                 .execute(db)
                 .await
                 .map_err(|err| storage_error_from_diesel(err, "AccountBalance", &hex_addr, None))?;
+            diesel::insert_into(schema::code::table)
+                .values(new_contract.new_code())
+                .on_conflict(schema::code::hash)
+                .do_nothing()
+                .execute(db)
+                .await
+                .map_err(|err| storage_error_from_diesel(err, "Code", &hex_addr, None))?;
             diesel::insert_into(schema::contract_code::table)
-                .values(new_contract.new_code(account_id, tx_id, created_ts))
+                .values(new_contract.new_contract_code(account_id, tx_id, created_ts))
                 .execute(db)
                 .await
                 .map_err(|err| storage_error_from_diesel(err, "ContractCode", &hex_addr, None))?;
-            self.upsert_slots(
-                [(
-                    tx_id,
-                    [(
-                        new.address.clone(),
-                        new.slots
-                            .iter()
-                            .map(|(k, v)| (k.clone(), Some(v.clone())))
-                            .collect(),
-                    )]
-                    .into_iter()
-                    .collect(),
-                )]
+            let rows = new
+                .store_iter()
+                .map(|(slot, value)| (tx_id, new.address.clone(), slot, value))
+                .collect();
+            self.upsert_slots(rows, db).await?;
+        }
+        Ok(())
+    }
+
+    /// Drops account changes from `deltas` that would not result in a new version.
+    ///
+    /// Used to replay blocks that may already be (partially) stored. A balance or code change is
+    /// dropped if a version of it modified by the same transaction exists already. A slot change
+    /// is dropped if a version of that slot modified by the same transaction exists already, or
+    /// if it sets the slot to the value it currently holds. `deltas` are expected in execution
+    /// order.
+    pub(crate) async fn prune_applied_account_changes(
+        &self,
+        chain: &Chain,
+        deltas: &mut [(TxHash, AccountDelta)],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let addresses: HashSet<&Address> = deltas
+            .iter()
+            .map(|(_, delta)| &delta.address)
+            .collect();
+        if addresses.is_empty() {
+            return Ok(());
+        }
+        let tx_hashes: HashSet<&TxHash> = deltas
+            .iter()
+            .map(|(tx, _)| tx)
+            .collect();
+
+        let applied_balances: HashSet<(Address, TxHash)> = schema::account_balance::table
+            .inner_join(schema::account::table)
+            .inner_join(schema::transaction::table)
+            .filter(schema::account::chain_id.eq(chain_id))
+            .filter(schema::account::address.eq_any(&addresses))
+            .filter(schema::transaction::hash.eq_any(&tx_hashes))
+            .select((schema::account::address, schema::transaction::hash))
+            .get_results::<(Address, TxHash)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+        let applied_code: HashSet<(Address, TxHash)> = schema::contract_code::table
+            .inner_join(schema::account::table)
+            .inner_join(schema::transaction::table)
+            .filter(schema::account::chain_id.eq(chain_id))
+            .filter(schema::account::address.eq_any(&addresses))
+            .filter(schema::transaction::hash.eq_any(&tx_hashes))
+            .select((schema::account::address, schema::transaction::hash))
+            .get_results::<(Address, TxHash)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+        let applied_slots: HashSet<(Address, StoreKey, TxHash)> = schema::contract_storage::table
+            .inner_join(schema::account::table)
+            .inner_join(schema::transaction::table)
+            .filter(schema::account::chain_id.eq(chain_id))
+            .filter(schema::account::address.eq_any(&addresses))
+            .filter(schema::transaction::hash.eq_any(&tx_hashes))
+            .select((
+                schema::account::address,
+                schema::contract_storage::slot,
+                schema::transaction::hash,
+            ))
+            .get_results::<(Address, StoreKey, TxHash)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+        let mut current: HashMap<(Address, StoreKey), Option<StoreVal>> =
+            schema::contract_storage::table
+                .inner_join(schema::account::table)
+                .filter(schema::account::chain_id.eq(chain_id))
+                .filter(schema::account::address.eq_any(&addresses))
+                .filter(schema::contract_storage::valid_to.eq(MAX_TS))
+                .select((
+                    schema::account::address,
+                    schema::contract_storage::slot,
+                    schema::contract_storage::value,
+                ))
+                .get_results::<(Address, StoreKey, Option<StoreVal>)>(conn)
+                .await
+                .map_err(PostgresError::from)?
                 .into_iter()
-                .collect(),
-                db,
-            )
-            .await?;
+                .map(|(address, slot, value)| ((address, slot), value))
+                .collect();
+
+        for (tx, delta) in deltas.iter_mut() {
+            let address = delta.address.clone();
+            if applied_balances.contains(&(address.clone(), tx.clone())) {
+                delta.balance = None;
+            }
+            if applied_code.contains(&(address.clone(), tx.clone())) {
+                delta.code = None;
+            }
+            delta.slots.retain(|slot, value| {
+                if applied_slots.contains(&(address.clone(), slot.clone(), tx.clone())) {
+                    return false;
+                }
+                let key = (address.clone(), slot.clone());
+                if current.get(&key) == Some(value) {
+                    return false;
+                }
+                current.insert(key, value.clone());
+                true
+            });
         }
         Ok(())
     }
@@ -1129,7 +1538,9 @@ impl PostgresGateway {
 
         let mut balance_data = Vec::new();
         let mut code_data = Vec::new();
-        let mut slot_data: HashMap<i64, AccountToContractStore> = HashMap::new();
+        let mut code_blobs = HashMap::new();
+        let mut slot_data = Vec::new();
+        let mut slot_writers = HashSet::new();
 
         for delta in new.iter() {
             let contract_id = delta.contract_id();
@@ -1161,10 +1572,12 @@ impl PostgresGateway {
             }
 
             if let Some(new_code) = delta.code.as_ref() {
-                let hash = keccak256(new_code.clone());
+                let hash: CodeHash = keccak256(new_code.clone()).into();
+                code_blobs
+                    .entry(hash.clone())
+                    .or_insert(orm::NewCode { hash: hash.clone(), bytecode: new_code });
                 let new = orm::NewContractCode {
-                    code: new_code,
-                    hash: hash.into(),
+                    hash,
                     account_id,
                     modify_tx: tx_id,
                     valid_from: ts,
@@ -1173,24 +1586,18 @@ impl PostgresGateway {
                 code_data.push(WithOrdinal::new(new, (account_id, ts, index)));
             }
 
-            let slots = delta.slots.clone();
-            if !slots.is_empty() {
-                match slot_data.entry(tx_id) {
-                    Entry::Occupied(mut e) => {
-                        let v = e.get_mut();
-                        if v.contains_key(&contract_id.address) {
-                            return Err(StorageError::Unexpected(format!("Ambiguous update! Contract 0x{} received different updates in same tx!", hex::encode(&contract_id.address))));
-                        }
-                        v.extend([(contract_id.address.clone(), slots)]);
-                    }
-
-                    Entry::Vacant(e) => {
-                        let v = [(contract_id.address.clone(), slots)]
-                            .into_iter()
-                            .collect();
-                        e.insert(v);
-                    }
+            if !delta.slots.is_empty() {
+                if !slot_writers.insert((tx_id, contract_id.address.clone())) {
+                    return Err(StorageError::Unexpected(format!(
+                        "Ambiguous update! Contract 0x{} received different updates in same tx!",
+                        hex::encode(&contract_id.address)
+                    )));
                 }
+                slot_data.extend(
+                    delta
+                        .dirty_slots_iter()
+                        .map(|(slot, value)| (tx_id, contract_id.address.clone(), slot, value)),
+                );
             }
         }
 
@@ -1214,7 +1621,18 @@ impl PostgresGateway {
                 .map(|b| b.entity)
                 .collect::<Vec<_>>();
             apply_versioning::<_, orm::ContractCode>(&mut sorted, conn).await?;
-            diesel::insert_into(schema::contract_code::table)
+            diesel::insert_into(schema::code::table)
+                .values(
+                    code_blobs
+                        .into_values()
+                        .collect::<Vec<_>>(),
+                )
+                .on_conflict(schema::code::hash)
+                .do_nothing()
+                .execute(conn)
+                .await
+                .map_err(PostgresError::from)?;
+            diesel::insert_into(schema::contract_code::table)
                 .values(&sorted)
                 .execute(conn)
                 .await
@@ -1309,10 +1727,15 @@ impl PostgresGateway {
         // To support blocks as versions, we need to ingest all blocks, else the
         // below method can error for any blocks that are not present.
         let start_version_ts = match start_version {
-            Some(version) => maybe_lookup_block_ts(version, conn).await?,
+            Some(version) => {
+                self.version_to_ts(version, conn)
+                    .await?
+            }
             None => Utc::now().naive_utc(),
         };
-        let target_version_ts = maybe_lookup_block_ts(target_version, conn).await?;
+        let target_version_ts = self
+            .version_to_ts(target_version, conn)
+            .await?;
 
         let balance_deltas = self
             .get_balance_deltas_internal(chain_id, &start_version_ts, &target_version_ts, conn)
@@ -1338,6 +1761,7 @@ impl PostgresGateway {
         // In the worst case each changed slot is changed on a different
         // account. On mainnet that would be at max 300 contracts/slots, which
         // although not ideal is still bearable.
+        let start = Instant::now();
         let account_addresses = schema::account::table
             .filter(
                 schema::account::id.eq_any(
@@ -1352,6 +1776,7 @@ impl PostgresGateway {
             .get_results::<(i64, Address)>(conn)
             .await
             .map_err(PostgresError::from)?;
+        self.record_query("account_addresses", start, account_addresses.len());
 
         let deltas = account_addresses
             .into_iter()
@@ -1385,151 +1810,1162 @@ impl PostgresGateway {
             .collect::<Result<HashMap<_, _>, _>>()?;
         Ok(deltas.into_values().collect())
     }
-}
 
-/// Tests for PostgresGateway's ContractStateGateway methods
-///
-/// The tests below test the functionality using the concrete EVM types.
-#[cfg(test)]
-mod test {
-    use crate::postgres::{
-        db_fixtures,
-        db_fixtures::{yesterday_midnight, yesterday_one_am},
-    };
-    use diesel_async::AsyncConnection;
-    use rstest::rstest;
-    use std::{str::FromStr, time::Duration};
-    use tycho_core::{
-        storage::{BlockIdentifier, VersionKind},
-        Bytes,
-    };
+    /// Retrieves contract changes of a chain that happened after block `since`, page by page.
+    ///
+    /// Changes are ordered by the time they became valid, the contract address and the changed
+    /// slot; balance and code changes come before the slots of the same contract and block.
+    /// At most `limit` changes are read per call, starting right after `cursor` if given. The
+    /// changes of a page are grouped by contract into deltas with `ChangeType::Update`, each
+    /// carrying the latest value within the page, ordered by the first change to their contract.
+    ///
+    /// # Returns
+    /// The deltas and, if the page is full, the cursor to continue from. Re-calling with the
+    /// returned cursor continues exactly after the last returned change.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn get_changes_since(
+        &self,
+        chain: &Chain,
+        since: &BlockIdentifier,
+        cursor: Option<&ChangeCursor>,
+        limit: usize,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(Vec<AccountDelta>, Option<ChangeCursor>), StorageError> {
+        if limit == 0 {
+            return Ok((Vec::new(), cursor.cloned()));
+        }
+        let chain_id = self.get_chain_id(chain);
+        let since_ts = orm::Block::by_id(since, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &since.to_string(), None))?
+            .ts;
+
+        // Each source is limited to `limit` rows: if a change ends up in the page, all changes
+        // of any source ordered before it were fetched as well.
+        // Versions superseded within the same block are skipped, they never became visible.
+        let slot_rows = {
+            use schema::contract_storage::dsl::*;
+            let mut q = contract_storage
+                .inner_join(schema::account::table)
+                .filter(schema::account::chain_id.eq(chain_id))
+                .filter(valid_from.gt(since_ts))
+                .filter(valid_to.ne(valid_from))
+                .into_boxed();
+            if let Some(c) = cursor {
+                q = q.filter(
+                    valid_from
+                        .gt(c.valid_from)
+                        .or(valid_from.eq(c.valid_from).and(
+                            schema::account::address
+                                .gt(&c.address)
+                                .or(schema::account::address
+                                    .eq(&c.address)
+                                    .and(slot.gt(&c.slot))),
+                        )),
+                );
+            }
+            q.order_by((valid_from, schema::account::address, slot))
+                .limit(limit as i64)
+                .select((valid_from, schema::account::address, slot, value))
+                .get_results::<(NaiveDateTime, Address, StoreKey, Option<StoreVal>)>(conn)
+                .await
+                .map_err(PostgresError::from)?
+        };
+        let balance_rows = {
+            use schema::account_balance::dsl::*;
+            let mut q = account_balance
+                .inner_join(schema::account::table)
+                .filter(schema::account::chain_id.eq(chain_id))
+                .filter(valid_from.gt(since_ts))
+                .filter(
+                    valid_to
+                        .is_null()
+                        .or(valid_to.ne(valid_from.nullable())),
+                )
+                .into_boxed();
+            if let Some(c) = cursor {
+                q = q.filter(
+                    valid_from
+                        .gt(c.valid_from)
+                        .or(valid_from
+                            .eq(c.valid_from)
+                            .and(schema::account::address.gt(&c.address))),
+                );
+            }
+            q.order_by((valid_from, schema::account::address))
+                .limit(limit as i64)
+                .select((valid_from, schema::account::address, balance))
+                .get_results::<(NaiveDateTime, Address, Balance)>(conn)
+                .await
+                .map_err(PostgresError::from)?
+        };
+        let code_rows = {
+            use schema::contract_code::dsl::*;
+            let mut q = contract_code
+                .inner_join(schema::account::table)
+                .inner_join(schema::code::table)
+                .filter(schema::account::chain_id.eq(chain_id))
+                .filter(valid_from.gt(since_ts))
+                .filter(
+                    valid_to
+                        .is_null()
+                        .or(valid_to.ne(valid_from.nullable())),
+                )
+                .into_boxed();
+            if let Some(c) = cursor {
+                q = q.filter(
+                    valid_from
+                        .gt(c.valid_from)
+                        .or(valid_from
+                            .eq(c.valid_from)
+                            .and(schema::account::address.gt(&c.address))),
+                );
+            }
+            q.order_by((valid_from, schema::account::address))
+                .limit(limit as i64)
+                .select((valid_from, schema::account::address, schema::code::bytecode))
+                .get_results::<(NaiveDateTime, Address, Code)>(conn)
+                .await
+                .map_err(PostgresError::from)?
+        };
 
-    use super::*;
+        let mut rows = slot_rows
+            .into_iter()
+            .map(|(ts, address, slot, value)| {
+                ((ts, address, slot.clone()), ContractChange::Slot(slot, value))
+            })
+            .chain(
+                balance_rows
+                    .into_iter()
+                    .map(|(ts, address, balance)| {
+                        ((ts, address, Bytes::new()), ContractChange::Balance(balance))
+                    }),
+            )
+            .chain(
+                code_rows
+                    .into_iter()
+                    .map(|(ts, address, code)| {
+                        ((ts, address, Bytes::new()), ContractChange::Code(code))
+                    }),
+            )
+            .collect::<Vec<_>>();
+        rows.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let mut positions: HashMap<Address, usize> = HashMap::new();
+        let mut deltas: Vec<AccountDelta> = Vec::new();
+        let mut last_key = None;
+        let mut taken = 0;
+        for (key, change) in rows {
+            // a balance and a code change of the same contract and block share a position
+            if last_key.as_ref() != Some(&key) {
+                if taken == limit {
+                    break;
+                }
+                taken += 1;
+            }
+            let idx = *positions
+                .entry(key.1.clone())
+                .or_insert_with(|| {
+                    deltas.push(AccountDelta::new(
+                        *chain,
+                        key.1.clone(),
+                        HashMap::new(),
+                        None,
+                        None,
+                        ChangeType::Update,
+                    ));
+                    deltas.len() - 1
+                });
+            let delta = &mut deltas[idx];
+            match change {
+                ContractChange::Slot(slot, value) => {
+                    delta.slots.insert(slot, value);
+                }
+                ContractChange::Balance(balance) => delta.balance = Some(balance),
+                ContractChange::Code(code) => delta.code = Some(code),
+            }
+            last_key = Some(key);
+        }
 
-    type EvmGateway = PostgresGateway;
-    type MaybeTS = Option<NaiveDateTime>;
+        let next = match last_key {
+            Some((valid_from, address, slot)) if taken == limit => {
+                Some(ChangeCursor { valid_from, address, slot })
+            }
+            _ => None,
+        };
+        Ok((deltas, next))
+    }
 
-    async fn setup_db() -> AsyncPgConnection {
-        let db_url = std::env::var("DATABASE_URL").unwrap();
-        let mut conn = AsyncPgConnection::establish(&db_url)
-            .await
-            .unwrap();
-        conn.begin_test_transaction()
+    /// Retrieves the slot changes of selected contracts and slots between two versions.
+    ///
+    /// Works like the slot part of [`PostgresGateway::get_accounts_delta`] but only considers
+    /// the given `contracts` and `slots`, so the query scans just the matching storage rows.
+    /// Contracts without changes to any of the requested slots are omitted from the result.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn get_slots_delta_filtered(
+        &self,
+        chain: &Chain,
+        contracts: &[Address],
+        slots: &[StoreKey],
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<AccountToContractStore, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start_version_ts = match start_version {
+            Some(version) => {
+                self.version_to_ts(version, conn)
+                    .await?
+            }
+            None => Utc::now().naive_utc(),
+        };
+        let target_version_ts = self
+            .version_to_ts(target_version, conn)
+            .await?;
+
+        let changed_values = self
+            .get_slots_delta_rows(
+                chain_id,
+                Some((contracts, slots)),
+                &start_version_ts,
+                &target_version_ts,
+                conn,
+            )
+            .await?;
+        let account_addresses = schema::account::table
+            .filter(schema::account::chain_id.eq(chain_id))
+            .filter(schema::account::address.eq_any(contracts))
+            .select((schema::account::id, schema::account::address))
+            .get_results::<(i64, Address)>(conn)
             .await
-            .unwrap();
-        conn
-    }
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
 
-    /// This sets up the data needed to test the gateway. The setup is structured such that each
-    /// accounts historical changes are kept together this makes it easy to reason about that change
-    /// an account should have at each version Please not that if you change something here, also
-    /// update the account fixtures right below, which contain account states at each version
-    async fn setup_data(conn: &mut AsyncPgConnection) {
-        let chain_id = db_fixtures::insert_chain(conn, "ethereum").await;
-        let blk = db_fixtures::insert_blocks(conn, chain_id).await;
-        let ts = db_fixtures::yesterday_midnight();
-        let ts_p1 = db_fixtures::yesterday_one_am();
-        let tx_hashes = [
-            "0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945".to_string(),
-            "0x794f7df7a3fe973f1583fbb92536f9a8def3a89902439289315326c04068de54".to_string(),
-            "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7".to_string(),
-            "0x50449de1973d86f21bfafa7c72011854a7e33a226709dc3e2e4edcca34188388".to_string(),
-        ];
-        let txn = db_fixtures::insert_txns(
-            conn,
-            &[
-                // deploy c0
-                (blk[0], 1i64, &tx_hashes[0]),
-                // change c0 state, deploy c2
-                (blk[0], 2i64, &tx_hashes[1]),
-                // ----- Block 01 LAST
-                // deploy c1, delete c2
-                (blk[1], 1i64, &tx_hashes[2]),
-                // change c0 and c1 state
-                (blk[1], 2i64, &tx_hashes[3]),
-                // ----- Block 02 LAST
-            ],
-        )
-        .await;
+        let mut result = AccountToContractStore::new();
+        for (cid, raw_key, raw_val) in changed_values.into_iter() {
+            let address = account_addresses
+                .get(&cid)
+                .ok_or_else(|| StorageError::NotFound("Account".to_string(), cid.to_string()))?;
+            result
+                .entry(address.clone())
+                .or_default()
+                .insert(raw_key, raw_val);
+        }
+        Ok(result)
+    }
 
-        // Account C0
-        let c0 = db_fixtures::insert_account(
-            conn,
-            "6B175474E89094C44Da98b954EedeAC495271d0F",
-            "account0",
-            chain_id,
-            Some(txn[0]),
-        )
-        .await;
-        db_fixtures::insert_account_balance(conn, 0, txn[0], Some(&ts), c0).await;
-        let c0_code =
-            db_fixtures::insert_contract_code(conn, c0, txn[0], Bytes::from("C0C0C0")).await;
-        db_fixtures::insert_account_balance(conn, 100, txn[1], Some(&ts_p1), c0).await;
-        // Slot 2 is never modified again
-        db_fixtures::insert_slots(conn, c0, txn[1], &ts, None, &[(2, 1, None)]).await;
-        // First version for slots 0 and 1.
-        db_fixtures::insert_slots(
-            conn,
-            c0,
-            txn[1],
-            &ts,
-            Some(&ts_p1),
-            &[(0, 1, None), (1, 5, None)],
-        )
-        .await;
-        db_fixtures::insert_account_balance(conn, 101, txn[3], None, c0).await;
-        // Second and final version for 0 and 1, new slots 5 and 6
-        db_fixtures::insert_slots(
-            conn,
-            c0,
-            txn[3],
-            &ts_p1,
-            None,
-            &[(0, 2, Some(1)), (1, 3, Some(5)), (5, 25, None), (6, 30, None)],
-        )
-        .await;
+    /// Retrieves the slot changes of all contracts of a chain between two versions, keyed by
+    /// contract address.
+    ///
+    /// Resolves the changed rows by account id first and looks up the addresses of the
+    /// changed accounts in a second query, like [`PostgresGateway::get_accounts_delta`] does.
+    /// This transfers the least data if few accounts changed, see
+    /// [`PostgresGateway::get_slots_delta_joined`] for the single query alternative.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn get_slots_delta_by_address(
+        &self,
+        chain: &Chain,
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<AccountToContractStore, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start_version_ts = match start_version {
+            Some(version) => {
+                self.version_to_ts(version, conn)
+                    .await?
+            }
+            None => Utc::now().naive_utc(),
+        };
+        let target_version_ts = self
+            .version_to_ts(target_version, conn)
+            .await?;
 
-        // Account C1
-        let c1 = db_fixtures::insert_account(
-            conn,
-            "73BcE791c239c8010Cd3C857d96580037CCdd0EE",
-            "c1",
-            chain_id,
-            Some(txn[2]),
-        )
-        .await;
-        db_fixtures::insert_account_balance(conn, 50, txn[2], None, c1).await;
-        let c1_code =
-            db_fixtures::insert_contract_code(conn, c1, txn[2], Bytes::from("C1C1C1")).await;
-        db_fixtures::insert_slots(
-            conn,
-            c1,
-            txn[3],
-            &ts_p1,
-            None,
-            &[(0, 128, None), (1, 255, None)],
-        )
-        .await;
+        let slot_deltas = self
+            .get_slots_delta(chain_id, &start_version_ts, &target_version_ts, conn)
+            .await?;
+        let start = Instant::now();
+        let account_addresses = schema::account::table
+            .filter(schema::account::id.eq_any(slot_deltas.keys()))
+            .select((schema::account::id, schema::account::address))
+            .get_results::<(i64, Address)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+        self.record_query("account_addresses", start, account_addresses.len());
 
-        // Account C2
-        let c2 = db_fixtures::insert_account(
-            conn,
-            "94a3F312366b8D0a32A00986194053C0ed0CdDb1",
-            "c2",
-            chain_id,
-            Some(txn[1]),
-        )
-        .await;
-        db_fixtures::insert_account_balance(conn, 25, txn[1], None, c2).await;
-        let c2_code =
-            db_fixtures::insert_contract_code(conn, c2, txn[1], Bytes::from("C2C2C2")).await;
-        db_fixtures::insert_slots(conn, c2, txn[1], &ts, None, &[(1, 2, None), (2, 4, None)]).await;
-        db_fixtures::delete_account(conn, c2, &ts_p1).await;
+        slot_deltas
+            .into_iter()
+            .map(|(cid, store)| {
+                let address = account_addresses
+                    .get(&cid)
+                    .ok_or_else(|| {
+                        StorageError::NotFound("Account".to_string(), cid.to_string())
+                    })?;
+                Ok((address.clone(), store))
+            })
+            .collect()
+    }
 
-        // linked protocol components
-        let protocol_system_id =
-            db_fixtures::insert_protocol_system(conn, "ambient".to_owned()).await;
-        let protocol_type_id = db_fixtures::insert_protocol_type(
+    /// Retrieves the slot changes of all contracts of a chain between two versions in a stable
+    /// order.
+    ///
+    /// Same data as [`PostgresGateway::get_slots_delta_by_address`], but contracts are sorted by
+    /// address and the slots of each contract by slot key, so the output can be diffed or
+    /// snapshotted deterministically.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn get_slots_delta_ordered(
+        &self,
+        chain: &Chain,
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<(Address, OrderedContractStore)>, StorageError> {
+        let deltas = self
+            .get_slots_delta_by_address(chain, start_version, target_version, conn)
+            .await?;
+        let mut ordered = deltas
+            .into_iter()
+            .map(|(address, store)| {
+                let mut slots = store.into_iter().collect::<Vec<_>>();
+                slots.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+                (address, slots)
+            })
+            .collect::<Vec<_>>();
+        ordered.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+        Ok(ordered)
+    }
+
+    /// Retrieves the slot changes of all contracts of a chain between two versions, keyed by
+    /// contract address, in a single query.
+    ///
+    /// Returns the same result as [`PostgresGateway::get_slots_delta_by_address`], but selects
+    /// the contract address with every changed row instead of resolving account ids in a
+    /// second query. Each row carries 20 instead of 8 bytes for the contract, in exchange
+    /// this saves a round trip and a large `IN` list when many accounts changed, e.g. during
+    /// backfills.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn get_slots_delta_joined(
+        &self,
+        chain: &Chain,
+        start_version: Option<&BlockOrTimestamp>,
+        target_version: &BlockOrTimestamp,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<AccountToContractStore, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start_version_ts = match start_version {
+            Some(version) => {
+                self.version_to_ts(version, conn)
+                    .await?
+            }
+            None => Utc::now().naive_utc(),
+        };
+        let target_version_ts = self
+            .version_to_ts(target_version, conn)
+            .await?;
+
+        let start = Instant::now();
+        let predicate = slots_delta_predicate(chain_id, None);
+        let changed_values = if start_version_ts <= target_version_ts {
+            slots_delta_forward_query(
+                schema::account::address,
+                predicate,
+                &start_version_ts,
+                &target_version_ts,
+            )
+            .get_results::<(Address, StoreKey, Option<StoreVal>)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+        } else {
+            slots_delta_backward_query(
+                schema::account::address,
+                predicate,
+                &start_version_ts,
+                &target_version_ts,
+            )
+            .get_results::<(Address, StoreKey, Option<StoreVal>)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+        };
+        self.record_query("slots_delta_joined", start, changed_values.len());
+
+        let mut result = AccountToContractStore::new();
+        for (address, raw_key, raw_val) in changed_values.into_iter() {
+            result
+                .entry(address)
+                .or_default()
+                .insert(raw_key, raw_val);
+        }
+        Ok(result)
+    }
+
+    /// Counts the storage changes per contract between two versions.
+    ///
+    /// Every stored slot version with `valid_from` inside the version window counts as a
+    /// change, regardless of the direction between `start_version` and `target_version`.
+    ///
+    /// # Returns
+    /// A `(address, change_count)` pair per contract with at least one change, ordered by
+    /// descending change count.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn contract_change_stats(
+        &self,
+        chain: &Chain,
+        start_version: &BlockOrTimestamp,
+        target_version: &BlockOrTimestamp,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<(Address, u64)>, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start_version_ts = self
+            .version_to_ts(start_version, conn)
+            .await?;
+        let target_version_ts = self
+            .version_to_ts(target_version, conn)
+            .await?;
+        let (lower_ts, upper_ts) = if start_version_ts <= target_version_ts {
+            (start_version_ts, target_version_ts)
+        } else {
+            (target_version_ts, start_version_ts)
+        };
+
+        let counts = schema::contract_storage::table
+            .inner_join(schema::account::table)
+            .filter(schema::contract_storage::chain_id.eq(chain_id))
+            .filter(schema::contract_storage::valid_from.gt(lower_ts))
+            .filter(schema::contract_storage::valid_from.le(upper_ts))
+            .group_by(schema::account::id)
+            .select((schema::account::id, diesel::dsl::count_star()))
+            .get_results::<(i64, i64)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+        let account_addresses = schema::account::table
+            .filter(schema::account::id.eq_any(counts.iter().map(|(id, _)| *id)))
+            .select((schema::account::id, schema::account::address))
+            .get_results::<(i64, Address)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect::<HashMap<_, _>>();
+
+        let mut result = counts
+            .into_iter()
+            .map(|(cid, count)| {
+                let address = account_addresses
+                    .get(&cid)
+                    .ok_or_else(|| {
+                        StorageError::NotFound("Account".to_string(), cid.to_string())
+                    })?;
+                Ok((address.clone(), count as u64))
+            })
+            .collect::<Result<Vec<_>, StorageError>>()?;
+        result.sort_by(|(a_addr, a_count), (b_addr, b_count)| {
+            b_count
+                .cmp(a_count)
+                .then_with(|| a_addr.cmp(b_addr))
+        });
+        Ok(result)
+    }
+
+    /// Deletes outdated contract storage versions of a chain that expired before `before`.
+    ///
+    /// A version is only deleted if a later version of the same slot exists, so each slot
+    /// keeps at least its latest version, even if that one expired before `before` as well.
+    ///
+    /// # Returns
+    /// The number of deleted versions.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn prune_storage(
+        &self,
+        chain: &Chain,
+        before: NaiveDateTime,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let start = Instant::now();
+        let deleted = sql_query(
+            r#"
+            DELETE FROM contract_storage
+            WHERE contract_storage.chain_id = $1
+                AND contract_storage.valid_to < $2
+                AND EXISTS (
+                    SELECT 1
+                    FROM contract_storage AS later
+                    WHERE later.chain_id = contract_storage.chain_id
+                        AND later.account_id = contract_storage.account_id
+                        AND later.slot = contract_storage.slot
+                        AND later.valid_to > contract_storage.valid_to
+                )
+            "#,
+        )
+        .bind::<BigInt, _>(chain_id)
+        .bind::<Timestamptz, _>(before)
+        .execute(conn)
+        .await
+        .map_err(PostgresError::from)?;
+        self.record_query("prune_storage", start, deleted);
+        debug!(deleted, "Pruned outdated storage versions");
+        Ok(deleted)
+    }
+
+    /// Finds contract storage slots with overlapping validity ranges.
+    ///
+    /// Returns the `(contract, slot)` pairs of a chain for which more than one version is valid
+    /// at the same point in time. This indicates a versioning bug, a consistent database returns
+    /// an empty vec. This is a maintenance tool: it scans all stored slot versions of the chain
+    /// and should not be used on the hot path.
+    #[instrument(skip_all)]
+    pub async fn check_storage_consistency(
+        &self,
+        chain: &Chain,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<(Address, StoreKey)>, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let versions = schema::contract_storage::table
+            .inner_join(schema::account::table)
+            .filter(schema::account::chain_id.eq(chain_id))
+            .order_by((
+                schema::account::address,
+                schema::contract_storage::slot,
+                schema::contract_storage::valid_from,
+            ))
+            .select((
+                schema::account::address,
+                schema::contract_storage::slot,
+                schema::contract_storage::valid_from,
+                schema::contract_storage::valid_to,
+            ))
+            .get_results::<(Address, StoreKey, NaiveDateTime, NaiveDateTime)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+
+        let mut inconsistent = Vec::new();
+        let mut current: Option<(&Address, &StoreKey, NaiveDateTime)> = None;
+        for (address, slot, valid_from, valid_to) in versions.iter() {
+            match current {
+                Some((a, s, max_valid_to)) if a == address && s == slot => {
+                    if *valid_from < max_valid_to &&
+                        inconsistent.last() != Some(&(address.clone(), slot.clone()))
+                    {
+                        inconsistent.push((address.clone(), slot.clone()));
+                    }
+                    current = Some((a, s, max_valid_to.max(*valid_to)));
+                }
+                _ => current = Some((address, slot, *valid_to)),
+            }
+        }
+        Ok(inconsistent)
+    }
+}
+
+/// Groups slot delta rows by contract id.
+///
+/// Slot keys and values are moved into the resulting stores as is, no bytes are copied. Each
+/// store is allocated with the number of rows of its contract up front, so large deltas don't
+/// pay for repeated rehashing while the stores grow.
+///
+/// Exposed so the grouping can be benchmarked, see `benches/slots_delta.rs`.
+#[doc(hidden)]
+pub fn group_slot_rows(
+    rows: Vec<(i64, StoreKey, Option<StoreVal>)>,
+) -> HashMap<i64, ContractStore> {
+    let mut sizes: HashMap<i64, usize> = HashMap::new();
+    for (cid, _, _) in rows.iter() {
+        *sizes.entry(*cid).or_default() += 1;
+    }
+    let mut result: HashMap<i64, ContractStore> = sizes
+        .into_iter()
+        .map(|(cid, size)| (cid, HashMap::with_capacity(size)))
+        .collect();
+    for (cid, raw_key, raw_val) in rows.into_iter() {
+        result
+            .entry(cid)
+            .or_default()
+            .insert(raw_key, raw_val);
+    }
+    result
+}
+
+type SlotsDeltaPredicate<'a> = Box<
+    dyn BoxableExpression<
+            InnerJoinQuerySource<schema::contract_storage::table, schema::account::table>,
+            Pg,
+            SqlType = Bool,
+        > + 'a,
+>;
+
+/// Restricts the storage rows considered by the slot delta queries to a chain and, optionally,
+/// to a set of contracts and slots.
+fn slots_delta_predicate<'a>(
+    chain_id: i64,
+    filter: Option<(&'a [Address], &'a [StoreKey])>,
+) -> SlotsDeltaPredicate<'a> {
+    let predicate: SlotsDeltaPredicate<'a> =
+        Box::new(schema::contract_storage::chain_id.eq(chain_id));
+    match filter {
+        Some((contracts, slots)) => Box::new(
+            predicate
+                .and(schema::account::address.eq_any(contracts))
+                .and(schema::contract_storage::slot.eq_any(slots)),
+        ),
+        None => predicate,
+    }
+}
+
+/// The column identifying the contract in the rows of the slot delta queries, either
+/// `account::id` or `account::address`.
+trait SlotsDeltaContract:
+    Expression
+    + SelectableExpression<
+        InnerJoinQuerySource<schema::contract_storage::table, schema::account::table>,
+    > + ValidGrouping<(), IsAggregate = is_aggregate::No>
+    + QueryFragment<Pg>
+    + QueryId
+    + Copy
+    + Send
+{
+}
+
+impl SlotsDeltaContract for schema::account::id {}
+impl SlotsDeltaContract for schema::account::address {}
+
+/// Builds the slot delta query for `start <= target`.
+///
+/// Going forward
+///                  ]     changes to forward   ]
+/// -----------------|--------------------------|
+///                start                     target
+/// We query for changes between start and target version. Then sort
+/// these by account and slot by change time in a descending manner
+/// (latest change first). Next we deduplicate by account and slot.
+/// Finally, we select the value column to give us the latest value
+/// within the version range.
+fn slots_delta_forward_query<'a, C>(
+    contract: C,
+    predicate: SlotsDeltaPredicate<'a>,
+    start_version_ts: &'a NaiveDateTime,
+    target_version_ts: &'a NaiveDateTime,
+) -> impl Query<SqlType = (C::SqlType, Bytea, Nullable<Bytea>)> + QueryFragment<Pg> + QueryId + 'a
+where
+    C: SlotsDeltaContract + 'a,
+{
+    schema::contract_storage::table
+        .inner_join(schema::account::table)
+        .filter(predicate)
+        .filter(schema::contract_storage::valid_from.gt(start_version_ts))
+        .filter(schema::contract_storage::valid_from.le(target_version_ts))
+        .order_by((
+            schema::account::id,
+            schema::contract_storage::slot,
+            schema::contract_storage::valid_from.desc(),
+            schema::contract_storage::ordinal.desc(),
+        ))
+        .select((contract, schema::contract_storage::slot, schema::contract_storage::value))
+        .distinct_on((schema::account::id, schema::contract_storage::slot))
+}
+
+/// Builds the slot delta query for `target < start`.
+///
+/// Going backwards
+///                  ]     changes to revert    ]
+/// -----------------|--------------------------|
+///                target                     start
+/// We query for changes between target and start version. Then sort
+/// these for each account and slot by change time in an ascending
+/// manner. Next, we deduplicate by taking the first row for each
+/// account and slot. Finally we select the previous_value column to
+/// give us the value before this first change within the version
+/// range.
+fn slots_delta_backward_query<'a, C>(
+    contract: C,
+    predicate: SlotsDeltaPredicate<'a>,
+    start_version_ts: &'a NaiveDateTime,
+    target_version_ts: &'a NaiveDateTime,
+) -> impl Query<SqlType = (C::SqlType, Bytea, Nullable<Bytea>)> + QueryFragment<Pg> + QueryId + 'a
+where
+    C: SlotsDeltaContract + 'a,
+{
+    schema::contract_storage::table
+        .inner_join(schema::account::table)
+        .filter(predicate)
+        .filter(schema::contract_storage::valid_from.gt(target_version_ts))
+        .filter(schema::contract_storage::valid_from.le(start_version_ts))
+        .order_by((
+            schema::account::id.asc(),
+            schema::contract_storage::slot.asc(),
+            schema::contract_storage::valid_from.asc(),
+            schema::contract_storage::ordinal.asc(),
+        ))
+        .select((
+            contract,
+            schema::contract_storage::slot,
+            schema::contract_storage::previous_value,
+        ))
+        .distinct_on((schema::account::id, schema::contract_storage::slot))
+}
+
+#[derive(QueryableByName)]
+struct PartitionName {
+    #[diesel(sql_type = Nullable<Text>)]
+    name: Option<String>,
+}
+
+/// Resolves the partition holding the current slot versions of a chain.
+///
+/// Current versions live in the default sub-partition of their chain's partition, which is
+/// the only table carrying the unique `(account_id, slot)` index required to upsert them.
+/// Chains that have no partition yet end up in the top level default partition.
+async fn latest_slots_partition(
+    chain_id: i64,
+    conn: &mut AsyncPgConnection,
+) -> Result<String, StorageError> {
+    let partition = format!("contract_storage_p{}_default", chain_id);
+    let found = sql_query("SELECT to_regclass($1)::text AS name")
+        .bind::<Text, _>(format!("public.{}", partition))
+        .get_result::<PartitionName>(conn)
+        .await
+        .map_err(PostgresError::from)?;
+    Ok(match found.name {
+        Some(_) => partition,
+        None => "contract_storage_default".to_string(),
+    })
+}
+
+/// Builds the query upserting the current versions of slots into `partition`.
+///
+/// The conflict target has to repeat the predicate of the partial unique index, see the
+/// `partition_contract_storage_by_chain` migration.
+fn upsert_latest_slots_query(
+    partition: &str,
+    rows: &[orm::NewSlot],
+) -> BoxedSqlQuery<'static, Pg, SqlQuery> {
+    sql_query(format!(
+        r#"
+        INSERT INTO {partition} (slot, value, previous_value, account_id, chain_id, modify_tx,
+            ordinal, valid_from, valid_to)
+        SELECT * FROM unnest($1, $2, $3, $4, $5, $6, $7, $8, $9)
+        ON CONFLICT (account_id, slot) WHERE valid_to = '262142-12-31 23:59:59.999999+00'
+        DO UPDATE SET value = EXCLUDED.value,
+            previous_value = EXCLUDED.previous_value,
+            modify_tx = EXCLUDED.modify_tx,
+            ordinal = EXCLUDED.ordinal,
+            valid_from = EXCLUDED.valid_from
+        "#
+    ))
+    .into_boxed()
+    .bind::<Array<Bytea>, _>(
+        rows.iter()
+            .map(|r| r.slot.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<Nullable<Bytea>>, _>(
+        rows.iter()
+            .map(|r| r.value.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<Nullable<Bytea>>, _>(
+        rows.iter()
+            .map(|r| r.previous_value.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<BigInt>, _>(
+        rows.iter()
+            .map(|r| r.account_id)
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<BigInt>, _>(
+        rows.iter()
+            .map(|r| r.chain_id)
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<BigInt>, _>(
+        rows.iter()
+            .map(|r| r.modify_tx)
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<BigInt>, _>(
+        rows.iter()
+            .map(|r| r.ordinal)
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<Timestamptz>, _>(
+        rows.iter()
+            .map(|r| r.valid_from)
+            .collect::<Vec<_>>(),
+    )
+    .bind::<Array<Timestamptz>, _>(
+        rows.iter()
+            .map(|r| r.valid_to)
+            .collect::<Vec<_>>(),
+    )
+}
+
+/// Writes the latest slot versions to the `contract_storage_current` snapshot.
+///
+/// Live slots are upserted, deleted slots are removed from the snapshot. Expects at most one
+/// version per slot.
+pub(super) async fn update_current_slots(
+    latest: &[orm::NewSlot],
+    conn: &mut AsyncPgConnection,
+) -> Result<(), PostgresError> {
+    use schema::contract_storage_current::dsl::*;
+
+    let (live, deleted): (Vec<_>, Vec<_>) = latest
+        .iter()
+        .partition(|row| row.value.is_some());
+    let live = live
+        .into_iter()
+        .filter_map(orm::NewCurrentSlot::from_latest)
+        .collect::<Vec<_>>();
+    for chunk in live.chunks(1_000) {
+        diesel::insert_into(contract_storage_current)
+            .values(chunk)
+            .on_conflict((account_id, slot))
+            .do_update()
+            .set((
+                value.eq(excluded(value)),
+                modify_tx.eq(excluded(modify_tx)),
+                ordinal.eq(excluded(ordinal)),
+                valid_from.eq(excluded(valid_from)),
+            ))
+            .execute(conn)
+            .await?;
+    }
+    for chunk in deleted.chunks(1_000) {
+        let mut query = diesel::delete(contract_storage_current).into_boxed();
+        for row in chunk {
+            query = query.or_filter(
+                account_id
+                    .eq(row.account_id)
+                    .and(slot.eq(&row.slot)),
+            );
+        }
+        query.execute(conn).await?;
+    }
+    Ok(())
+}
+
+/// Reverts the contract storage of a chain to the state at `ts`.
+///
+/// Versions created after `ts` are removed and the versions valid at `ts` become the current
+/// versions again, the `contract_storage_current` snapshot is reverted along with them.
+pub(super) async fn revert_contract_storage(
+    chain_id: i64,
+    ts: NaiveDateTime,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), PostgresError> {
+    diesel::delete(
+        schema::contract_storage::table
+            .filter(schema::contract_storage::chain_id.eq(chain_id))
+            .filter(schema::contract_storage::valid_from.gt(ts)),
+    )
+    .execute(conn)
+    .await?;
+    diesel::delete(
+        schema::contract_storage_current::table
+            .filter(schema::contract_storage_current::chain_id.eq(chain_id))
+            .filter(schema::contract_storage_current::valid_from.gt(ts)),
+    )
+    .execute(conn)
+    .await?;
+    let restored = diesel::update(
+        schema::contract_storage::table
+            .filter(schema::contract_storage::chain_id.eq(chain_id))
+            .filter(schema::contract_storage::valid_to.gt(ts))
+            .filter(schema::contract_storage::valid_to.lt(MAX_TS)),
+    )
+    .set(schema::contract_storage::valid_to.eq(MAX_TS))
+    .returning(orm::ContractStorage::as_returning())
+    .get_results(conn)
+    .await?
+    .into_iter()
+    .map(orm::NewSlot::from)
+    .collect::<Vec<_>>();
+    update_current_slots(&restored, conn).await
+}
+
+#[derive(QueryableByName)]
+struct SlotsDeltaRow {
+    #[diesel(sql_type = BigInt)]
+    account_id: i64,
+    #[diesel(sql_type = Bytea)]
+    slot: StoreKey,
+    #[diesel(sql_type = Nullable<Bytea>)]
+    value: Option<StoreVal>,
+}
+
+/// Builds the slot delta query for [`DeltaQueryStrategy::WindowFunction`].
+///
+/// Selects the same rows as [`slots_delta_forward_query`] and [`slots_delta_backward_query`],
+/// but instead of deduplicating with `DISTINCT ON`, it ranks the versions of each slot within
+/// the version range by `ROW_NUMBER()` and keeps the first ranked one: the latest version going
+/// forward and the earliest version going backward.
+fn slots_delta_window_query(
+    chain_id: i64,
+    filter: Option<(&[Address], &[StoreKey])>,
+    start_version_ts: &NaiveDateTime,
+    target_version_ts: &NaiveDateTime,
+) -> BoxedSqlQuery<'static, Pg, SqlQuery> {
+    let (value, order, lower_ts, upper_ts) = if start_version_ts <= target_version_ts {
+        ("value", "DESC", start_version_ts, target_version_ts)
+    } else {
+        ("previous_value", "ASC", target_version_ts, start_version_ts)
+    };
+    let filter_clause = if filter.is_some() {
+        "AND account.address = ANY($4) AND contract_storage.slot = ANY($5)"
+    } else {
+        ""
+    };
+    let query = sql_query(format!(
+        r#"
+        SELECT account_id, slot, value
+        FROM (
+            SELECT contract_storage.account_id,
+                contract_storage.slot,
+                contract_storage.{value} AS value,
+                ROW_NUMBER() OVER (
+                    PARTITION BY contract_storage.account_id, contract_storage.slot
+                    ORDER BY contract_storage.valid_from {order}, contract_storage.ordinal {order}
+                ) AS rank
+            FROM contract_storage
+            JOIN account ON account.id = contract_storage.account_id
+            WHERE contract_storage.chain_id = $1
+                AND contract_storage.valid_from > $2
+                AND contract_storage.valid_from <= $3
+                {filter_clause}
+        ) AS ranked
+        WHERE rank = 1
+        "#
+    ))
+    .into_boxed()
+    .bind::<BigInt, _>(chain_id)
+    .bind::<Timestamptz, _>(*lower_ts)
+    .bind::<Timestamptz, _>(*upper_ts);
+    match filter {
+        Some((contracts, slots)) => query
+            .bind::<Array<Bytea>, _>(contracts.to_vec())
+            .bind::<Array<Bytea>, _>(slots.to_vec()),
+        None => query,
+    }
+}
+
+/// Wraps a query into `EXPLAIN`, yielding the query plan as one text row per line.
+#[derive(Debug)]
+struct Explain<Q>(Q);
+
+impl<Q> QueryId for Explain<Q> {
+    type QueryId = ();
+
+    const HAS_STATIC_QUERY_ID: bool = false;
+}
+
+impl<Q: Query> Query for Explain<Q> {
+    type SqlType = Text;
+}
+
+impl<Q: QueryFragment<Pg>> QueryFragment<Pg> for Explain<Q> {
+    fn walk_ast<'b>(&'b self, mut out: AstPass<'_, 'b, Pg>) -> QueryResult<()> {
+        out.push_sql("EXPLAIN ");
+        self.0.walk_ast(out.reborrow())
+    }
+}
+
+/// Ensures the stored code hash of a contract matches its stored code.
+fn verify_code_hash(
+    address: &Address,
+    code: &Code,
+    code_hash: &CodeHash,
+) -> Result<(), StorageError> {
+    if keccak256(code) != code_hash.as_ref() {
+        return Err(StorageError::DecodeError(format!(
+            "Code hash mismatch for contract {address}: stored {code_hash}, computed {}",
+            Bytes::from(keccak256(code))
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that an imported contract belongs to `chain`, has an address and that its code
+/// matches its code hash.
+fn validate_imported_contract(
+    chain: &Chain,
+    account: &models::contract::Account,
+) -> Result<(), String> {
+    if account.chain != *chain {
+        return Err(format!(
+            "contract {} belongs to chain {}, expected {chain}",
+            account.address, account.chain
+        ));
+    }
+    if account.address.is_empty() {
+        return Err("contract address is empty".to_string());
+    }
+    if keccak256(&account.code) != account.code_hash.as_ref() {
+        return Err(format!(
+            "code hash mismatch for contract {}: expected {}, computed {}",
+            account.address,
+            account.code_hash,
+            Bytes::from(keccak256(&account.code))
+        ));
+    }
+    Ok(())
+}
+
+/// Tests for PostgresGateway's ContractStateGateway methods
+///
+/// The tests below test the functionality using the concrete EVM types.
+#[cfg(test)]
+mod test {
+    use crate::postgres::{
+        db_fixtures,
+        db_fixtures::{yesterday_midnight, yesterday_one_am},
+        GatewayMetrics,
+    };
+    use diesel_async::AsyncConnection;
+    use rstest::rstest;
+    use std::{str::FromStr, sync::Arc, time::Duration};
+    use tracing::{
+        field::{Field, Visit},
+        span::{Attributes, Id, Record},
+        Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{Context, SubscriberExt},
+        registry::LookupSpan,
+        Layer,
+    };
+    use tycho_core::{
+        storage::{BlockIdentifier, VersionKind},
+        Bytes,
+    };
+
+    use super::*;
+
+    type EvmGateway = PostgresGateway;
+    type MaybeTS = Option<NaiveDateTime>;
+
+    async fn setup_db() -> AsyncPgConnection {
+        let db_url = std::env::var("DATABASE_URL").unwrap();
+        let mut conn = AsyncPgConnection::establish(&db_url)
+            .await
+            .unwrap();
+        conn.begin_test_transaction()
+            .await
+            .unwrap();
+        conn
+    }
+
+    /// This sets up the data needed to test the gateway. The setup is structured such that each
+    /// accounts historical changes are kept together this makes it easy to reason about that change
+    /// an account should have at each version Please not that if you change something here, also
+    /// update the account fixtures right below, which contain account states at each version
+    async fn setup_data(conn: &mut AsyncPgConnection) {
+        let chain_id = db_fixtures::insert_chain(conn, "ethereum").await;
+        let blk = db_fixtures::insert_blocks(conn, chain_id).await;
+        let ts = db_fixtures::yesterday_midnight();
+        let ts_p1 = db_fixtures::yesterday_one_am();
+        let tx_hashes = [
+            "0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945".to_string(),
+            "0x794f7df7a3fe973f1583fbb92536f9a8def3a89902439289315326c04068de54".to_string(),
+            "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7".to_string(),
+            "0x50449de1973d86f21bfafa7c72011854a7e33a226709dc3e2e4edcca34188388".to_string(),
+        ];
+        let txn = db_fixtures::insert_txns(
+            conn,
+            &[
+                // deploy c0
+                (blk[0], 1i64, &tx_hashes[0]),
+                // change c0 state, deploy c2
+                (blk[0], 2i64, &tx_hashes[1]),
+                // ----- Block 01 LAST
+                // deploy c1, delete c2
+                (blk[1], 1i64, &tx_hashes[2]),
+                // change c0 and c1 state
+                (blk[1], 2i64, &tx_hashes[3]),
+                // ----- Block 02 LAST
+            ],
+        )
+        .await;
+
+        // Account C0
+        let c0 = db_fixtures::insert_account(
+            conn,
+            "6B175474E89094C44Da98b954EedeAC495271d0F",
+            "account0",
+            chain_id,
+            Some(txn[0]),
+        )
+        .await;
+        db_fixtures::insert_account_balance(conn, 0, txn[0], Some(&ts), c0).await;
+        let c0_code =
+            db_fixtures::insert_contract_code(conn, c0, txn[0], Bytes::from("C0C0C0")).await;
+        db_fixtures::insert_account_balance(conn, 100, txn[1], Some(&ts_p1), c0).await;
+        // Slot 2 is never modified again
+        db_fixtures::insert_slots(conn, c0, txn[1], &ts, None, &[(2, 1, None)]).await;
+        // First version for slots 0 and 1.
+        db_fixtures::insert_slots(
+            conn,
+            c0,
+            txn[1],
+            &ts,
+            Some(&ts_p1),
+            &[(0, 1, None), (1, 5, None)],
+        )
+        .await;
+        db_fixtures::insert_account_balance(conn, 101, txn[3], None, c0).await;
+        // Second and final version for 0 and 1, new slots 5 and 6
+        db_fixtures::insert_slots(
+            conn,
+            c0,
+            txn[3],
+            &ts_p1,
+            None,
+            &[(0, 2, Some(1)), (1, 3, Some(5)), (5, 25, None), (6, 30, None)],
+        )
+        .await;
+
+        // Account C1
+        let c1 = db_fixtures::insert_account(
+            conn,
+            "73BcE791c239c8010Cd3C857d96580037CCdd0EE",
+            "c1",
+            chain_id,
+            Some(txn[2]),
+        )
+        .await;
+        db_fixtures::insert_account_balance(conn, 50, txn[2], None, c1).await;
+        let c1_code =
+            db_fixtures::insert_contract_code(conn, c1, txn[2], Bytes::from("C1C1C1")).await;
+        db_fixtures::insert_slots(
+            conn,
+            c1,
+            txn[3],
+            &ts_p1,
+            None,
+            &[(0, 128, None), (1, 255, None)],
+        )
+        .await;
+
+        // Account C2
+        let c2 = db_fixtures::insert_account(
+            conn,
+            "94a3F312366b8D0a32A00986194053C0ed0CdDb1",
+            "c2",
+            chain_id,
+            Some(txn[1]),
+        )
+        .await;
+        db_fixtures::insert_account_balance(conn, 25, txn[1], None, c2).await;
+        let c2_code =
+            db_fixtures::insert_contract_code(conn, c2, txn[1], Bytes::from("C2C2C2")).await;
+        db_fixtures::insert_slots(conn, c2, txn[1], &ts, None, &[(1, 2, None), (2, 4, None)]).await;
+        db_fixtures::delete_account(conn, c2, &ts_p1).await;
+
+        // linked protocol components
+        let protocol_system_id =
+            db_fixtures::insert_protocol_system(conn, "ambient".to_owned()).await;
+        let protocol_type_id = db_fixtures::insert_protocol_type(
             conn,
             "Pool",
             Some(models::FinancialType::Swap),
@@ -1623,6 +3059,21 @@ mod test {
             .collect()
     }
 
+    fn slot_rows(slots: HashMap<i64, AccountToContractStore>) -> Vec<SlotRow> {
+        slots
+            .into_iter()
+            .flat_map(|(tx, contracts)| {
+                contracts
+                    .into_iter()
+                    .flat_map(move |(address, storage)| {
+                        storage
+                            .into_iter()
+                            .map(move |(slot, value)| (tx, address.clone(), slot, value))
+                    })
+            })
+            .collect()
+    }
+
     fn account_c1(version: u64) -> models::contract::Account {
         match version {
             2 => models::contract::Account::new(
@@ -1768,6 +3219,28 @@ mod test {
         assert_eq!(results, exp);
     }
 
+    #[tokio::test]
+    async fn test_export_contracts() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let version = BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 1)));
+        let mut buf = Vec::new();
+
+        let exported = gw
+            .export_contracts(&Chain::Ethereum, Some(&version), &mut buf, &mut conn)
+            .await
+            .unwrap();
+
+        let lines = String::from_utf8(buf).unwrap();
+        let accounts = lines
+            .lines()
+            .map(|line| serde_json::from_str::<models::contract::Account>(line).unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(exported, 2);
+        assert_eq!(accounts, vec![account_c0(1), account_c2(1)]);
+    }
+
     #[rstest]
     #[case::empty(
     None,
@@ -1855,6 +3328,18 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_verify_code_hash() {
+        let address = Bytes::from("0x6b175474e89094c44da98b954eedeac495271d0f");
+        let code = Bytes::from("0x1234");
+
+        let valid = verify_code_hash(&address, &code, &Bytes::from(keccak256(&code)));
+        let mismatch = verify_code_hash(&address, &code, &Bytes::from(keccak256("0x5678")));
+
+        assert_eq!(valid, Ok(()));
+        assert!(matches!(mismatch, Err(StorageError::DecodeError(_))));
+    }
+
     #[tokio::test]
     async fn test_insert_contract() {
         let mut conn = setup_db().await;
@@ -1902,19 +3387,213 @@ mod test {
             ),
         );
         gateway
-            .upsert_contract(&expected, &mut conn)
+            .upsert_contract(&expected, &mut conn)
+            .await
+            .unwrap();
+
+        let contract_id = ContractId::new(
+            Chain::Ethereum,
+            Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"),
+        );
+        let actual = gateway
+            .get_contract(&contract_id, None, true, &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(expected, actual);
+    }
+
+    #[tokio::test]
+    async fn test_insert_contracts_share_code() {
+        let mut conn = setup_db().await;
+        let chain_id = db_fixtures::insert_chain(&mut conn, "ethereum").await;
+        let gateway = EvmGateway::from_connection(&mut conn).await;
+        let blk = db_fixtures::insert_blocks(&mut conn, chain_id).await;
+        let tx_hash = "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7";
+        db_fixtures::insert_txns(&mut conn, &[(blk[0], 1i64, tx_hash)]).await;
+        let code = Bytes::from("1234");
+        let code_hash = Bytes::from(&keccak256(&code));
+        let accounts = [
+            "6B175474E89094C44Da98b954EedeAC495271d0F",
+            "73BcE791c239c8010Cd3C857f96580037CCdd0EE",
+        ]
+        .map(|address| {
+            models::contract::Account::new(
+                Chain::Ethereum,
+                address.parse().expect("address ok"),
+                "NewAccount".to_owned(),
+                HashMap::new(),
+                Bytes::from("0x64"),
+                code.clone(),
+                code_hash.clone(),
+                tx_hash.parse().expect("txhash ok"),
+                tx_hash.parse().expect("txhash ok"),
+                Some(tx_hash.parse().expect("txhash ok")),
+            )
+        });
+
+        for account in accounts.iter() {
+            gateway
+                .upsert_contract(account, &mut conn)
+                .await
+                .unwrap();
+        }
+
+        let code_rows = schema::code::table
+            .select(schema::code::hash)
+            .get_results::<Bytes>(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(code_rows, vec![code_hash]);
+        for account in accounts {
+            let actual = gateway
+                .get_contract(
+                    &ContractId::new(Chain::Ethereum, account.address.clone()),
+                    None,
+                    true,
+                    &mut conn,
+                )
+                .await
+                .unwrap();
+            assert_eq!(actual, account);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_contract_wrong_code_hash() {
+        let mut conn = setup_db().await;
+        let chain_id = db_fixtures::insert_chain(&mut conn, "ethereum").await;
+        let gateway = EvmGateway::from_connection(&mut conn).await;
+        let blk = db_fixtures::insert_blocks(&mut conn, chain_id).await;
+        let tx_hash = "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7";
+        db_fixtures::insert_txns(&mut conn, &[(blk[0], 1i64, tx_hash)]).await;
+        let code_a = Bytes::from("1234");
+        let code_b = Bytes::from("5678");
+        let hash_a = Bytes::from(&keccak256(&code_a));
+        let account = |address: &str, code: &Bytes| {
+            models::contract::Account::new(
+                Chain::Ethereum,
+                address.parse().expect("address ok"),
+                "NewAccount".to_owned(),
+                HashMap::new(),
+                Bytes::from("0x64"),
+                code.clone(),
+                // claims code_a's hash regardless of the actual code
+                hash_a.clone(),
+                tx_hash.parse().expect("txhash ok"),
+                tx_hash.parse().expect("txhash ok"),
+                Some(tx_hash.parse().expect("txhash ok")),
+            )
+        };
+        let account_a = account("6B175474E89094C44Da98b954EedeAC495271d0F", &code_a);
+        let account_b = account("73BcE791c239c8010Cd3C857f96580037CCdd0EE", &code_b);
+
+        for acc in [&account_a, &account_b] {
+            gateway
+                .upsert_contract(acc, &mut conn)
+                .await
+                .unwrap();
+        }
+
+        let mut code_rows = schema::code::table
+            .select(schema::code::hash)
+            .get_results::<Bytes>(&mut conn)
+            .await
+            .unwrap();
+        code_rows.sort();
+        let mut expected_rows = vec![hash_a, Bytes::from(&keccak256(&code_b))];
+        expected_rows.sort();
+        assert_eq!(code_rows, expected_rows);
+        let actual = gateway
+            .get_contract(
+                &ContractId::new(Chain::Ethereum, account_b.address.clone()),
+                None,
+                true,
+                &mut conn,
+            )
+            .await
+            .unwrap();
+        assert_eq!(actual.code, code_b);
+        assert_eq!(actual.code_hash, Bytes::from(&keccak256(&code_b)));
+    }
+
+    #[tokio::test]
+    async fn test_import_contracts_round_trip() {
+        let mut conn = setup_db().await;
+        let chain_id = db_fixtures::insert_chain(&mut conn, "ethereum").await;
+        let gateway = EvmGateway::from_connection(&mut conn).await;
+        let blk = db_fixtures::insert_blocks(&mut conn, chain_id).await;
+        let tx_hash = "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7";
+        db_fixtures::insert_txns(&mut conn, &[(blk[0], 1i64, tx_hash)]).await;
+        let accounts = [
+            ("6B175474E89094C44Da98b954EedeAC495271d0F", Bytes::from("1234")),
+            ("73BcE791c239c8010Cd3C857f96580037CCdd0EE", Bytes::from("5678")),
+        ]
+        .map(|(address, code)| {
+            models::contract::Account::new(
+                Chain::Ethereum,
+                address.parse().expect("address ok"),
+                "NewAccount".to_owned(),
+                [(Bytes::from(1u8).lpad(32, 0), Bytes::from(2u8).lpad(32, 0))].into(),
+                Bytes::from("0x64"),
+                code.clone(),
+                Bytes::from(&keccak256(&code)),
+                tx_hash.parse().expect("txhash ok"),
+                tx_hash.parse().expect("txhash ok"),
+                Some(tx_hash.parse().expect("txhash ok")),
+            )
+        });
+        for account in accounts.iter() {
+            gateway
+                .upsert_contract(account, &mut conn)
+                .await
+                .unwrap();
+        }
+        let mut dump = Vec::new();
+        gateway
+            .export_contracts(&Chain::Ethereum, None, &mut dump, &mut conn)
+            .await
+            .unwrap();
+        diesel::delete(schema::account::table)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let imported = gateway
+            .import_contracts(&Chain::Ethereum, &mut dump.as_slice(), &mut conn)
             .await
             .unwrap();
 
-        let contract_id = ContractId::new(
-            Chain::Ethereum,
-            Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"),
-        );
         let actual = gateway
-            .get_contract(&contract_id, None, true, &mut conn)
+            .get_contracts(&Chain::Ethereum, None, None, true, None, &mut conn)
+            .await
+            .unwrap()
+            .entity;
+        assert_eq!(imported, 2);
+        assert_eq!(actual, accounts.to_vec());
+    }
+
+    #[tokio::test]
+    async fn test_import_contracts_malformed_line() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gateway = EvmGateway::from_connection(&mut conn).await;
+        let mut dump = Vec::new();
+        gateway
+            .export_contracts(&Chain::Ethereum, None, &mut dump, &mut conn)
             .await
             .unwrap();
-        assert_eq!(expected, actual);
+        let dump = String::from_utf8(dump).unwrap();
+        let first = dump.lines().next().unwrap();
+        let input = format!("{first}\n{{\"address\": 1}}\n");
+
+        let res = gateway
+            .import_contracts(&Chain::Ethereum, &mut input.as_bytes(), &mut conn)
+            .await;
+
+        assert!(
+            matches!(res, Err(StorageError::DecodeError(msg)) if msg.contains("line 2")),
+            "expected decode error for line 2"
+        );
     }
 
     #[tokio::test]
@@ -2112,6 +3791,120 @@ mod test {
         assert_eq!(res, exp);
     }
 
+    /// The live slots of all accounts according to the versioned storage.
+    async fn live_slots_delta(
+        gw: &EvmGateway,
+        conn: &mut AsyncPgConnection,
+    ) -> HashMap<i64, ContractStore> {
+        let chain_id = gw.get_chain_id(&Chain::Ethereum);
+        gw.get_slots_delta(chain_id, &NaiveDateTime::default(), &Utc::now().naive_utc(), conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(account_id, mut store)| {
+                store.retain(|_, value| value.is_some());
+                (account_id, store)
+            })
+            .filter(|(_, store)| !store.is_empty())
+            .collect()
+    }
+
+    /// The `contract_storage_current` snapshot of all accounts.
+    async fn current_snapshot(conn: &mut AsyncPgConnection) -> HashMap<i64, ContractStore> {
+        let mut snapshot: HashMap<i64, ContractStore> = HashMap::new();
+        for (account_id, slot, value) in schema::contract_storage_current::table
+            .select((
+                schema::contract_storage_current::account_id,
+                schema::contract_storage_current::slot,
+                schema::contract_storage_current::value,
+            ))
+            .get_results::<(i64, Bytes, Bytes)>(conn)
+            .await
+            .unwrap()
+        {
+            snapshot
+                .entry(account_id)
+                .or_default()
+                .insert(slot, Some(value));
+        }
+        snapshot
+    }
+
+    #[tokio::test]
+    async fn test_get_current_slots() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let address = Bytes::from("0x6B175474E89094C44Da98b954EedeAC495271d0F");
+        let account_id = schema::account::table
+            .filter(schema::account::address.eq(&address))
+            .select(schema::account::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        let exp = live_slots_delta(&gw, &mut conn)
+            .await
+            .remove(&account_id)
+            .unwrap();
+
+        let res = gw
+            .get_current_slots(&Chain::Ethereum, &address, &mut conn)
+            .await
+            .unwrap();
+
+        assert!(!res.is_empty());
+        assert_eq!(res, exp);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_slots_updates_current_snapshot() {
+        let mut conn = setup_db().await;
+        let (_, account_id, mut rows) = setup_slot_updates(&mut conn, false).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let (modify_tx, address, ..) = rows[0].clone();
+        rows.push((modify_tx, address, int_to_b256(1), Some(int_to_b256(1))));
+        let deletion = (rows[1].0, rows[1].1.clone(), int_to_b256(1), None);
+        gw.upsert_slots(vec![rows[0].clone(), rows[2].clone()], &mut conn)
+            .await
+            .unwrap();
+        gw.upsert_slots(vec![rows[1].clone(), deletion], &mut conn)
+            .await
+            .unwrap();
+
+        let snapshot = current_snapshot(&mut conn).await;
+
+        assert_eq!(
+            snapshot,
+            [(account_id, [(int_to_b256(0), Some(int_to_b256(11)))].into())].into()
+        );
+        assert_eq!(snapshot, live_slots_delta(&gw, &mut conn).await);
+    }
+
+    #[tokio::test]
+    async fn test_rebuild_current_snapshot() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let exp = live_slots_delta(&gw, &mut conn).await;
+        diesel::delete(schema::contract_storage_current::table)
+            .execute(&mut conn)
+            .await
+            .unwrap();
+
+        let rebuilt = gw
+            .rebuild_current_snapshot(&Chain::Ethereum, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            rebuilt,
+            exp.values()
+                .map(HashMap::len)
+                .sum::<usize>()
+        );
+        assert_eq!(current_snapshot(&mut conn).await, exp);
+    }
+
     #[tokio::test]
     async fn test_upsert_slots_against_empty_db() {
         let mut conn = setup_db().await;
@@ -2179,7 +3972,7 @@ mod test {
         .collect();
         let gw = EvmGateway::from_connection(&mut conn).await;
 
-        gw.upsert_slots(input_slots, &mut conn)
+        gw.upsert_slots(slot_rows(input_slots), &mut conn)
             .await
             .unwrap();
 
@@ -2195,6 +3988,78 @@ mod test {
         assert_eq!(fetched_slot_data, slot_data_tx_1);
     }
 
+    #[tokio::test]
+    async fn test_upsert_slots_same_slot_ordinals() {
+        let mut conn = setup_db().await;
+        let (_, _, rows) = setup_slot_updates(&mut conn, true).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+
+        gw.upsert_slots(rows, &mut conn)
+            .await
+            .unwrap();
+
+        let ordinals: Vec<(i64, Option<Bytes>)> = schema::contract_storage::table
+            .filter(schema::contract_storage::slot.eq(int_to_b256(0)))
+            .order_by(schema::contract_storage::ordinal.asc())
+            .select((schema::contract_storage::ordinal, schema::contract_storage::value))
+            .get_results(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(ordinals, vec![(0, Some(int_to_b256(10))), (1, Some(int_to_b256(11)))]);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_slots_sets_previous_value() {
+        let mut conn = setup_db().await;
+        let (chain_id, account_id, rows) = setup_slot_updates(&mut conn, false).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        for row in rows {
+            gw.upsert_slots(vec![row], &mut conn)
+                .await
+                .unwrap();
+        }
+        let exp: HashMap<i64, ContractStore> = [(
+            account_id,
+            [(int_to_b256(0), Some(int_to_b256(10)))]
+                .into_iter()
+                .collect(),
+        )]
+        .into_iter()
+        .collect();
+        let start_ts = yesterday_one_am() + Duration::from_secs(3600);
+        let end_ts = yesterday_midnight();
+
+        let res = gw
+            .get_slots_delta(chain_id, &start_ts, &end_ts, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(res, exp);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_slots_closes_previous_version() {
+        let mut conn = setup_db().await;
+        let (_, _, rows) = setup_slot_updates(&mut conn, false).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        for row in rows {
+            gw.upsert_slots(vec![row], &mut conn)
+                .await
+                .unwrap();
+        }
+
+        let versions: Vec<(NaiveDateTime, NaiveDateTime)> = schema::contract_storage::table
+            .filter(schema::contract_storage::slot.eq(int_to_b256(0)))
+            .order_by(schema::contract_storage::valid_from.asc())
+            .select((schema::contract_storage::valid_from, schema::contract_storage::valid_to))
+            .get_results(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(versions.len(), 2);
+        assert_eq!(versions[0].1, versions[1].0);
+        assert_eq!(versions[1].1, MAX_TS);
+    }
+
     #[tokio::test]
     async fn test_upsert_slots_invalidate_db_side_records() {
         let mut conn = setup_db().await;
@@ -2249,7 +4114,7 @@ mod test {
         .collect();
         let gw = EvmGateway::from_connection(&mut conn).await;
 
-        gw.upsert_slots(input_slots, &mut conn)
+        gw.upsert_slots(slot_rows(input_slots), &mut conn)
             .await
             .unwrap();
 
@@ -2265,10 +4130,88 @@ mod test {
         assert_eq!(fetched_slot_data, slot_data_tx_1);
     }
 
+    #[tokio::test]
+    async fn test_check_storage_consistency() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let c0 = get_account(&Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"), &mut conn)
+            .await
+            .unwrap();
+        let tx = schema::transaction::table
+            .select(schema::transaction::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        // archived version of slot 5 overlapping with its current version
+        db_fixtures::insert_slots(
+            &mut conn,
+            c0,
+            tx,
+            &yesterday_midnight(),
+            Some(&(yesterday_one_am() + Duration::from_secs(60))),
+            &[(5, 20, None)],
+        )
+        .await;
+
+        let res = gw
+            .check_storage_consistency(&Chain::Ethereum, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            vec![(Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"), int_to_b256(5))]
+        );
+    }
+
     fn int_to_b256(s: u64) -> Bytes {
         Bytes::from(s).lpad(32, 0)
     }
 
+    /// Inserts an account with two transactions, both in the first block if `same_block` is set
+    /// and in consecutive blocks otherwise.
+    ///
+    /// Returns the chain id, the account id and one slot row per transaction, setting slot 0 to
+    /// 10 in the first and to 11 in the second transaction.
+    async fn setup_slot_updates(
+        conn: &mut AsyncPgConnection,
+        same_block: bool,
+    ) -> (i64, i64, Vec<SlotRow>) {
+        let chain_id = db_fixtures::insert_chain(conn, "ethereum").await;
+        let blk = db_fixtures::insert_blocks(conn, chain_id).await;
+        let txn = db_fixtures::insert_txns(
+            conn,
+            &[
+                (
+                    blk[0],
+                    0i64,
+                    "0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945",
+                ),
+                (
+                    if same_block { blk[0] } else { blk[1] },
+                    1i64,
+                    "0xcb8e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130946",
+                ),
+            ],
+        )
+        .await;
+        let account_id = db_fixtures::insert_account(
+            conn,
+            "6B175474E89094C44Da98b954EedeAC495271d0F",
+            "Account1",
+            chain_id,
+            Some(txn[0]),
+        )
+        .await;
+        let address = Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F");
+        let rows = [(txn[0], 10u64), (txn[1], 11u64)]
+            .into_iter()
+            .map(|(tx, value)| (tx, address.clone(), int_to_b256(0), Some(int_to_b256(value))))
+            .collect();
+        (chain_id, account_id, rows)
+    }
+
     async fn setup_slots_delta(conn: &mut AsyncPgConnection) {
         let chain_id = db_fixtures::insert_chain(conn, "ethereum").await;
         let blk = db_fixtures::insert_blocks(conn, chain_id).await;
@@ -2290,45 +4233,150 @@ mod test {
             ],
         )
         .await;
-        let c0 = db_fixtures::insert_account(
-            conn,
+        let c0 = db_fixtures::insert_account(
+            conn,
+            "6B175474E89094C44Da98b954EedeAC495271d0F",
+            "c0",
+            chain_id,
+            Some(txn[0]),
+        )
+        .await;
+        db_fixtures::insert_slots(conn, c0, txn[0], &ts, None, &[(2, 1, None)]).await;
+        db_fixtures::insert_slots(
+            conn,
+            c0,
+            txn[0],
+            &ts,
+            Some(&ts_p1),
+            &[(0, 1, None), (1, 5, None)],
+        )
+        .await;
+        db_fixtures::insert_slots(
+            conn,
+            c0,
+            txn[1],
+            &ts_p1,
+            None,
+            &[(0, 2, Some(1)), (1, 3, Some(5)), (5, 25, None), (6, 30, None)],
+        )
+        .await;
+    }
+
+    async fn get_account(
+        address: &Address,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<i64, StorageError> {
+        Ok(schema::account::table
+            .filter(schema::account::address.eq(address))
+            .select(schema::account::id)
+            .first::<i64>(conn)
+            .await
+            .map_err(PostgresError::from)?)
+    }
+
+    #[tokio::test]
+    async fn test_get_changes_since() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let slots = |data: &[(u8, u8)]| -> ContractStore {
+            data.iter()
+                .map(|(k, v)| (bytes32(*k), Some(bytes32(*v))))
+                .collect()
+        };
+        let exp = vec![
+            AccountDelta::new(
+                Chain::Ethereum,
+                Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"),
+                slots(&[(0, 2), (1, 3), (5, 25), (6, 30)]),
+                Some(bytes32(101)),
+                None,
+                ChangeType::Update,
+            ),
+            AccountDelta::new(
+                Chain::Ethereum,
+                Bytes::from("73BcE791c239c8010Cd3C857d96580037CCdd0EE"),
+                slots(&[(0, 128), (1, 255)]),
+                Some(bytes32(50)),
+                Some(Bytes::from("C1C1C1")),
+                ChangeType::Update,
+            ),
+        ];
+
+        let res = gw
+            .get_changes_since(
+                &Chain::Ethereum,
+                &BlockIdentifier::Number((Chain::Ethereum, 1)),
+                None,
+                100,
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res, (exp, None));
+    }
+
+    #[tokio::test]
+    async fn test_get_changes_since_paginated() {
+        let mut conn = setup_db().await;
+        let chain_id = db_fixtures::insert_chain(&mut conn, "ethereum").await;
+        let blk = db_fixtures::insert_blocks(&mut conn, chain_id).await;
+        let txn = db_fixtures::insert_txns(
+            &mut conn,
+            &[(blk[1], 1i64, "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7")],
+        )
+        .await;
+        let account_id = db_fixtures::insert_account(
+            &mut conn,
             "6B175474E89094C44Da98b954EedeAC495271d0F",
-            "c0",
+            "account0",
             chain_id,
-            Some(txn[0]),
+            None,
         )
         .await;
-        db_fixtures::insert_slots(conn, c0, txn[0], &ts, None, &[(2, 1, None)]).await;
+        let changes = [(0, 10, None), (1, 11, None), (2, 12, None), (3, 13, None), (4, 14, None)];
         db_fixtures::insert_slots(
-            conn,
-            c0,
+            &mut conn,
+            account_id,
             txn[0],
-            &ts,
-            Some(&ts_p1),
-            &[(0, 1, None), (1, 5, None)],
-        )
-        .await;
-        db_fixtures::insert_slots(
-            conn,
-            c0,
-            txn[1],
-            &ts_p1,
+            &db_fixtures::yesterday_one_am(),
             None,
-            &[(0, 2, Some(1)), (1, 3, Some(5)), (5, 25, None), (6, 30, None)],
+            &changes,
         )
         .await;
-    }
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let since = BlockIdentifier::Number((Chain::Ethereum, 1));
+
+        let mut cursor = None;
+        let mut page_sizes = Vec::new();
+        let mut slots = Vec::new();
+        loop {
+            let (deltas, next) = gw
+                .get_changes_since(&Chain::Ethereum, &since, cursor.as_ref(), 2, &mut conn)
+                .await
+                .unwrap();
+            let mut page = deltas
+                .into_iter()
+                .flat_map(|delta| delta.slots.into_iter())
+                .collect::<Vec<_>>();
+            page.sort();
+            page_sizes.push(page.len());
+            slots.extend(page);
+            match next {
+                Some(next) => cursor = Some(next),
+                None => break,
+            }
+        }
 
-    async fn get_account(
-        address: &Address,
-        conn: &mut AsyncPgConnection,
-    ) -> Result<i64, StorageError> {
-        Ok(schema::account::table
-            .filter(schema::account::address.eq(address))
-            .select(schema::account::id)
-            .first::<i64>(conn)
-            .await
-            .map_err(PostgresError::from)?)
+        assert_eq!(page_sizes, vec![2, 2, 1]);
+        assert_eq!(
+            slots,
+            changes
+                .iter()
+                .map(|(k, v, _)| (bytes32(*k as u8), Some(bytes32(*v as u8))))
+                .collect::<Vec<_>>()
+        );
     }
 
     #[tokio::test]
@@ -2385,6 +4433,285 @@ mod test {
         assert_eq!(res, exp);
     }
 
+    #[rstest]
+    #[case::forward(yesterday_midnight(), yesterday_one_am() + Duration::from_secs(3600))]
+    #[case::backward(yesterday_one_am() + Duration::from_secs(3600), yesterday_midnight())]
+    #[tokio::test]
+    async fn test_get_slots_delta_strategies_match(
+        #[case] start_ts: NaiveDateTime,
+        #[case] target_ts: NaiveDateTime,
+    ) {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let chain_id = gw.get_chain_id(&Chain::Ethereum);
+        let window_gw = gw
+            .clone()
+            .with_delta_query_strategy(DeltaQueryStrategy::WindowFunction);
+
+        let distinct_on = gw
+            .get_slots_delta(chain_id, &start_ts, &target_ts, &mut conn)
+            .await
+            .unwrap();
+        let window = window_gw
+            .get_slots_delta(chain_id, &start_ts, &target_ts, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            distinct_on
+                .values()
+                .map(HashMap::len)
+                .sum::<usize>(),
+            4
+        );
+        assert_eq!(window, distinct_on);
+    }
+
+    #[tokio::test]
+    async fn test_get_slots_delta_filtered_window_function() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn)
+            .await
+            .with_delta_query_strategy(DeltaQueryStrategy::WindowFunction);
+        let addr = Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F");
+        let start = BlockOrTimestamp::Timestamp(yesterday_one_am() + Duration::from_secs(3600));
+        let end = BlockOrTimestamp::Timestamp(yesterday_midnight());
+
+        let res = gw
+            .get_slots_delta_filtered(
+                &Chain::Ethereum,
+                std::slice::from_ref(&addr),
+                &[bytes32(1u8), bytes32(5u8)],
+                Some(&start),
+                &end,
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            [(addr, [(bytes32(1u8), Some(bytes32(5u8))), (bytes32(5u8), None)].into())].into()
+        );
+    }
+
+    #[cfg(feature = "query_plan")]
+    #[tokio::test]
+    async fn test_explain_slots_delta_uses_index() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        // The fixture tables are tiny, so the planner would always pick a sequential scan.
+        // Disabling it checks that a suitable index exists at all.
+        diesel::sql_query("SET enable_seqscan = off")
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let start_ts = yesterday_midnight();
+        let end_ts = yesterday_one_am() + Duration::from_secs(3600);
+
+        let plan = gw
+            .explain_slots_delta(&Chain::Ethereum, &start_ts, &end_ts, &mut conn)
+            .await
+            .unwrap();
+
+        assert!(
+            plan.lines()
+                .any(|l| l.contains("Index") && l.contains("contract_storage")),
+            "Expected an index scan on contract_storage, got:\n{plan}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_slots_delta_filtered() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let addr = Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F");
+        let storage: ContractStore = vec![(1u8, 3u8), (5u8, 25u8)]
+            .into_iter()
+            .map(|(k, v)| (bytes32(k), Some(bytes32(v))))
+            .collect();
+        let exp: AccountToContractStore = [(addr.clone(), storage)]
+            .into_iter()
+            .collect();
+        let start = BlockOrTimestamp::Timestamp(yesterday_midnight());
+        let end = BlockOrTimestamp::Timestamp(yesterday_one_am() + Duration::from_secs(3600));
+
+        let res = gw
+            .get_slots_delta_filtered(
+                &Chain::Ethereum,
+                &[addr],
+                &[bytes32(1u8), bytes32(5u8)],
+                Some(&start),
+                &end,
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(res, exp);
+    }
+
+    #[tokio::test]
+    async fn test_get_slots_delta_ordered() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let start = BlockOrTimestamp::Timestamp(yesterday_midnight());
+        let end = BlockOrTimestamp::Timestamp(yesterday_one_am() + Duration::from_secs(3600));
+
+        let res = gw
+            .get_slots_delta_ordered(&Chain::Ethereum, Some(&start), &end, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            vec![(
+                Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"),
+                vec![
+                    (bytes32(0u8), Some(bytes32(2u8))),
+                    (bytes32(1u8), Some(bytes32(3u8))),
+                    (bytes32(5u8), Some(bytes32(25u8))),
+                    (bytes32(6u8), Some(bytes32(30u8))),
+                ]
+            )]
+        );
+    }
+
+    #[rstest]
+    #[case::forward(yesterday_midnight(), yesterday_one_am() + Duration::from_secs(3600))]
+    #[case::backward(yesterday_one_am() + Duration::from_secs(3600), yesterday_midnight())]
+    #[tokio::test]
+    async fn test_get_slots_delta_joined(
+        #[case] start_ts: NaiveDateTime,
+        #[case] target_ts: NaiveDateTime,
+    ) {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let start = BlockOrTimestamp::Timestamp(start_ts);
+        let target = BlockOrTimestamp::Timestamp(target_ts);
+
+        let by_address = gw
+            .get_slots_delta_by_address(&Chain::Ethereum, Some(&start), &target, &mut conn)
+            .await
+            .unwrap();
+        let joined = gw
+            .get_slots_delta_joined(&Chain::Ethereum, Some(&start), &target, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            by_address
+                .get(&Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"))
+                .map(HashMap::len),
+            Some(4)
+        );
+        assert_eq!(joined, by_address);
+    }
+
+    #[tokio::test]
+    async fn test_prune_storage() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let addr = Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F");
+        let account_id = get_account(&addr, &mut conn)
+            .await
+            .unwrap();
+        let txn = schema::transaction::table
+            .order_by(schema::transaction::id)
+            .select(schema::transaction::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        // slot 7 only has an expired version, it must survive pruning
+        db_fixtures::insert_slots(
+            &mut conn,
+            account_id,
+            txn,
+            &yesterday_midnight(),
+            Some(&yesterday_one_am()),
+            &[(7, 70, None)],
+        )
+        .await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+
+        let deleted = gw
+            .prune_storage(&Chain::Ethereum, yesterday_one_am() + Duration::from_secs(1), &mut conn)
+            .await
+            .unwrap();
+
+        let mut remaining = schema::contract_storage::table
+            .filter(schema::contract_storage::account_id.eq(account_id))
+            .select((schema::contract_storage::slot, schema::contract_storage::value))
+            .get_results::<(StoreKey, Option<StoreVal>)>(&mut conn)
+            .await
+            .unwrap();
+        remaining.sort();
+        assert_eq!(deleted, 2);
+        assert_eq!(
+            remaining,
+            [(0, 2), (1, 3), (2, 1), (5, 25), (6, 30), (7, 70)]
+                .into_iter()
+                .map(|(k, v)| (bytes32(k), Some(bytes32(v))))
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_contract_change_stats() {
+        let mut conn = setup_db().await;
+        setup_slots_delta(&mut conn).await;
+        let chain_id = schema::chain::table
+            .select(schema::chain::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        let txn = schema::transaction::table
+            .order_by(schema::transaction::id.desc())
+            .select(schema::transaction::id)
+            .first::<i64>(&mut conn)
+            .await
+            .unwrap();
+        let c1 = db_fixtures::insert_account(
+            &mut conn,
+            "73BcE791c239c8010Cd3C857d96580037CCdd0EE",
+            "c1",
+            chain_id,
+            None,
+        )
+        .await;
+        db_fixtures::insert_slots(
+            &mut conn,
+            c1,
+            txn,
+            &yesterday_one_am(),
+            None,
+            &[(0, 1, None), (1, 2, None)],
+        )
+        .await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let start = BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 1)));
+        let end = BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2)));
+
+        let res = gw
+            .contract_change_stats(&Chain::Ethereum, &start, &end, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            res,
+            vec![
+                (Bytes::from("6B175474E89094C44Da98b954EedeAC495271d0F"), 4),
+                (Bytes::from("73BcE791c239c8010Cd3C857d96580037CCdd0EE"), 2),
+            ]
+        );
+    }
+
     #[rstest]
     #[case::with_start_version(
         Some(BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2))))
@@ -2490,6 +4817,142 @@ mod test {
         assert_eq!(changes, exp);
     }
 
+    /// Records the fields of all spans, keyed by span name.
+    #[derive(Clone, Default)]
+    struct SpanRecorder(Arc<std::sync::Mutex<HashMap<String, HashMap<String, String>>>>);
+
+    struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+    impl Visit for FieldVisitor<'_> {
+        fn record_str(&mut self, field: &Field, value: &str) {
+            self.0
+                .insert(field.name().to_string(), value.to_string());
+        }
+
+        fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{:?}", value));
+        }
+    }
+
+    impl SpanRecorder {
+        fn record(&self, name: &str, fields: HashMap<String, String>) {
+            self.0
+                .lock()
+                .unwrap()
+                .entry(name.to_string())
+                .or_default()
+                .extend(fields);
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for SpanRecorder {
+        fn on_new_span(&self, attrs: &Attributes<'_>, _id: &Id, _ctx: Context<'_, S>) {
+            let mut fields = HashMap::new();
+            attrs.record(&mut FieldVisitor(&mut fields));
+            self.record(attrs.metadata().name(), fields);
+        }
+
+        fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+            if let Some(span) = ctx.span(id) {
+                let mut fields = HashMap::new();
+                values.record(&mut FieldVisitor(&mut fields));
+                self.record(span.name(), fields);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_accounts_delta_spans() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EvmGateway::from_connection(&mut conn).await;
+        let recorder = SpanRecorder::default();
+        let _guard =
+            tracing::subscriber::set_default(tracing_subscriber::registry().with(recorder.clone()));
+
+        gw.get_accounts_delta(
+            &Chain::Ethereum,
+            Some(&BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2)))),
+            &BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 1))),
+            &mut conn,
+        )
+        .await
+        .unwrap();
+
+        let spans = recorder.0.lock().unwrap();
+        let slots_span = &spans["get_slots_delta"];
+        assert_eq!(slots_span["chain"], "ethereum");
+        assert_eq!(slots_span["direction"], "backward");
+        assert!(slots_span.contains_key("start_version_ts"));
+        assert!(slots_span.contains_key("target_version_ts"));
+        assert!(spans["maybe_lookup_block_ts"].contains_key("ts"));
+    }
+
+    #[derive(Default)]
+    struct RecordingMetrics(std::sync::Mutex<Vec<(String, usize)>>);
+
+    impl GatewayMetrics for RecordingMetrics {
+        fn on_query(&self, name: &str, _duration: Duration, rows: usize) {
+            self.0
+                .lock()
+                .unwrap()
+                .push((name.to_string(), rows));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_accounts_delta_records_metrics() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let metrics = Arc::new(RecordingMetrics::default());
+        let gw = EvmGateway::from_connection(&mut conn)
+            .await
+            .with_metrics(metrics.clone());
+
+        gw.get_accounts_delta(
+            &Chain::Ethereum,
+            Some(&BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 1)))),
+            &BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2))),
+            &mut conn,
+        )
+        .await
+        .unwrap();
+
+        let events = metrics.0.lock().unwrap().clone();
+        let names: Vec<_> = events
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect();
+        assert_eq!(names, vec!["block_ts", "block_ts", "slots_delta", "account_addresses"]);
+        // c0 changed 4 slots and c1 was created with 2 slots
+        assert_eq!(events[2].1, 6);
+    }
+
+    #[tokio::test]
+    async fn test_version_to_ts_cached() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let metrics = Arc::new(RecordingMetrics::default());
+        let gw = EvmGateway::from_connection(&mut conn)
+            .await
+            .with_metrics(metrics.clone());
+        let version = BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2)));
+
+        let first = gw
+            .version_to_ts(&version, &mut conn)
+            .await
+            .unwrap();
+        let second = gw
+            .version_to_ts(&version, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(first, db_fixtures::yesterday_one_am());
+        assert_eq!(second, first);
+        assert_eq!(*metrics.0.lock().unwrap(), vec![("block_ts".to_string(), 1)]);
+    }
+
     #[rstest]
     #[case::forward("forward")]
     #[case::backward("backward")]
@@ -1,7 +1,10 @@
 use super::{orm, schema, storage_error_from_diesel, PostgresGateway, StorageError};
-use diesel::{ExpressionMethods, QueryDsl};
+use diesel::{upsert::excluded, ExpressionMethods, OptionalExtension, QueryDsl};
 use diesel_async::{AsyncPgConnection, RunQueryDsl};
-use tycho_core::models::{Chain, ExtractionState};
+use tycho_core::{
+    models::{BlockHash, Chain, ExtractionState},
+    storage::BlockIdentifier,
+};
 
 impl PostgresGateway {
     pub async fn get_state(
@@ -87,6 +90,65 @@ impl PostgresGateway {
         }
         Ok(())
     }
+
+    /// Retrieves the last block processed for a protocol system.
+    ///
+    /// Returns `Ok(None)` if no cursor was set yet, e.g. on the first run of an indexer, or if
+    /// the block it pointed to was reverted since.
+    pub async fn get_cursor(
+        &self,
+        chain: &Chain,
+        protocol_system: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<BlockIdentifier>, StorageError> {
+        let chain_id = self.get_chain_id(chain);
+        let hash = schema::cursor::table
+            .inner_join(schema::protocol_system::table)
+            .inner_join(schema::block::table)
+            .filter(schema::cursor::chain_id.eq(chain_id))
+            .filter(schema::protocol_system::name.eq(protocol_system))
+            .select(schema::block::hash)
+            .first::<BlockHash>(conn)
+            .await
+            .optional()
+            .map_err(|err| storage_error_from_diesel(err, "Cursor", protocol_system, None))?;
+        Ok(hash.map(BlockIdentifier::Hash))
+    }
+
+    /// Sets the last block processed for a protocol system, replacing any previous cursor.
+    ///
+    /// Both the block and the protocol system must already be stored.
+    pub async fn set_cursor(
+        &self,
+        chain: &Chain,
+        protocol_system: &str,
+        block: &BlockIdentifier,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        let block_id = orm::Block::by_id(block, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &block.to_string(), None))?
+            .id;
+        let protocol_system_id = schema::protocol_system::table
+            .filter(schema::protocol_system::name.eq(protocol_system))
+            .select(schema::protocol_system::id)
+            .first::<i64>(conn)
+            .await
+            .map_err(|err| {
+                storage_error_from_diesel(err, "ProtocolSystem", protocol_system, None)
+            })?;
+        let new =
+            orm::NewCursor { chain_id: self.get_chain_id(chain), protocol_system_id, block_id };
+        diesel::insert_into(schema::cursor::table)
+            .values(&new)
+            .on_conflict((schema::cursor::chain_id, schema::cursor::protocol_system_id))
+            .do_update()
+            .set(schema::cursor::block_id.eq(excluded(schema::cursor::block_id)))
+            .execute(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Cursor", protocol_system, None))?;
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -240,4 +302,38 @@ mod test {
             "20".to_owned().into_bytes()
         );
     }
+
+    #[tokio::test]
+    async fn test_set_and_get_cursor() {
+        let mut conn = setup_db().await;
+        let gateway = get_dgw(&mut conn).await;
+        crate::postgres::db_fixtures::insert_protocol_system(&mut conn, "ambient".to_string())
+            .await;
+
+        let first_run = gateway
+            .get_cursor(&Chain::Ethereum, "ambient", &mut conn)
+            .await
+            .unwrap();
+        assert_eq!(first_run, None);
+
+        for number in [1, 2] {
+            let block = BlockIdentifier::Number((Chain::Ethereum, number));
+            gateway
+                .set_cursor(&Chain::Ethereum, "ambient", &block, &mut conn)
+                .await
+                .unwrap();
+            let expected = gateway
+                .get_block(&block, &mut conn)
+                .await
+                .unwrap()
+                .hash;
+
+            let cursor = gateway
+                .get_cursor(&Chain::Ethereum, "ambient", &mut conn)
+                .await
+                .unwrap();
+
+            assert_eq!(cursor, Some(BlockIdentifier::Hash(expected)));
+        }
+    }
 }
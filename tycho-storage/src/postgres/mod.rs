@@ -107,6 +107,21 @@
 //! redundancy is to avoid additional joins and further optimize query
 //! performance.
 //!
+//! The table is partitioned by list over `chain_id`, a copy of the account's chain,
+//! and each chain partition is in turn partitioned by range over `valid_to`, both
+//! levels managed by pg_partman. The default sub-partition of a chain holds the
+//! currently valid versions and each outdated version moves into the daily
+//! sub-partition it expired on. Queries go through the `contract_storage` parent
+//! and filter on `chain_id`, so Postgres only scans the partitions of that chain.
+//! Current versions are unique per `(account_id, slot)` through a partial unique
+//! index on each default sub-partition, which is why they are upserted into the
+//! chain's default sub-partition directly instead of the parent.
+//!
+//! The current value of each live slot is additionally kept in the plain
+//! `contract_storage_current` table, which the slot writer and reverts maintain
+//! together with the versions. It serves current state reads without touching
+//! the versioned table.
+//!
 //! ### Reverts
 //! If a reorg is observed, we will be asked by the stream to revert to a previous
 //! block number. This is handled using the `ON DELETE CASCADE` feature provided by
@@ -128,19 +143,31 @@
 //! into a single transaction. This guarantees preservation of valid state
 //! throughout the application lifetime, even if the process panics during
 //! database operations.
-use std::{collections::HashMap, hash::Hash, ops::Deref, str::FromStr, sync::Arc, time::Duration};
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    num::NonZeroUsize,
+    ops::Deref,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
 
 use chrono::NaiveDateTime;
 use diesel::prelude::*;
 use diesel_async::{
-    pooled_connection::{deadpool::Pool, AsyncDieselConnectionManager},
+    pooled_connection::{
+        deadpool::Pool, AsyncDieselConnectionManager, ManagerConfig, RecycleCheckCallback,
+        RecyclingMethod,
+    },
     AsyncPgConnection, RunQueryDsl,
 };
 use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
-use tracing::{debug, info};
+use lru::LruCache;
+use tracing::{debug, info, instrument, Level, Span};
 
 use tycho_core::{
-    models::{Chain, TxHash},
+    models::{BlockHash, Chain, TxHash},
     storage::{BlockIdentifier, BlockOrTimestamp, StorageError, Version, VersionKind},
 };
 use unicode_segmentation::UnicodeSegmentation;
@@ -152,9 +179,13 @@ mod contract;
 mod extraction_state;
 mod orm;
 mod protocol;
+pub mod retry;
 mod schema;
 mod versioning;
 
+#[doc(hidden)]
+pub use contract::group_slot_rows;
+
 const MIGRATIONS: EmbeddedMigrations = embed_migrations!("./migrations/");
 
 // +262142-12-31T23:59:59.999999999
@@ -214,19 +245,14 @@ where
         })
     }
 
-    /// Retrieves the corresponding enum variant for a database ID. Panics on
-    /// cache miss.
+    /// Retrieves the corresponding enum variant for a database ID, returning `None` on a cache
+    /// miss.
     ///
     /// # Arguments
     ///
     /// * `id` - The database ID to lookup.
-    fn get_value(&self, id: &i64) -> E {
-        self.map_enum
-            .get(id)
-            .unwrap_or_else(|| {
-                panic!("Unexpected cache miss for id {}, entries: {:?}", id, self.map_enum)
-            })
-            .to_owned()
+    fn try_get_value(&self, id: &i64) -> Option<E> {
+        self.map_enum.get(id).cloned()
     }
 
     /// Checks if an enum variant exists in the cache. Returns `true` if the variant is found,
@@ -324,10 +350,25 @@ struct PostgresError(StorageError);
 
 impl From<diesel::result::Error> for PostgresError {
     fn from(value: diesel::result::Error) -> Self {
+        if is_transient(&value) {
+            return PostgresError(StorageError::Transient(format!("DieselError: {}", value)));
+        }
         PostgresError(StorageError::Unexpected(format!("DieselError: {}", value)))
     }
 }
 
+/// Whether the error stems from the connection rather than the query, so that retrying the
+/// query on a fresh connection may succeed.
+fn is_transient(err: &diesel::result::Error) -> bool {
+    matches!(
+        err,
+        diesel::result::Error::DatabaseError(
+            diesel::result::DatabaseErrorKind::ClosedConnection,
+            _
+        ) | diesel::result::Error::BrokenTransactionManager
+    )
+}
+
 impl From<PostgresError> for StorageError {
     fn from(value: PostgresError) -> Self {
         value.0
@@ -388,33 +429,39 @@ fn storage_error_from_diesel(
             }
             PostgresError(StorageError::NotFound(entity.to_owned(), id.to_owned()))
         }
+        _ if is_transient(&err) => PostgresError(StorageError::Transient(err_string)),
         _ => PostgresError(StorageError::Unexpected(err_string)),
     }
 }
 
+#[instrument(level = Level::DEBUG, skip(conn), fields(ts))]
 async fn maybe_lookup_block_ts(
     block: &BlockOrTimestamp,
     conn: &mut AsyncPgConnection,
 ) -> Result<NaiveDateTime, StorageError> {
-    match block {
-        BlockOrTimestamp::Block(BlockIdentifier::Hash(h)) => Ok(orm::Block::by_hash(h, conn)
-            .await
-            .map_err(|err| storage_error_from_diesel(err, "Block", &hex::encode(h), None))?
-            .ts),
+    let ts = match block {
+        BlockOrTimestamp::Block(BlockIdentifier::Hash(h)) => {
+            orm::Block::by_hash(h, conn)
+                .await
+                .map_err(|err| storage_error_from_diesel(err, "Block", &hex::encode(h), None))?
+                .ts
+        }
         BlockOrTimestamp::Block(BlockIdentifier::Number((chain, no))) => {
-            Ok(orm::Block::by_number(*chain, *no, conn)
+            orm::Block::by_number(*chain, *no, conn)
                 .await
                 .map_err(|err| storage_error_from_diesel(err, "Block", &format!("{}", no), None))?
-                .ts)
+                .ts
         }
         BlockOrTimestamp::Block(BlockIdentifier::Latest(chain)) => {
-            Ok(orm::Block::most_recent(*chain, conn)
+            orm::Block::most_recent(*chain, conn)
                 .await
                 .map_err(|err| storage_error_from_diesel(err, "Block", "latest", None))?
-                .ts)
+                .ts
         }
-        BlockOrTimestamp::Timestamp(ts) => Ok(*ts),
-    }
+        BlockOrTimestamp::Timestamp(ts) => *ts,
+    };
+    Span::current().record("ts", tracing::field::display(ts));
+    Ok(ts)
 }
 
 async fn maybe_lookup_version_ts(
@@ -427,10 +474,84 @@ async fn maybe_lookup_version_ts(
     maybe_lookup_block_ts(&version.0, conn).await
 }
 
+/// Receives timings of expensive gateway queries.
+///
+/// Implementations must be cheap, they are called inline after each instrumented query.
+pub trait GatewayMetrics: Send + Sync {
+    /// Called once a query completed, with the number of rows it returned.
+    fn on_query(&self, _name: &str, _duration: Duration, _rows: usize) {}
+}
+
+/// How the slot delta queries pick the version of each changed slot that determines its delta.
+///
+/// Both strategies return identical results, they only differ in the query Postgres has to
+/// plan, which may favour one or the other depending on data and index shapes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DeltaQueryStrategy {
+    /// Deduplicates the sorted versions with `DISTINCT ON (account_id, slot)`.
+    #[default]
+    DistinctOn,
+    /// Ranks the versions of each slot with `ROW_NUMBER()` and keeps the first one.
+    WindowFunction,
+}
+
+/// Default number of entries kept per identifier kind by [`BlockTsCache`].
+pub const DEFAULT_BLOCK_TS_CACHE_SIZE: usize = 4096;
+
+/// Timestamps of recently resolved blocks, keyed by the identifier they were requested with.
+///
+/// A block's timestamp never changes, but reverts may delete blocks or change which block a
+/// number refers to, so the gateway clears the cache whenever it reverts.
+struct BlockTsCache {
+    by_hash: LruCache<BlockHash, NaiveDateTime>,
+    by_number: LruCache<(Chain, i64), NaiveDateTime>,
+}
+
+impl BlockTsCache {
+    fn new(size: NonZeroUsize) -> Self {
+        Self { by_hash: LruCache::new(size), by_number: LruCache::new(size) }
+    }
+
+    fn get(&mut self, id: &BlockIdentifier) -> Option<NaiveDateTime> {
+        match id {
+            BlockIdentifier::Hash(hash) => self.by_hash.get(hash).copied(),
+            BlockIdentifier::Number(key) => self.by_number.get(key).copied(),
+            BlockIdentifier::Latest(_) => None,
+        }
+    }
+
+    fn put(&mut self, id: &BlockIdentifier, ts: NaiveDateTime) {
+        match id {
+            BlockIdentifier::Hash(hash) => {
+                self.by_hash.put(hash.clone(), ts);
+            }
+            BlockIdentifier::Number(key) => {
+                self.by_number.put(*key, ts);
+            }
+            BlockIdentifier::Latest(_) => {}
+        }
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct PostgresGateway {
     protocol_system_id_cache: Arc<ProtocolSystemEnumCache>,
+    /// Ids of protocol systems registered after the gateway was created, shared by all clones
+    /// of the gateway.
+    protocol_system_ids: Arc<Mutex<HashMap<String, i64>>>,
     chain_id_cache: Arc<ChainEnumCache>,
+    metrics: Option<Arc<dyn GatewayMetrics>>,
+    /// Timestamps of recently resolved blocks, shared by all clones of the gateway.
+    block_ts_cache: Arc<Mutex<BlockTsCache>>,
+    delta_query_strategy: DeltaQueryStrategy,
+    /// Whether writing a component of an unknown protocol system registers the system instead
+    /// of failing.
+    auto_register_systems: bool,
+    /// Whether writing a component that holds unknown tokens stores placeholders for these
+    /// tokens instead of failing.
+    auto_create_missing_tokens: bool,
+    /// Whether contract code read from the db is checked against its stored hash.
+    verify_code_hash: bool,
     /// Any versions dated before this date, as per their `valid_to` column, will be
     /// discarded and never be inserted into the db. We supply this as an absolute date
     /// since updating it must be done carefully. To avoid gaps in versions this can't
@@ -447,11 +568,106 @@ impl PostgresGateway {
     ) -> Self {
         Self {
             protocol_system_id_cache: protocol_system_cache,
+            protocol_system_ids: Arc::new(Mutex::new(HashMap::new())),
             chain_id_cache: cache,
+            metrics: None,
+            block_ts_cache: Arc::new(Mutex::new(BlockTsCache::new(
+                NonZeroUsize::new(DEFAULT_BLOCK_TS_CACHE_SIZE).expect("cache size is non zero"),
+            ))),
+            delta_query_strategy: DeltaQueryStrategy::default(),
+            auto_register_systems: false,
+            auto_create_missing_tokens: false,
+            verify_code_hash: false,
             retention_horizon,
         }
     }
 
+    /// Reports query timings of this gateway to `metrics`.
+    pub fn with_metrics(mut self, metrics: Arc<dyn GatewayMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Makes contract reads hash the stored code and fail on a mismatch with the stored code
+    /// hash. Off by default as hashing large contracts on every read is not free.
+    pub fn with_code_hash_verification(mut self, verify: bool) -> Self {
+        self.verify_code_hash = verify;
+        self
+    }
+
+    /// Sets how many resolved block timestamps are cached, per hash and per number. Defaults
+    /// to [`DEFAULT_BLOCK_TS_CACHE_SIZE`].
+    pub fn with_block_ts_cache_size(mut self, size: NonZeroUsize) -> Self {
+        self.block_ts_cache = Arc::new(Mutex::new(BlockTsCache::new(size)));
+        self
+    }
+
+    /// Selects the query used to retrieve slot deltas, see [`DeltaQueryStrategy`].
+    pub fn with_delta_query_strategy(mut self, strategy: DeltaQueryStrategy) -> Self {
+        self.delta_query_strategy = strategy;
+        self
+    }
+
+    /// Makes component writes register protocol systems seen for the first time. Off by
+    /// default, so a typo in a system name fails the write instead of creating a new system.
+    pub fn with_auto_register_systems(mut self, auto_register: bool) -> Self {
+        self.auto_register_systems = auto_register;
+        self
+    }
+
+    /// Makes component writes store placeholder tokens for token addresses that are not stored
+    /// yet. Placeholders use the address as symbol, 18 decimals and a quality of 0, so they
+    /// are picked up by token analysis later on. Off by default.
+    pub fn with_auto_create_missing_tokens(mut self, auto_create: bool) -> Self {
+        self.auto_create_missing_tokens = auto_create;
+        self
+    }
+
+    fn record_query(&self, name: &str, start: Instant, rows: usize) {
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.on_query(name, start.elapsed(), rows);
+        }
+    }
+
+    /// Resolves a version to a timestamp, serving blocks resolved before from the cache.
+    ///
+    /// Blocks identified by hash or number are cached, `Latest` is always looked up. Lookups
+    /// that hit the database are reported to the metrics as `block_ts` queries.
+    async fn version_to_ts(
+        &self,
+        version: &BlockOrTimestamp,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<NaiveDateTime, StorageError> {
+        let id = match version {
+            BlockOrTimestamp::Block(
+                id @ (BlockIdentifier::Hash(_) | BlockIdentifier::Number(_)),
+            ) => id,
+            _ => return maybe_lookup_block_ts(version, conn).await,
+        };
+        if let Some(ts) = self.block_ts_cache().get(id) {
+            return Ok(ts);
+        }
+        let start = Instant::now();
+        let ts = maybe_lookup_block_ts(version, conn).await?;
+        self.record_query("block_ts", start, 1);
+        self.block_ts_cache().put(id, ts);
+        Ok(ts)
+    }
+
+    /// Drops all cached block timestamps, required after reverts: these change which block a
+    /// number refers to and may delete blocks.
+    fn clear_block_ts_cache(&self) {
+        let mut cache = self.block_ts_cache();
+        cache.by_hash.clear();
+        cache.by_number.clear();
+    }
+
+    fn block_ts_cache(&self) -> std::sync::MutexGuard<'_, BlockTsCache> {
+        self.block_ts_cache
+            .lock()
+            .expect("block ts cache lock poisoned")
+    }
+
     #[allow(dead_code)]
     pub async fn from_connection(conn: &mut AsyncPgConnection) -> Self {
         let chain_id_mapping: Vec<(i64, String)> = async {
@@ -484,20 +700,65 @@ impl PostgresGateway {
         self.chain_id_cache.get_id(chain)
     }
 
+    /// Resolves a chain database id known to the gateway. Panics on cache miss.
     fn get_chain(&self, id: &i64) -> Chain {
-        self.chain_id_cache.get_value(id)
+        self.chain_from_id(*id)
+            .unwrap_or_else(|| panic!("Unexpected cache miss for chain id {id}"))
     }
 
-    fn get_protocol_system_id(&self, protocol_system: &String) -> i64 {
-        self.protocol_system_id_cache
-            .get_id(protocol_system)
+    /// Resolves a chain database id to its `Chain`.
+    ///
+    /// Served from the chain cache preloaded at construction, so no query is issued. Returns
+    /// `None` if the id is unknown.
+    pub fn chain_from_id(&self, id: i64) -> Option<Chain> {
+        self.chain_id_cache.try_get_value(&id)
     }
 
+    /// Fetches the id of a protocol system known to the gateway. Panics on cache miss.
+    fn get_cached_protocol_system_id(&self, protocol_system: &String) -> i64 {
+        self.try_get_protocol_system_id(protocol_system)
+            .unwrap_or_else(|| {
+                panic!("Unexpected cache miss for protocol system {protocol_system}")
+            })
+    }
+
+    /// Fetches the name of a protocol system known to the gateway. Panics on cache miss.
     fn get_protocol_system(&self, id: &i64) -> String {
         self.protocol_system_id_cache
-            .get_value(id)
+            .try_get_value(id)
+            .or_else(|| {
+                self.protocol_system_ids()
+                    .iter()
+                    .find(|(_, system_id)| *system_id == id)
+                    .map(|(name, _)| name.clone())
+            })
+            .unwrap_or_else(|| panic!("Unexpected cache miss for protocol system id {id}"))
     }
 
+    /// Fetches the id of a protocol system that was either preloaded or resolved since.
+    fn try_get_protocol_system_id(&self, name: &str) -> Option<i64> {
+        self.protocol_system_id_cache
+            .map_id
+            .get(name)
+            .copied()
+            .or_else(|| {
+                self.protocol_system_ids()
+                    .get(name)
+                    .copied()
+            })
+    }
+
+    fn protocol_system_ids(&self) -> std::sync::MutexGuard<'_, HashMap<String, i64>> {
+        self.protocol_system_ids
+            .lock()
+            .expect("protocol system id cache lock poisoned")
+    }
+
+    /// Creates a gateway whose enum caches are loaded through `pool`.
+    ///
+    /// The gateway itself holds no connection: callers check out a connection from the pool per
+    /// query (see `CachedGateway`), so concurrent reads do not contend on a single connection.
+    /// The caches are reference counted and shared by all clones of the gateway.
     pub async fn new(
         pool: Pool<AsyncPgConnection>,
         retention_horizon: NaiveDateTime,
@@ -525,6 +786,8 @@ impl PostgresGateway {
 /// # Arguments
 ///
 /// - `db_url`: A string slice that holds the URL of the database to connect to.
+/// - `statement_cache_size`: Bounds the prepared statement cache of each pooled connection, see
+///   [`build_pool`].
 ///
 /// # Returns
 ///
@@ -533,15 +796,61 @@ impl PostgresGateway {
 /// - `Ok`: Contains a `Pool` of `AsyncPgConnection`s if the connection was established
 ///   successfully.
 /// - `Err`: Contains a `StorageError` if there was an issue creating the connection pool.
-async fn connect(db_url: &str) -> Result<Pool<AsyncPgConnection>, StorageError> {
-    let config = AsyncDieselConnectionManager::<AsyncPgConnection>::new(db_url);
-    let pool = Pool::builder(config)
-        .build()
-        .map_err(|err| StorageError::Unexpected(format!("{}", err)))?;
+async fn connect(
+    db_url: &str,
+    statement_cache_size: Option<usize>,
+) -> Result<Pool<AsyncPgConnection>, StorageError> {
+    let pool = build_pool(db_url, statement_cache_size)?;
     run_migrations(db_url);
     Ok(pool)
 }
 
+/// Creates a connection pool without touching the database.
+///
+/// Each connection prepares a distinct query once and reuses the statement for as long as the
+/// connection lives. diesel-async has no setting to bound this cache, so with
+/// `statement_cache_size` set, the pool counts the prepared statements of a connection
+/// whenever it is handed out again and replaces connections holding more than
+/// `statement_cache_size` statements with fresh ones. A connection can exceed the size while
+/// checked out, a size of 0 effectively disables reusing statements across checkouts.
+///
+/// Defaults to `None`, an unbounded cache.
+fn build_pool(
+    db_url: &str,
+    statement_cache_size: Option<usize>,
+) -> Result<Pool<AsyncPgConnection>, StorageError> {
+    let mut manager_config = ManagerConfig::default();
+    if let Some(size) = statement_cache_size {
+        manager_config.recycling_method =
+            RecyclingMethod::CustomFunction(check_statement_cache_size(size));
+    }
+    let config =
+        AsyncDieselConnectionManager::<AsyncPgConnection>::new_with_config(db_url, manager_config);
+    Pool::builder(config)
+        .build()
+        .map_err(|err| StorageError::Unexpected(format!("{}", err)))
+}
+
+/// Recycle check rejecting connections that hold more than `size` prepared statements.
+fn check_statement_cache_size(size: usize) -> Box<RecycleCheckCallback<AsyncPgConnection>> {
+    Box::new(move |conn| {
+        Box::pin(async move {
+            let prepared = diesel::select(diesel::dsl::sql::<diesel::sql_types::BigInt>(
+                "(SELECT count(*) FROM pg_prepared_statements)",
+            ))
+            .get_result::<i64>(conn)
+            .await?;
+            if prepared as usize > size {
+                return Err(diesel::result::Error::QueryBuilderError(
+                    format!("{prepared} prepared statements exceed the cache size of {size}")
+                        .into(),
+                ));
+            }
+            Ok(())
+        })
+    })
+}
+
 /// Ensures the `Chain` enum is present in the database, if not it inserts it.
 ///
 /// This function serves as a way to ensure all chains found within the `chains`  
@@ -641,6 +950,7 @@ pub mod testing {
             "token",
             "account",
             "protocol_state",
+            "protocol_component_link",
             "protocol_component",
             "protocol_type",
             "protocol_system",
@@ -749,7 +1059,7 @@ pub mod db_fixtures {
     use std::str::FromStr;
 
     use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
-    use diesel::{prelude::*, sql_query};
+    use diesel::{prelude::*, sql_query, upsert::excluded};
     use diesel_async::{AsyncPgConnection, RunQueryDsl};
     use serde_json::Value;
 
@@ -911,6 +1221,12 @@ pub mod db_fixtures {
         valid_to: Option<&NaiveDateTime>,
         slots: &[(u64, u64, Option<u64>)],
     ) {
+        let chain_id = schema::account::table
+            .filter(schema::account::id.eq(contract_id))
+            .select(schema::account::chain_id)
+            .first::<i64>(conn)
+            .await
+            .expect("setup account id not found");
         let data = slots
             .iter()
             .enumerate()
@@ -922,6 +1238,7 @@ pub mod db_fixtures {
                         .eq(hex::decode(format!("{:064x}", *v)).unwrap()),
                     schema::contract_storage::previous_value.eq(previous_value),
                     schema::contract_storage::account_id.eq(contract_id),
+                    schema::contract_storage::chain_id.eq(chain_id),
                     schema::contract_storage::modify_tx.eq(modify_tx),
                     schema::contract_storage::valid_from.eq(valid_from),
                     schema::contract_storage::valid_to.eq(valid_to.unwrap_or(&MAX_TS)),
@@ -935,6 +1252,43 @@ pub mod db_fixtures {
             .execute(conn)
             .await
             .unwrap();
+
+        // Current versions are mirrored into the snapshot, like the slot writer does.
+        if valid_to.is_none() {
+            let current = slots
+                .iter()
+                .enumerate()
+                .map(|(idx, (k, v, _))| orm::NewCurrentSlot {
+                    account_id: contract_id,
+                    chain_id,
+                    slot: Bytes::from(hex::decode(format!("{:064x}", *k)).unwrap()),
+                    value: Bytes::from(hex::decode(format!("{:064x}", *v)).unwrap()),
+                    modify_tx,
+                    ordinal: idx as i64,
+                    valid_from: *valid_from,
+                })
+                .collect::<Vec<_>>();
+            diesel::insert_into(schema::contract_storage_current::table)
+                .values(&current)
+                .on_conflict((
+                    schema::contract_storage_current::account_id,
+                    schema::contract_storage_current::slot,
+                ))
+                .do_update()
+                .set((
+                    schema::contract_storage_current::value
+                        .eq(excluded(schema::contract_storage_current::value)),
+                    schema::contract_storage_current::modify_tx
+                        .eq(excluded(schema::contract_storage_current::modify_tx)),
+                    schema::contract_storage_current::ordinal
+                        .eq(excluded(schema::contract_storage_current::ordinal)),
+                    schema::contract_storage_current::valid_from
+                        .eq(excluded(schema::contract_storage_current::valid_from)),
+                ))
+                .execute(conn)
+                .await
+                .unwrap();
+        }
     }
 
     pub async fn insert_account_balance(
@@ -986,9 +1340,17 @@ pub mod db_fixtures {
             .await
             .expect("setup tx id not found");
 
+        let hash = Bytes::from(&keccak256(&code));
+        diesel::insert_into(schema::code::table)
+            .values((schema::code::hash.eq(&hash), schema::code::bytecode.eq(&code)))
+            .on_conflict(schema::code::hash)
+            .do_nothing()
+            .execute(conn)
+            .await
+            .unwrap();
+
         let data = (
-            schema::contract_code::code.eq(&code),
-            schema::contract_code::hash.eq(Bytes::from(&keccak256(&code))),
+            schema::contract_code::hash.eq(&hash),
             schema::contract_code::account_id.eq(account_id),
             schema::contract_code::modify_tx.eq(modify_tx),
             schema::contract_code::valid_from.eq(ts),
@@ -1329,3 +1691,40 @@ pub mod db_fixtures {
         .expect("calculating fixture component tvl failed");
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn backend_pid(pool: &Pool<AsyncPgConnection>) -> i32 {
+        let mut conn = pool.get().await.unwrap();
+        // a cacheable query, so the connection holds a prepared statement afterwards
+        schema::chain::table
+            .select(schema::chain::id)
+            .load::<i64>(&mut conn)
+            .await
+            .unwrap();
+        diesel::select(diesel::dsl::sql::<diesel::sql_types::Integer>("pg_backend_pid()"))
+            .get_result(&mut conn)
+            .await
+            .unwrap()
+    }
+
+    #[rstest::rstest]
+    #[case::unbounded(None, true)]
+    #[case::within_size(Some(10), true)]
+    #[case::exceeding_size(Some(0), false)]
+    #[tokio::test]
+    async fn test_build_pool_statement_cache_size(
+        #[case] statement_cache_size: Option<usize>,
+        #[case] exp_reused: bool,
+    ) {
+        let db_url = std::env::var("DATABASE_URL").unwrap();
+        let pool = build_pool(&db_url, statement_cache_size).unwrap();
+
+        let first = backend_pid(&pool).await;
+        let second = backend_pid(&pool).await;
+
+        assert_eq!(first == second, exp_reused);
+    }
+}
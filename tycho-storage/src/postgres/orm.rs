@@ -1,9 +1,10 @@
 use super::{
     schema::{
-        account, account_balance, block, chain, component_balance, component_balance_default,
-        component_tvl, contract_code, contract_storage, contract_storage_default, extraction_state,
-        protocol_component, protocol_component_holds_contract, protocol_component_holds_token,
-        protocol_state, protocol_state_default, protocol_system, protocol_type, token, transaction,
+        account, account_balance, block, chain, code, component_balance, component_balance_default,
+        component_tvl, contract_code, contract_storage, contract_storage_current, cursor,
+        extraction_state, protocol_component, protocol_component_holds_contract,
+        protocol_component_holds_token, protocol_state, protocol_state_default, protocol_system,
+        protocol_type, token, transaction,
     },
     versioning::{StoredVersionedRow, VersionedRow},
     PostgresError, MAX_TS, MAX_VERSION_TS,
@@ -23,7 +24,7 @@ use diesel_async::{AsyncPgConnection, RunQueryDsl};
 use diesel_derive_enum::DbEnum;
 use std::collections::{HashMap, HashSet};
 use tycho_core::{
-    models,
+    keccak256, models,
     models::{
         Address, AttrStoreKey, Balance, BlockHash, Code, CodeHash, ComponentId, ContractId,
         PaginationParams, StoreVal, TxHash,
@@ -129,6 +130,15 @@ pub struct NewExtractionState<'a> {
     pub modified_ts: NaiveDateTime,
 }
 
+#[derive(Insertable)]
+#[diesel(table_name = cursor)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewCursor {
+    pub chain_id: i64,
+    pub protocol_system_id: i64,
+    pub block_id: i64,
+}
+
 #[derive(AsChangeset, Debug)]
 #[diesel(table_name = extraction_state)]
 pub struct ExtractionStateForm<'a> {
@@ -315,6 +325,17 @@ impl From<models::FinancialType> for FinancialType {
     }
 }
 
+impl From<FinancialType> for models::FinancialType {
+    fn from(value: FinancialType) -> Self {
+        match value {
+            FinancialType::Swap => Self::Swap,
+            FinancialType::Psm => Self::Psm,
+            FinancialType::Debt => Self::Debt,
+            FinancialType::Leverage => Self::Leverage,
+        }
+    }
+}
+
 #[derive(Debug, DbEnum, Clone, PartialEq)]
 #[ExistingTypePath = "crate::postgres::schema::sql_types::ImplementationType"]
 pub enum ImplementationType {
@@ -331,6 +352,15 @@ impl From<models::ImplementationType> for ImplementationType {
     }
 }
 
+impl From<ImplementationType> for models::ImplementationType {
+    fn from(value: ImplementationType) -> Self {
+        match value {
+            ImplementationType::Vm => Self::Vm,
+            ImplementationType::Custom => Self::Custom,
+        }
+    }
+}
+
 #[derive(Identifiable, Queryable, Selectable)]
 #[diesel(table_name = protocol_type)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -344,6 +374,67 @@ pub struct ProtocolType {
     pub modified_ts: NaiveDateTime,
 }
 
+impl ProtocolType {
+    /// Converts the stored protocol type into its model.
+    ///
+    /// If `validate_schema` is set, the attribute schema is checked to be well-formed early
+    /// instead of failing later once components are validated against it. See
+    /// [`validate_attribute_schema`] for the supported schema subset.
+    pub fn into_model(self, validate_schema: bool) -> Result<models::ProtocolType, StorageError> {
+        if validate_schema {
+            if let Some(schema) = self.attribute_schema.as_ref() {
+                validate_attribute_schema(schema).map_err(|reason| {
+                    StorageError::DecodeError(format!(
+                        "ProtocolType {} has an invalid attribute schema: {}",
+                        self.name, reason
+                    ))
+                })?;
+            }
+        }
+        Ok(models::ProtocolType::new(
+            self.name,
+            self.financial_type.into(),
+            self.attribute_schema,
+            self.implementation.into(),
+        ))
+    }
+}
+
+/// Checks that an attribute schema is a well-formed instance of the JSON schema subset used to
+/// validate static attributes.
+///
+/// The schema must be an object. If present, `required` must be a list of attribute names and
+/// `properties` must map attribute names to objects whose `type`, if given, is a string.
+fn validate_attribute_schema(schema: &serde_json::Value) -> Result<(), String> {
+    let schema = schema
+        .as_object()
+        .ok_or("schema is not an object")?;
+    if let Some(required) = schema.get("required") {
+        let is_valid = required.as_array().is_some_and(|keys| {
+            keys.iter()
+                .all(serde_json::Value::is_string)
+        });
+        if !is_valid {
+            return Err("required is not a list of strings".to_string());
+        }
+    }
+    if let Some(properties) = schema.get("properties") {
+        let properties = properties
+            .as_object()
+            .ok_or("properties is not an object")?;
+        for (key, property) in properties.iter() {
+            let is_valid = property.as_object().is_some_and(|p| {
+                p.get("type")
+                    .is_none_or(serde_json::Value::is_string)
+            });
+            if !is_valid {
+                return Err(format!("property {} is not an object with a string type", key));
+            }
+        }
+    }
+    Ok(())
+}
+
 #[derive(Identifiable, Queryable, Selectable, Debug)]
 #[diesel(table_name = component_balance)]
 #[diesel(belongs_to(ProtocolComponent))]
@@ -505,13 +596,32 @@ pub struct NewProtocolType {
     pub implementation: ImplementationType,
 }
 impl ProtocolType {
-    pub async fn id_by_name(name: &String, conn: &mut AsyncPgConnection) -> QueryResult<i64> {
+    pub async fn id_and_schema_by_name(
+        name: &String,
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<(i64, Option<serde_json::Value>)> {
         protocol_type::table
             .filter(protocol_type::name.eq(name))
-            .select(protocol_type::id)
-            .first::<i64>(conn)
+            .select((protocol_type::id, protocol_type::attribute_schema))
+            .first::<(i64, Option<serde_json::Value>)>(conn)
             .await
     }
+
+    /// Fetches ids and attribute schemas of the protocol types in `names`, keyed by name.
+    /// Unknown names are missing from the result.
+    pub async fn ids_and_schemas_by_name(
+        names: &[&str],
+        conn: &mut AsyncPgConnection,
+    ) -> QueryResult<HashMap<String, (i64, Option<serde_json::Value>)>> {
+        Ok(protocol_type::table
+            .filter(protocol_type::name.eq_any(names))
+            .select((protocol_type::name, protocol_type::id, protocol_type::attribute_schema))
+            .get_results::<(String, i64, Option<serde_json::Value>)>(conn)
+            .await?
+            .into_iter()
+            .map(|(name, id, schema)| (name, (id, schema)))
+            .collect())
+    }
 }
 #[derive(Identifiable, Queryable, Associations, Selectable, Clone, Debug, PartialEq)]
 #[diesel(belongs_to(Chain))]
@@ -551,6 +661,12 @@ pub struct NewProtocolComponent {
 }
 
 impl NewProtocolComponent {
+    /// Creates a new insertable protocol component.
+    ///
+    /// If `validate_against` is provided, the static attributes are checked against this
+    /// protocol type's attribute schema before being serialized. See
+    /// [`validate_static_attributes`] for the checks performed.
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         external_id: &str,
         chain_id: i64,
@@ -559,10 +675,14 @@ impl NewProtocolComponent {
         creation_tx: i64,
         created_at: NaiveDateTime,
         attributes: &HashMap<String, Bytes>,
-    ) -> Self {
+        validate_against: Option<&serde_json::Value>,
+    ) -> Result<Self, StorageError> {
+        if let Some(schema) = validate_against {
+            validate_static_attributes(external_id, attributes, schema)?;
+        }
         let attributes =
             (!attributes.is_empty()).then(|| serde_json::to_value(attributes).unwrap());
-        Self {
+        Ok(Self {
             external_id: external_id.to_string(),
             chain_id,
             protocol_type_id,
@@ -570,8 +690,54 @@ impl NewProtocolComponent {
             creation_tx,
             created_at,
             attributes,
+        })
+    }
+}
+
+/// Validates static attributes against a protocol type's attribute schema.
+///
+/// The schema follows the JSON schema object layout. All keys listed under `required` must be
+/// present. Since attribute values are stored as hex encoded strings, any attribute declared
+/// under `properties` with a `type` other than `"string"` is rejected.
+fn validate_static_attributes(
+    external_id: &str,
+    attributes: &HashMap<String, Bytes>,
+    schema: &serde_json::Value,
+) -> Result<(), StorageError> {
+    let missing: Vec<&str> = schema
+        .get("required")
+        .and_then(serde_json::Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(serde_json::Value::as_str)
+        .filter(|key| !attributes.contains_key(*key))
+        .collect();
+    if !missing.is_empty() {
+        return Err(StorageError::DecodeError(format!(
+            "ProtocolComponent {} is missing required static attributes: {}",
+            external_id,
+            missing.join(", ")
+        )));
+    }
+
+    if let Some(properties) = schema
+        .get("properties")
+        .and_then(serde_json::Value::as_object)
+    {
+        for key in attributes.keys() {
+            let declared_type = properties
+                .get(key)
+                .and_then(|p| p.get("type"))
+                .and_then(serde_json::Value::as_str);
+            if let Some(t) = declared_type.filter(|t| *t != "string") {
+                return Err(StorageError::DecodeError(format!(
+                    "ProtocolComponent {} static attribute {} has type string, expected {}",
+                    external_id, key, t
+                )));
+            }
         }
     }
+    Ok(())
 }
 
 impl ProtocolComponent {
@@ -1016,6 +1182,21 @@ impl ProtocolState {
     }
 }
 
+/// Groups protocol state rows by their `protocol_component_id`.
+///
+/// Rows may be interleaved across components; within each group the original row order is
+/// preserved.
+pub fn group_protocol_states(rows: Vec<ProtocolState>) -> HashMap<i64, Vec<ProtocolState>> {
+    let mut groups: HashMap<i64, Vec<ProtocolState>> = HashMap::new();
+    for row in rows {
+        groups
+            .entry(row.protocol_component_id)
+            .or_default()
+            .push(row);
+    }
+    groups
+}
+
 #[derive(Insertable, Clone, Debug, PartialEq)]
 #[diesel(table_name = protocol_state)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
@@ -1365,7 +1546,6 @@ impl VersionedRow for NewAccountBalance {
 #[diesel(check_for_backend(diesel::pg::Pg))]
 pub struct ContractCode {
     pub id: i64,
-    pub code: Code,
     pub hash: CodeHash,
     pub account_id: i64,
     pub modify_tx: i64,
@@ -1428,11 +1608,19 @@ impl StoredVersionedRow for ContractCode {
     }
 }
 
+/// A distinct contract code, shared by all contract_code entries with the same hash.
+#[derive(Insertable, Debug)]
+#[diesel(table_name = code)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct NewCode<'a> {
+    pub hash: CodeHash,
+    pub bytecode: &'a Code,
+}
+
 #[derive(Insertable, Debug)]
 #[diesel(table_name = contract_code)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-pub struct NewContractCode<'a> {
-    pub code: &'a Code,
+pub struct NewContractCode {
     pub hash: CodeHash,
     pub account_id: i64,
     pub modify_tx: i64,
@@ -1440,7 +1628,7 @@ pub struct NewContractCode<'a> {
     pub valid_to: Option<NaiveDateTime>,
 }
 
-impl<'a> VersionedRow for NewContractCode<'a> {
+impl VersionedRow for NewContractCode {
     type SortKey = (i64, NaiveDateTime, i64);
     type EntityId = i64;
     type Version = NaiveDateTime;
@@ -1472,10 +1660,17 @@ pub struct NewContract {
     pub deleted_at: Option<NaiveDateTime>,
     pub balance: Balance,
     pub code: Code,
-    pub code_hash: CodeHash,
 }
 
 impl NewContract {
+    /// The hash `code` is stored under.
+    ///
+    /// Always computed from the bytecode, never taken from the caller, since code rows are
+    /// deduplicated by hash: a wrong hash would silently attach the contract to someone else's
+    /// code.
+    pub fn code_hash(&self) -> CodeHash {
+        keccak256(&self.code).into()
+    }
     pub fn new_account(&self) -> NewAccount {
         NewAccount {
             title: &self.title,
@@ -1500,15 +1695,17 @@ impl NewContract {
             valid_to: None,
         }
     }
-    pub fn new_code(
+    pub fn new_code(&self) -> NewCode {
+        NewCode { hash: self.code_hash(), bytecode: &self.code }
+    }
+    pub fn new_contract_code(
         &self,
         account_id: i64,
         modify_tx: i64,
         modify_ts: NaiveDateTime,
     ) -> NewContractCode {
         NewContractCode {
-            code: &self.code,
-            hash: self.code_hash.clone(),
+            hash: self.code_hash(),
             account_id,
             modify_tx,
             valid_from: modify_ts,
@@ -1527,6 +1724,7 @@ pub struct ContractStorage {
     pub value: Option<Bytes>,
     pub previous_value: Option<Bytes>,
     pub account_id: i64,
+    pub chain_id: i64,
     pub modify_tx: i64,
     pub ordinal: i64,
     pub valid_from: NaiveDateTime,
@@ -1543,6 +1741,7 @@ pub struct NewSlot {
     pub value: Option<Bytes>,
     pub previous_value: Option<Bytes>,
     pub account_id: i64,
+    pub chain_id: i64,
     pub modify_tx: i64,
     pub ordinal: i64,
     pub valid_from: NaiveDateTime,
@@ -1611,6 +1810,7 @@ impl From<ContractStorage> for NewSlot {
             value: value.value,
             previous_value: value.previous_value,
             account_id: value.account_id,
+            chain_id: value.chain_id,
             modify_tx: value.modify_tx,
             ordinal: value.ordinal,
             valid_from: value.valid_from,
@@ -1619,32 +1819,33 @@ impl From<ContractStorage> for NewSlot {
     }
 }
 
-#[derive(Insertable, Debug)]
-#[diesel(table_name = contract_storage_default)]
+/// The current value of a live slot, see `contract_storage_current`.
+#[derive(Insertable, Debug, Clone, PartialEq)]
+#[diesel(table_name = contract_storage_current)]
 #[diesel(check_for_backend(diesel::pg::Pg))]
-pub struct NewSlotLatest {
-    pub slot: Bytes,
-    pub value: Option<Bytes>,
-    pub previous_value: Option<Bytes>,
+pub struct NewCurrentSlot {
     pub account_id: i64,
+    pub chain_id: i64,
+    pub slot: Bytes,
+    pub value: Bytes,
     pub modify_tx: i64,
     pub ordinal: i64,
     pub valid_from: NaiveDateTime,
-    pub valid_to: NaiveDateTime,
 }
 
-impl From<NewSlot> for NewSlotLatest {
-    fn from(value: NewSlot) -> Self {
-        Self {
-            slot: value.slot,
-            value: value.value,
-            previous_value: value.previous_value,
-            account_id: value.account_id,
-            modify_tx: value.modify_tx,
-            ordinal: value.ordinal,
-            valid_from: value.valid_from,
-            valid_to: MAX_TS,
-        }
+impl NewCurrentSlot {
+    /// Creates the current value of a slot from its latest version, `None` if the slot was
+    /// deleted.
+    pub fn from_latest(latest: &NewSlot) -> Option<Self> {
+        latest.value.as_ref().map(|value| Self {
+            account_id: latest.account_id,
+            chain_id: latest.chain_id,
+            slot: latest.slot.clone(),
+            value: value.clone(),
+            modify_tx: latest.modify_tx,
+            ordinal: latest.ordinal,
+            valid_from: latest.valid_from,
+        })
     }
 }
 
@@ -6,27 +6,190 @@ use diesel::{
     prelude::*,
     upsert::{excluded, on_constraint},
 };
-use diesel_async::{AsyncPgConnection, RunQueryDsl};
+use diesel_async::{
+    scoped_futures::ScopedFutureExt, AsyncConnection, AsyncPgConnection, RunQueryDsl,
+};
 use itertools::Itertools;
-use tracing::{error, instrument, trace, warn, Level};
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+use tracing::{debug, error, instrument, trace, warn, Level};
 
 use tycho_core::{
     models::{
         self, Address, Balance, Chain, ChangeType, ComponentId, FinancialType, ImplementationType,
         PaginationParams, StoreVal, TxHash,
     },
-    storage::{BlockOrTimestamp, StorageError, Version, WithTotal},
+    storage::{BlockIdentifier, BlockOrTimestamp, StorageError, Version, VersionKind, WithTotal},
     Bytes,
 };
 
 use super::{
-    maybe_lookup_block_ts, maybe_lookup_version_ts,
+    maybe_lookup_version_ts,
     orm::{self, Account, ComponentTVL, NewAccount},
     schema, storage_error_from_diesel, PostgresError, PostgresGateway, WithOrdinal, WithTxHash,
     MAX_TS, MAX_VERSION_TS,
 };
 use crate::postgres::versioning::{apply_partitioned_versioning, VersioningEntry};
 
+/// Number of tokens fetched per page by [`PostgresGateway::export_tokens_csv`].
+const EXPORT_TOKENS_PAGE_SIZE: i64 = 1000;
+
+/// Renders a token quality as a lowercase label, following the tiers documented on
+/// [`models::token::CurrencyToken::quality`]. Qualities outside of these tiers are rendered as
+/// their number.
+fn token_quality_label(quality: u32) -> String {
+    match quality {
+        100 => "normal".to_string(),
+        75 => "rebase".to_string(),
+        50 => "fee".to_string(),
+        10 => "analysis_failed".to_string(),
+        5..=9 => "reanalysis_failed".to_string(),
+        0 => "no_decimals".to_string(),
+        other => other.to_string(),
+    }
+}
+
+/// Quotes a CSV field if it contains a separator, quote or line break.
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+fn currency_token_from_orm(
+    orm_token: orm::Token,
+    address: &Address,
+    chain: Chain,
+) -> models::token::CurrencyToken {
+    let gas_usage: Vec<_> = orm_token
+        .gas
+        .iter()
+        .map(|u| u.map(|g| g as u64))
+        .collect();
+    models::token::CurrencyToken::new(
+        address,
+        orm_token.symbol.as_str(),
+        orm_token.decimals as u32,
+        orm_token.tax as u64,
+        gas_usage.as_slice(),
+        chain,
+        orm_token.quality as u32,
+    )
+}
+
+/// Upper bound for the size of a single protocol state attribute value.
+const MAX_ATTR_BYTES: usize = 1024 * 1024;
+
+/// Ensures all updated attribute values of a state delta are within [`MAX_ATTR_BYTES`].
+///
+/// Attribute values are stored as raw bytes, so no hex or prefix normalisation is necessary.
+fn validate_attribute_values(
+    delta: &models::protocol::ProtocolComponentStateDelta,
+) -> Result<(), StorageError> {
+    for (attribute, value) in delta.updated_attributes.iter() {
+        if value.len() > MAX_ATTR_BYTES {
+            return Err(StorageError::DecodeError(format!(
+                "Attribute {} of component {} exceeds the size limit: {} > {} bytes",
+                attribute,
+                delta.component_id,
+                value.len(),
+                MAX_ATTR_BYTES
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Ensures a float balance is finite.
+///
+/// NaN or infinite values, e.g. from adapters dividing by zero decimals, would corrupt float
+/// based queries such as filtering components by balance.
+fn validate_balance_float(
+    value: f64,
+    component_id: &str,
+    token: &Address,
+) -> Result<(), StorageError> {
+    if !value.is_finite() {
+        return Err(StorageError::DecodeError(format!(
+            "Balance of token {} in component {} is not finite: {}",
+            token, component_id, value
+        )));
+    }
+    Ok(())
+}
+
+/// Groups addresses by protocol component id.
+///
+/// Duplicate addresses for the same component are dropped, keeping the first-seen order.
+fn map_addresses_to_protocol_component(
+    protocol_component_to_address: Vec<(i64, Address)>,
+) -> HashMap<i64, Vec<Address>> {
+    protocol_component_to_address
+        .into_iter()
+        .fold(HashMap::new(), |mut acc, (key, address)| {
+            let addresses: &mut Vec<Address> = acc.entry(key).or_default();
+            if !addresses.contains(&address) {
+                addresses.push(address);
+            }
+            acc
+        })
+}
+
+/// Reverts the protocol states of the given components to the state at `ts`.
+///
+/// State rows created after `ts` are deleted and the rows that were valid at `ts` become valid
+/// again.
+pub(super) async fn revert_protocol_states(
+    component_ids: &[i64],
+    ts: NaiveDateTime,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), PostgresError> {
+    diesel::delete(
+        schema::protocol_state::table
+            .filter(schema::protocol_state::protocol_component_id.eq_any(component_ids))
+            .filter(schema::protocol_state::valid_from.gt(ts)),
+    )
+    .execute(conn)
+    .await?;
+    diesel::update(
+        schema::protocol_state::table
+            .filter(schema::protocol_state::protocol_component_id.eq_any(component_ids))
+            .filter(schema::protocol_state::valid_to.gt(ts)),
+    )
+    .set(schema::protocol_state::valid_to.eq(MAX_TS))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
+/// Reverts the token balances of the given components to the state at `ts`.
+///
+/// Balance rows created after `ts` are deleted and the rows that were valid at `ts` become valid
+/// again.
+pub(super) async fn revert_component_balances(
+    component_ids: &[i64],
+    ts: NaiveDateTime,
+    conn: &mut AsyncPgConnection,
+) -> Result<(), PostgresError> {
+    diesel::delete(
+        schema::component_balance::table
+            .filter(schema::component_balance::protocol_component_id.eq_any(component_ids))
+            .filter(schema::component_balance::valid_from.gt(ts)),
+    )
+    .execute(conn)
+    .await?;
+    diesel::update(
+        schema::component_balance::table
+            .filter(schema::component_balance::protocol_component_id.eq_any(component_ids))
+            .filter(schema::component_balance::valid_to.gt(ts)),
+    )
+    .set(schema::component_balance::valid_to.eq(MAX_TS))
+    .execute(conn)
+    .await?;
+    Ok(())
+}
+
 // Private methods
 impl PostgresGateway {
     /// # Decoding ProtocolStates from database results.
@@ -34,12 +197,10 @@ impl PostgresGateway {
     /// This function takes as input the database result for querying protocol states and their
     /// linked component id and transaction hash.
     ///
-    /// ## Assumptions:
-    /// - It is assumed that the rows in the result are ordered by component ID
-    ///
     /// The function processes these individual `ProtocolState` entities and combines all entities
     /// with matching component IDs into a single `ProtocolState`. The final output is a list
-    /// where each element is a `ProtocolState` representing a unique component.
+    /// where each element is a `ProtocolState` representing a unique component, ordered by the
+    /// components' database id.
     ///
     /// ## Returns:
     /// - A Result containing a vector of `ProtocolState`, otherwise, it will return a StorageError.
@@ -53,34 +214,33 @@ impl PostgresGateway {
         let data_vec = states_result
             .map_err(|err| storage_error_from_diesel(err, "ProtocolStates", context, None))?;
 
-        // Decode final state deltas. We can assume result is sorted by component_id.
-        // Therefore we can use slices to iterate over the data in groups of
-        // component_id.
-        let mut protocol_states = Vec::new();
-
-        let mut index = 0;
-        while index < data_vec.len() {
-            let component_start = index;
-            let current_component_id = &data_vec[index].1;
-
-            // Iterate until the component_id changes
-            while index < data_vec.len() && &data_vec[index].1 == current_component_id {
-                index += 1;
-            }
+        let mut external_ids: HashMap<i64, ComponentId> = HashMap::new();
+        let rows = data_vec
+            .into_iter()
+            .map(|(state, component_id)| {
+                external_ids.insert(state.protocol_component_id, component_id);
+                state
+            })
+            .collect();
 
-            let states_slice = &data_vec[component_start..index];
+        let mut protocol_states = Vec::new();
+        for (db_id, states) in orm::group_protocol_states(rows)
+            .into_iter()
+            .sorted_by_key(|(db_id, _)| *db_id)
+        {
+            let component_id = &external_ids[&db_id];
             let protocol_balances: HashMap<Address, Balance> = balances
-                .remove(current_component_id)
+                .remove(component_id)
                 .unwrap_or_default()
                 .into_iter()
                 .map(|(key, balance)| (key, balance.balance))
                 .collect();
 
             let protocol_state = models::protocol::ProtocolComponentState::new(
-                current_component_id,
-                states_slice
-                    .iter()
-                    .map(|x| (x.0.attribute_name.clone(), x.0.attribute_value.clone()))
+                component_id,
+                states
+                    .into_iter()
+                    .map(|state| (state.attribute_name, state.attribute_value))
                     .collect(),
                 protocol_balances,
             );
@@ -132,6 +292,13 @@ impl PostgresGateway {
         }
     }
 
+    /// Retrieves protocol components filtered by chain, system, external ids, tvl and/or
+    /// creation time.
+    ///
+    /// If `created_after` is set, only components created strictly after it are returned, ordered
+    /// ascending by their creation time. Soft-deleted components are excluded unless
+    /// `include_deleted` is set.
+    #[allow(clippy::too_many_arguments)]
     #[instrument(level = Level::DEBUG, skip(self, ids, conn))]
     pub async fn get_protocol_components(
         &self,
@@ -139,6 +306,8 @@ impl PostgresGateway {
         system: Option<String>,
         ids: Option<&[&str]>,
         min_tvl: Option<f64>,
+        created_after: Option<NaiveDateTime>,
+        include_deleted: bool,
         pagination_params: Option<&PaginationParams>,
         conn: &mut AsyncPgConnection,
     ) -> Result<WithTotal<Vec<models::protocol::ProtocolComponent>>, StorageError> {
@@ -151,13 +320,14 @@ impl PostgresGateway {
 
         let mut query = protocol_component
             .inner_join(transaction.on(creation_tx.eq(schema::transaction::id)))
+            .inner_join(schema::protocol_type::table)
             .left_join(schema::component_tvl::table)
-            .select((orm::ProtocolComponent::as_select(), hash))
+            .select((orm::ProtocolComponent::as_select(), hash, schema::protocol_type::name))
             .into_boxed();
 
         match (system, ids) {
             (Some(ps), None) => {
-                let protocol_system = self.get_protocol_system_id(&ps);
+                let protocol_system = self.get_cached_protocol_system_id(&ps);
                 query = query.filter(
                     chain_id
                         .eq(chain_id_value)
@@ -182,7 +352,7 @@ impl PostgresGateway {
                 );
             }
             (Some(ps), Some(external_ids)) => {
-                let protocol_system = self.get_protocol_system_id(&ps);
+                let protocol_system = self.get_cached_protocol_system_id(&ps);
                 query = query.filter(
                     chain_id.eq(chain_id_value).and(
                         external_id
@@ -209,6 +379,27 @@ impl PostgresGateway {
             count_query = count_query.filter(schema::component_tvl::tvl.gt(thr));
         }
 
+        if let Some(ts) = created_after {
+            query = query
+                .filter(created_at.gt(ts))
+                .order_by(created_at.asc());
+            count_query = count_query.filter(created_at.gt(ts));
+        }
+
+        if !include_deleted {
+            // reverted deletions are reset to MAX_TS instead of NULL
+            query = query.filter(
+                deleted_at
+                    .is_null()
+                    .or(deleted_at.ge(MAX_TS)),
+            );
+            count_query = count_query.filter(
+                deleted_at
+                    .is_null()
+                    .or(deleted_at.ge(MAX_TS)),
+            );
+        }
+
         let count = count_query
             .count()
             .get_result::<i64>(conn)
@@ -218,18 +409,15 @@ impl PostgresGateway {
         // Apply optional pagination when loading protocol components to ensure consistency
         if let Some(pagination) = pagination_params {
             query = query
-                .order_by(schema::protocol_component::id)
+                .then_order_by(schema::protocol_component::id)
                 .limit(pagination.page_size)
                 .offset(pagination.offset());
         }
 
         let orm_protocol_components = query
-            .load::<(orm::ProtocolComponent, TxHash)>(conn)
+            .load::<(orm::ProtocolComponent, TxHash, String)>(conn)
             .await
-            .map_err(PostgresError::from)?
-            .into_iter()
-            .map(|(pc, txh)| (pc, Some(txh)))
-            .collect();
+            .map_err(PostgresError::from)?;
 
         let res = self
             .build_protocol_components(orm_protocol_components, chain, conn)
@@ -238,16 +426,337 @@ impl PostgresGateway {
         Ok(WithTotal { entity: res, total: Some(count) })
     }
 
+    /// Retrieves a single protocol component, resolving its creation transaction hash.
+    ///
+    /// Soft-deleted components are treated like missing ones. Returns `NotFound` if the component
+    /// is not stored or deleted, or if its creation transaction is missing.
+    pub async fn get_protocol_component(
+        &self,
+        chain: &Chain,
+        external_id: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<models::protocol::ProtocolComponent, StorageError> {
+        use super::schema::{protocol_component, protocol_type, transaction};
+        let (pc, tx_hash, protocol_type_name) = protocol_component::table
+            .left_join(transaction::table.on(protocol_component::creation_tx.eq(transaction::id)))
+            .inner_join(protocol_type::table)
+            .filter(protocol_component::chain_id.eq(self.get_chain_id(chain)))
+            .filter(protocol_component::external_id.eq(external_id))
+            .filter(
+                protocol_component::deleted_at
+                    .is_null()
+                    .or(protocol_component::deleted_at.ge(MAX_TS)),
+            )
+            .select((
+                orm::ProtocolComponent::as_select(),
+                transaction::hash.nullable(),
+                protocol_type::name,
+            ))
+            .first::<(orm::ProtocolComponent, Option<TxHash>, String)>(conn)
+            .await
+            .map_err(|err| {
+                storage_error_from_diesel(err, "ProtocolComponent", external_id, None)
+            })?;
+        let tx_hash = tx_hash.ok_or_else(|| {
+            StorageError::NotFound(
+                "Transaction".to_string(),
+                format!("creation tx of component {external_id}"),
+            )
+        })?;
+        let mut components = self
+            .build_protocol_components(vec![(pc, tx_hash, protocol_type_name)], chain, conn)
+            .await?;
+        Ok(components.remove(0))
+    }
+
+    /// Retrieves the protocol components created in a block, ordered by external id.
+    ///
+    /// Components are matched through the block of their creation transaction. Returns an
+    /// empty vec if no component was created in the block and `NotFound` if the block is not
+    /// stored.
+    pub async fn get_components_created_in_block(
+        &self,
+        block: &BlockIdentifier,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<models::protocol::ProtocolComponent>, StorageError> {
+        use super::schema::{protocol_component, protocol_type, transaction};
+        let block = orm::Block::by_id(block, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &block.to_string(), None))?;
+        let orm_protocol_components = protocol_component::table
+            .inner_join(transaction::table.on(protocol_component::creation_tx.eq(transaction::id)))
+            .inner_join(protocol_type::table)
+            .filter(transaction::block_id.eq(block.id))
+            .filter(protocol_component::chain_id.eq(block.chain_id))
+            .order_by(protocol_component::external_id)
+            .select((orm::ProtocolComponent::as_select(), transaction::hash, protocol_type::name))
+            .load::<(orm::ProtocolComponent, TxHash, String)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+        self.build_protocol_components(
+            orm_protocol_components,
+            &self.get_chain(&block.chain_id),
+            conn,
+        )
+        .await
+    }
+
+    /// Retrieves the protocol components holding all of the given tokens.
+    ///
+    /// Components are optionally restricted to a single protocol system. A component is only
+    /// returned if it holds every token in `tokens` (AND semantics). An empty `tokens` slice
+    /// applies no token filter.
+    #[instrument(level = Level::DEBUG, skip(self, tokens, conn))]
+    pub async fn get_protocol_components_by_tokens(
+        &self,
+        chain: &Chain,
+        system: Option<&str>,
+        tokens: &[Address],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<models::protocol::ProtocolComponent>, StorageError> {
+        use super::schema::{protocol_component::dsl::*, transaction::dsl::*};
+        let chain_id_value = self.get_chain_id(chain);
+
+        let mut query = protocol_component
+            .inner_join(transaction.on(creation_tx.eq(schema::transaction::id)))
+            .inner_join(schema::protocol_type::table)
+            .filter(chain_id.eq(chain_id_value))
+            .select((orm::ProtocolComponent::as_select(), hash, schema::protocol_type::name))
+            .into_boxed();
+
+        if let Some(ps) = system {
+            let system_id = self
+                .get_protocol_system_id(ps, conn)
+                .await?;
+            query = query.filter(protocol_system_id.eq(system_id));
+        }
+
+        if !tokens.is_empty() {
+            #[allow(clippy::mutable_key_type)]
+            let requested: HashSet<&Address> = tokens.iter().collect();
+            let held_tokens: Vec<(i64, Address)> = schema::protocol_component_holds_token::table
+                .inner_join(schema::token::table)
+                .inner_join(
+                    schema::account::table.on(schema::token::account_id.eq(schema::account::id)),
+                )
+                .filter(schema::account::address.eq_any(&requested))
+                .select((
+                    schema::protocol_component_holds_token::protocol_component_id,
+                    schema::account::address,
+                ))
+                .load(conn)
+                .await
+                .map_err(PostgresError::from)?;
+
+            let matching_ids: Vec<i64> = held_tokens
+                .into_iter()
+                .into_group_map()
+                .into_iter()
+                .filter(|(_, held)| {
+                    held.iter()
+                        .collect::<HashSet<_>>()
+                        .len() ==
+                        requested.len()
+                })
+                .map(|(pc_id, _)| pc_id)
+                .collect();
+            query = query.filter(schema::protocol_component::id.eq_any(matching_ids));
+        }
+
+        let orm_protocol_components = query
+            .order_by(schema::protocol_component::id)
+            .load::<(orm::ProtocolComponent, TxHash, String)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+
+        self.build_protocol_components(orm_protocol_components, chain, conn)
+            .await
+    }
+
+    /// Retrieves protocol components by their external ids.
+    ///
+    /// The returned components follow the order of `external_ids`. Ids that could not be found
+    /// on the given chain are omitted, so callers can detect them by comparing lengths.
+    #[instrument(level = Level::DEBUG, skip(self, external_ids, conn))]
+    pub async fn get_protocol_components_by_ids(
+        &self,
+        chain: &Chain,
+        external_ids: &[String],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<models::protocol::ProtocolComponent>, StorageError> {
+        use super::schema::{protocol_component::dsl::*, transaction::dsl::*};
+        let chain_id_value = self.get_chain_id(chain);
+
+        let orm_protocol_components = protocol_component
+            .inner_join(transaction.on(creation_tx.eq(schema::transaction::id)))
+            .inner_join(schema::protocol_type::table)
+            .filter(chain_id.eq(chain_id_value))
+            .filter(external_id.eq_any(external_ids))
+            .select((orm::ProtocolComponent::as_select(), hash, schema::protocol_type::name))
+            .load::<(orm::ProtocolComponent, TxHash, String)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+
+        let mut components_by_id: HashMap<ComponentId, models::protocol::ProtocolComponent> = self
+            .build_protocol_components(orm_protocol_components, chain, conn)
+            .await?
+            .into_iter()
+            .map(|pc| (pc.id.clone(), pc))
+            .collect();
+
+        Ok(external_ids
+            .iter()
+            .filter_map(|ext_id| components_by_id.remove(ext_id))
+            .collect())
+    }
+
+    /// Retrieves all protocol components with a static attribute `key` set to `value`.
+    ///
+    /// Uses a JSONB containment filter on the stored attributes, so the value is encoded the same
+    /// way static attributes are serialized on insert. Components are ordered by their db id.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn find_components_by_attribute(
+        &self,
+        chain: &Chain,
+        key: &str,
+        value: &Bytes,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<models::protocol::ProtocolComponent>, StorageError> {
+        use super::schema::{protocol_component::dsl::*, transaction::dsl::*};
+        let chain_id_value = self.get_chain_id(chain);
+        let needle = serde_json::to_value(HashMap::from([(key, value)])).map_err(|err| {
+            StorageError::Unexpected(format!("Failed to encode attribute {}: {}", key, err))
+        })?;
+
+        let orm_protocol_components = protocol_component
+            .inner_join(transaction.on(creation_tx.eq(schema::transaction::id)))
+            .inner_join(schema::protocol_type::table)
+            .filter(chain_id.eq(chain_id_value))
+            .filter(attributes.contains(needle))
+            .order_by(schema::protocol_component::id)
+            .select((orm::ProtocolComponent::as_select(), hash, schema::protocol_type::name))
+            .load::<(orm::ProtocolComponent, TxHash, String)>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+
+        self.build_protocol_components(orm_protocol_components, chain, conn)
+            .await
+    }
+
+    /// Soft-deletes a protocol component.
+    ///
+    /// Marks the component as deleted by `deletion_tx` at `ts`. Soft-deleted components are
+    /// hidden from [`PostgresGateway::get_protocol_components`] unless explicitly requested.
+    /// Returns `NotFound` if the component does not exist on the given chain.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn delete_protocol_component(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        deletion_tx: i64,
+        ts: NaiveDateTime,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        self.set_protocol_component_deletion(external_id, chain, Some(deletion_tx), Some(ts), conn)
+            .await
+    }
+
+    /// Restores a soft-deleted protocol component.
+    ///
+    /// Returns `NotFound` if the component does not exist on the given chain.
+    #[instrument(level = Level::DEBUG, skip(self, conn))]
+    pub async fn restore_protocol_component(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        self.set_protocol_component_deletion(external_id, chain, None, None, conn)
+            .await
+    }
+
+    async fn set_protocol_component_deletion(
+        &self,
+        ext_id: &str,
+        chain: &Chain,
+        tx: Option<i64>,
+        ts: Option<NaiveDateTime>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        use super::schema::protocol_component::dsl::*;
+        let updated = diesel::update(
+            protocol_component
+                .filter(external_id.eq(ext_id))
+                .filter(chain_id.eq(self.get_chain_id(chain))),
+        )
+        .set((deletion_tx.eq(tx), deleted_at.eq(ts)))
+        .execute(conn)
+        .await
+        .map_err(PostgresError::from)?;
+        if updated == 0 {
+            Err(StorageError::NotFound("ProtocolComponent".to_string(), ext_id.to_string()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Patches the static attributes of a protocol component.
+    ///
+    /// Attributes in `patch` are merged into the stored attributes within a single JSONB `||`
+    /// update, so attributes not contained in `patch` are left untouched even with concurrent
+    /// writers. Returns `NotFound` if the component does not exist on the given chain.
+    #[instrument(level = Level::DEBUG, skip(self, patch, conn))]
+    pub async fn update_component_attributes(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        patch: &HashMap<String, Bytes>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        use diesel::sql_types::{BigInt, Jsonb, Text};
+        let chain_id = self.get_chain_id(chain);
+        let patch = serde_json::to_value(patch).map_err(|err| {
+            StorageError::Unexpected(format!(
+                "Failed to encode attributes of {}: {}",
+                external_id, err
+            ))
+        })?;
+        let updated = diesel::sql_query(
+            r#"
+            UPDATE protocol_component
+            SET attributes = COALESCE(attributes, '{}'::jsonb) || $1
+            WHERE external_id = $2 AND chain_id = $3
+            "#,
+        )
+        .bind::<Jsonb, _>(patch)
+        .bind::<Text, _>(external_id)
+        .bind::<BigInt, _>(chain_id)
+        .execute(conn)
+        .await
+        .map_err(PostgresError::from)?;
+        if updated == 0 {
+            return Err(StorageError::NotFound(
+                "ProtocolComponent".to_string(),
+                external_id.to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Builds protocol component models from their db rows.
+    ///
+    /// Expects each component together with its creation tx hash and protocol type name, which
+    /// callers resolve by joining within the component query.
     #[instrument(level = Level::DEBUG, skip(self, orm_protocol_components, conn))]
     async fn build_protocol_components(
         &self,
-        orm_protocol_components: Vec<(orm::ProtocolComponent, Option<TxHash>)>,
+        orm_protocol_components: Vec<(orm::ProtocolComponent, TxHash, String)>,
         chain: &Chain,
         conn: &mut AsyncPgConnection,
     ) -> Result<Vec<models::protocol::ProtocolComponent>, StorageError> {
         let protocol_component_ids = orm_protocol_components
             .iter()
-            .map(|(pc, _)| pc.id)
+            .map(|(pc, _, _)| pc.id)
             .collect::<Vec<i64>>();
 
         let protocol_component_tokens: Vec<(i64, Address)> =
@@ -281,32 +790,31 @@ impl PostgresGateway {
                 ))
                 .filter(
                     schema::protocol_component_holds_contract::protocol_component_id
-                        .eq_any(protocol_component_ids),
+                        .eq_any(&protocol_component_ids),
                 )
                 .load::<(i64, Address)>(conn)
                 .await
                 .map_err(PostgresError::from)?;
 
-        let protocol_type_names_by_id: HashMap<i64, String> = schema::protocol_type::table
-            .select((schema::protocol_type::id, schema::protocol_type::name))
-            .load::<(i64, String)>(conn)
+        let linked_components: HashMap<i64, (i64, String)> = schema::protocol_component_link::table
+            .inner_join(
+                schema::protocol_component::table.on(schema::protocol_component::id
+                    .eq(schema::protocol_component_link::linked_component_id)),
+            )
+            .filter(
+                schema::protocol_component_link::protocol_component_id
+                    .eq_any(&protocol_component_ids),
+            )
+            .select((
+                schema::protocol_component_link::protocol_component_id,
+                (schema::protocol_component::chain_id, schema::protocol_component::external_id),
+            ))
+            .load::<(i64, (i64, String))>(conn)
             .await
             .map_err(PostgresError::from)?
             .into_iter()
             .collect();
 
-        fn map_addresses_to_protocol_component(
-            protocol_component_to_address: Vec<(i64, Address)>,
-        ) -> HashMap<i64, Vec<Address>> {
-            protocol_component_to_address
-                .into_iter()
-                .fold(HashMap::new(), |mut acc, (key, address)| {
-                    acc.entry(key)
-                        .or_default()
-                        .push(address);
-                    acc
-                })
-        }
         let protocol_component_tokens =
             map_addresses_to_protocol_component(protocol_component_tokens);
         let protocol_component_contracts =
@@ -314,7 +822,7 @@ impl PostgresGateway {
 
         orm_protocol_components
             .into_iter()
-            .map(|(pc, tx_hash)| {
+            .map(|(pc, tx_hash, protocol_type_name)| {
                 let ps = self.get_protocol_system(&pc.protocol_system_id);
                 let tokens_by_pc: Vec<Address> = protocol_component_tokens
                     .get(&pc.id)
@@ -335,23 +843,25 @@ impl PostgresGateway {
                     Default::default()
                 };
 
-                Ok(models::protocol::ProtocolComponent::new(
+                let mut component = models::protocol::ProtocolComponent::new(
                     &pc.external_id,
                     &ps,
-                    protocol_type_names_by_id
-                        .get(&pc.protocol_type_id)
-                        .ok_or(PostgresError(StorageError::NotFound(
-                            "ProtocolType".into(),
-                            pc.protocol_type_id.to_string(),
-                        )))?,
+                    &protocol_type_name,
                     *chain,
                     tokens_by_pc,
                     contracts_by_pc,
                     static_attributes,
                     ChangeType::Creation,
-                    tx_hash.unwrap_or(Bytes::from(&[0; 32])),
+                    tx_hash,
                     pc.created_at,
-                ))
+                );
+                component.linked_component =
+                    linked_components
+                        .get(&pc.id)
+                        .map(|(linked_chain_id, linked_id)| {
+                            (self.get_chain(linked_chain_id), linked_id.clone())
+                        });
+                Ok(component)
             })
             .collect()
     }
@@ -409,50 +919,335 @@ impl PostgresGateway {
         Ok(res)
     }
 
-    pub async fn add_protocol_components(
+    /// Checks that every token held by `components` is stored on the component's chain.
+    ///
+    /// Missing tokens are stored as placeholders if enabled, see
+    /// [`PostgresGateway::with_auto_create_missing_tokens`]. Otherwise returns `NotFound`
+    /// listing all missing token addresses.
+    async fn ensure_component_tokens(
         &self,
-        new: &[models::protocol::ProtocolComponent],
+        components: &[models::protocol::ProtocolComponent],
         conn: &mut AsyncPgConnection,
     ) -> Result<(), StorageError> {
-        use super::schema::{
-            account::dsl::*, protocol_component::dsl::*, protocol_component_holds_contract::dsl::*,
-            protocol_component_holds_token::dsl::*, token::dsl::*,
-        };
-        let mut values: Vec<orm::NewProtocolComponent> = Vec::with_capacity(new.len());
-        let tx_hashes: Vec<TxHash> = new
+        let requested: BTreeSet<(i64, &Address)> = components
             .iter()
-            .map(|pc| pc.creation_tx.clone())
+            .flat_map(|pc| {
+                let chain_id = self.get_chain_id(&pc.chain);
+                pc.tokens
+                    .iter()
+                    .map(move |address| (chain_id, address))
+            })
             .collect();
-        let tx_hash_id_mapping: HashMap<TxHash, i64> =
-            orm::Transaction::ids_by_hash(&tx_hashes, conn)
-                .await
-                .map_err(PostgresError::from)?;
-        let pt_id = orm::ProtocolType::id_by_name(&new[0].protocol_type_name, conn)
+        if requested.is_empty() {
+            return Ok(());
+        }
+        let stored: HashSet<(i64, Address)> = schema::token::table
+            .inner_join(schema::account::table)
+            .filter(
+                schema::account::address.eq_any(
+                    requested
+                        .iter()
+                        .map(|(_, address)| *address),
+                ),
+            )
+            .select((schema::account::chain_id, schema::account::address))
+            .get_results::<(i64, Address)>(conn)
             .await
-            .map_err(|err| {
-                storage_error_from_diesel(err, "ProtocolType", &new[0].protocol_type_name, None)
-            })?;
-        for pc in new {
-            let txh = tx_hash_id_mapping
-                .get::<TxHash>(&pc.creation_tx.clone())
-                .ok_or(StorageError::DecodeError("TxHash not found".to_string()))?;
-
-            let new_pc = orm::NewProtocolComponent::new(
-                &pc.id,
-                self.get_chain_id(&pc.chain),
-                pt_id,
-                self.get_protocol_system_id(&pc.protocol_system.to_string()),
-                *txh,
-                pc.created_at,
-                &pc.static_attributes,
-            );
-            values.push(new_pc);
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+        let missing: Vec<(i64, &Address)> = requested
+            .into_iter()
+            .filter(|(chain_id, address)| !stored.contains(&(*chain_id, (*address).clone())))
+            .collect();
+        if missing.is_empty() {
+            return Ok(());
         }
 
-        let inserted_protocol_components: Vec<(i64, String, i64, i64)> =
-            diesel::insert_into(protocol_component)
-                .values(&values)
-                .on_conflict((schema::protocol_component::chain_id, external_id))
+        if !self.auto_create_missing_tokens {
+            return Err(StorageError::NotFound(
+                "Token".to_string(),
+                missing
+                    .iter()
+                    .map(|(_, address)| address.to_string())
+                    .join(", "),
+            ));
+        }
+        let placeholders: Vec<models::token::CurrencyToken> = missing
+            .into_iter()
+            .map(|(chain_id, address)| {
+                models::token::CurrencyToken::new(
+                    address,
+                    &address.to_string(),
+                    18,
+                    0,
+                    &[],
+                    self.get_chain(&chain_id),
+                    0,
+                )
+            })
+            .collect();
+        debug!(n_tokens = placeholders.len(), "Storing placeholders for missing tokens");
+        self.add_tokens(&placeholders, conn)
+            .await
+    }
+
+    /// Links a component to its counterpart on another chain, replacing any previous link.
+    ///
+    /// Links are directional: the counterpart only resolves back to this component if it was
+    /// linked the other way as well. Returns `NotFound` if either component is not stored.
+    pub async fn link_component(
+        &self,
+        chain: &Chain,
+        external_id: &str,
+        linked: (&Chain, &str),
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        use super::schema::protocol_component_link::dsl::*;
+        let component_id = self
+            .get_component_db_id(self.get_chain_id(chain), external_id, conn)
+            .await?;
+        let (linked_chain, linked_external_id) = linked;
+        let linked_id = self
+            .get_component_db_id(self.get_chain_id(linked_chain), linked_external_id, conn)
+            .await?;
+        diesel::insert_into(protocol_component_link)
+            .values((protocol_component_id.eq(component_id), linked_component_id.eq(linked_id)))
+            .on_conflict(protocol_component_id)
+            .do_update()
+            .set(linked_component_id.eq(excluded(linked_component_id)))
+            .execute(conn)
+            .await
+            .map_err(PostgresError::from)?;
+        Ok(())
+    }
+
+    /// Resolves the chain and external id of the counterpart a component is linked to.
+    ///
+    /// Returns `None` if the component is not linked and `NotFound` if it is not stored.
+    pub async fn get_linked_component(
+        &self,
+        chain: &Chain,
+        external_id: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Option<(Chain, ComponentId)>, StorageError> {
+        use super::schema::{protocol_component, protocol_component_link};
+        let component_id = self
+            .get_component_db_id(self.get_chain_id(chain), external_id, conn)
+            .await?;
+        let linked = protocol_component_link::table
+            .inner_join(
+                protocol_component::table
+                    .on(protocol_component::id.eq(protocol_component_link::linked_component_id)),
+            )
+            .filter(protocol_component_link::protocol_component_id.eq(component_id))
+            .select((protocol_component::chain_id, protocol_component::external_id))
+            .first::<(i64, String)>(conn)
+            .await
+            .optional()
+            .map_err(PostgresError::from)?;
+        Ok(linked.map(|(linked_chain_id, linked_external_id)| {
+            (self.get_chain(&linked_chain_id), linked_external_id)
+        }))
+    }
+
+    async fn get_component_db_id(
+        &self,
+        chain_id: i64,
+        external_id: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<i64, PostgresError> {
+        schema::protocol_component::table
+            .filter(schema::protocol_component::chain_id.eq(chain_id))
+            .filter(schema::protocol_component::external_id.eq(external_id))
+            .select(schema::protocol_component::id)
+            .first::<i64>(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "ProtocolComponent", external_id, None))
+    }
+
+    /// Retrieves the addresses of the contracts a component is linked to, ordered by address.
+    ///
+    /// Returns `NotFound` if the component is not stored.
+    pub async fn get_component_contracts(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<Address>, StorageError> {
+        use super::schema::{account, contract_code, protocol_component_holds_contract};
+        let component_id = self
+            .get_component_db_id(self.get_chain_id(chain), external_id, conn)
+            .await?;
+        let addresses = protocol_component_holds_contract::table
+            .inner_join(contract_code::table.inner_join(account::table))
+            .filter(protocol_component_holds_contract::protocol_component_id.eq(component_id))
+            .select(account::address)
+            .distinct()
+            .order_by(account::address)
+            .get_results::<Address>(conn)
+            .await
+            .map_err(PostgresError::from)?;
+        Ok(addresses)
+    }
+
+    /// Replaces the set of tokens held by a component.
+    ///
+    /// Only the difference to the stored set is written: associations of tokens that are no
+    /// longer held are deleted and associations of newly held tokens are inserted, both within
+    /// a single transaction. Returns `NotFound` if the component or any newly held token is not
+    /// stored.
+    pub async fn set_component_tokens(
+        &self,
+        external_id: &str,
+        chain: &Chain,
+        tokens: &[Address],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        use super::schema::{account, protocol_component_holds_token, token};
+        let chain_id = self.get_chain_id(chain);
+        conn.transaction(|conn| {
+            async move {
+                let component_id = self
+                    .get_component_db_id(chain_id, external_id, conn)
+                    .await?;
+                let stored: Vec<(Address, i64)> = protocol_component_holds_token::table
+                    .inner_join(token::table.inner_join(account::table))
+                    .filter(protocol_component_holds_token::protocol_component_id.eq(component_id))
+                    .select((account::address, token::id))
+                    .get_results(conn)
+                    .await?;
+                let stored_addresses: Vec<Address> = stored
+                    .iter()
+                    .map(|(address, _)| address.clone())
+                    .collect();
+                let (added, removed) = models::protocol::diff_tokens(tokens, &stored_addresses);
+
+                if !removed.is_empty() {
+                    let removed_ids: Vec<i64> = stored
+                        .iter()
+                        .filter(|(address, _)| removed.contains(address))
+                        .map(|(_, token_id)| *token_id)
+                        .collect();
+                    diesel::delete(
+                        protocol_component_holds_token::table
+                            .filter(
+                                protocol_component_holds_token::protocol_component_id
+                                    .eq(component_id),
+                            )
+                            .filter(protocol_component_holds_token::token_id.eq_any(removed_ids)),
+                    )
+                    .execute(conn)
+                    .await?;
+                }
+                if !added.is_empty() {
+                    let token_ids: HashMap<Address, i64> = token::table
+                        .inner_join(account::table)
+                        .filter(account::chain_id.eq(chain_id))
+                        .filter(account::address.eq_any(&added))
+                        .select((account::address, token::id))
+                        .get_results::<(Address, i64)>(conn)
+                        .await?
+                        .into_iter()
+                        .collect();
+                    let new_rows = added
+                        .iter()
+                        .map(|address| {
+                            let token_id = token_ids.get(address).ok_or_else(|| {
+                                PostgresError(StorageError::NotFound(
+                                    "Token".to_string(),
+                                    address.to_string(),
+                                ))
+                            })?;
+                            Ok(orm::NewProtocolComponentHoldsToken {
+                                protocol_component_id: component_id,
+                                token_id: *token_id,
+                            })
+                        })
+                        .collect::<Result<Vec<_>, PostgresError>>()?;
+                    diesel::insert_into(protocol_component_holds_token::table)
+                        .values(&new_rows)
+                        .execute(conn)
+                        .await?;
+                }
+                Ok::<(), PostgresError>(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+        Ok(())
+    }
+
+    pub async fn add_protocol_components(
+        &self,
+        new: &[models::protocol::ProtocolComponent],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        use super::schema::{
+            account::dsl::*, protocol_component::dsl::*, protocol_component_holds_contract::dsl::*,
+            protocol_component_holds_token::dsl::*, token::dsl::*,
+        };
+        let mut values: Vec<orm::NewProtocolComponent> = Vec::with_capacity(new.len());
+        let tx_hashes: Vec<TxHash> = new
+            .iter()
+            .map(|pc| pc.creation_tx.clone())
+            .collect();
+        let tx_hash_id_mapping: HashMap<TxHash, i64> =
+            orm::Transaction::ids_by_hash(&tx_hashes, conn)
+                .await
+                .map_err(PostgresError::from)?;
+        let type_names: Vec<&str> = new
+            .iter()
+            .map(|pc| pc.protocol_type_name.as_str())
+            .unique()
+            .collect();
+        let protocol_types = orm::ProtocolType::ids_and_schemas_by_name(&type_names, conn)
+            .await
+            .map_err(PostgresError::from)?;
+        let system_names: Vec<&str> = new
+            .iter()
+            .map(|pc| pc.protocol_system.as_str())
+            .collect();
+        let system_ids = if self.auto_register_systems {
+            self.register_protocol_systems(&system_names, conn)
+                .await?
+        } else {
+            self.get_protocol_system_ids(&system_names, conn)
+                .await?
+        };
+        for (pc, ps_id) in new.iter().zip(system_ids) {
+            let txh = tx_hash_id_mapping
+                .get::<TxHash>(&pc.creation_tx.clone())
+                .ok_or(StorageError::DecodeError("TxHash not found".to_string()))?;
+            let (pt_id, attribute_schema) = protocol_types
+                .get(&pc.protocol_type_name)
+                .ok_or_else(|| {
+                    StorageError::NotFound(
+                        "ProtocolType".to_string(),
+                        pc.protocol_type_name.clone(),
+                    )
+                })?;
+
+            let new_pc = orm::NewProtocolComponent::new(
+                &pc.id,
+                self.get_chain_id(&pc.chain),
+                *pt_id,
+                ps_id,
+                *txh,
+                pc.created_at,
+                &pc.static_attributes,
+                attribute_schema.as_ref(),
+            )?;
+            values.push(new_pc);
+        }
+
+        self.ensure_component_tokens(new, conn)
+            .await?;
+
+        let inserted_protocol_components: Vec<(i64, String, i64, i64)> =
+            diesel::insert_into(protocol_component)
+                .values(&values)
+                .on_conflict((schema::protocol_component::chain_id, external_id))
                 .do_nothing()
                 .returning((
                     schema::protocol_component::id,
@@ -675,6 +1470,59 @@ impl PostgresGateway {
         Ok(())
     }
 
+    /// Registers a protocol type and returns its database id.
+    ///
+    /// Re-registering an already known protocol type (by name) updates it in place, e.g. to
+    /// change its attribute schema, and returns the existing id.
+    #[instrument(skip(self, conn))]
+    pub async fn insert_protocol_type(
+        &self,
+        pt: &models::ProtocolType,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<i64, StorageError> {
+        use super::schema::protocol_type::dsl::*;
+        let value = orm::NewProtocolType {
+            name: pt.name.clone(),
+            financial_type: pt.financial_type.clone().into(),
+            attribute_schema: pt.attribute_schema.clone(),
+            implementation: pt.implementation.clone().into(),
+        };
+        diesel::insert_into(protocol_type)
+            .values(&value)
+            .on_conflict(name)
+            .do_update()
+            .set((
+                financial_type.eq(excluded(financial_type)),
+                attribute_schema.eq(excluded(attribute_schema)),
+                implementation.eq(excluded(implementation)),
+            ))
+            .returning(id)
+            .get_result(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "ProtocolType", &pt.name, None).into())
+    }
+
+    /// Retrieves all stored protocol types ordered by name.
+    ///
+    /// If `validate_schema` is set, each attribute schema is checked to be well-formed and a
+    /// `DecodeError` is returned for the first malformed one.
+    #[instrument(skip(self, conn))]
+    pub async fn get_protocol_types(
+        &self,
+        validate_schema: bool,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<models::ProtocolType>, StorageError> {
+        schema::protocol_type::table
+            .order_by(schema::protocol_type::name)
+            .select(orm::ProtocolType::as_select())
+            .get_results::<orm::ProtocolType>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .map(|pt| pt.into_model(validate_schema))
+            .collect()
+    }
+
     // Gets all protocol states from the db filtered by chain, component ids and/or protocol system.
     #[allow(clippy::too_many_arguments)]
     #[instrument(level = Level::DEBUG, skip(self, ids, conn))]
@@ -750,6 +1598,80 @@ impl PostgresGateway {
         }
     }
 
+    /// Drops state changes from `deltas` that would not result in a new version.
+    ///
+    /// Used to replay blocks that may already be (partially) stored. An attribute update is
+    /// dropped if a version of that attribute modified by the same transaction exists already,
+    /// an attribute deletion if the attribute currently has no version left to close.
+    pub(crate) async fn prune_applied_state_changes(
+        &self,
+        chain: &Chain,
+        deltas: &mut [(TxHash, models::protocol::ProtocolComponentStateDelta)],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        let chain_db_id = self.get_chain_id(chain);
+        let component_ids: HashSet<&str> = deltas
+            .iter()
+            .map(|(_, delta)| delta.component_id.as_str())
+            .collect();
+        if component_ids.is_empty() {
+            return Ok(());
+        }
+        let tx_hashes: HashSet<&TxHash> = deltas
+            .iter()
+            .map(|(tx, _)| tx)
+            .collect();
+
+        let applied: HashSet<(String, String, TxHash)> = schema::protocol_state::table
+            .inner_join(schema::protocol_component::table)
+            .inner_join(schema::transaction::table)
+            .filter(schema::protocol_component::chain_id.eq(chain_db_id))
+            .filter(schema::protocol_component::external_id.eq_any(&component_ids))
+            .filter(schema::transaction::hash.eq_any(&tx_hashes))
+            .select((
+                schema::protocol_component::external_id,
+                schema::protocol_state::attribute_name,
+                schema::transaction::hash,
+            ))
+            .get_results::<(String, String, TxHash)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+        let mut current: HashSet<(String, String)> = schema::protocol_state::table
+            .inner_join(schema::protocol_component::table)
+            .filter(schema::protocol_component::chain_id.eq(chain_db_id))
+            .filter(schema::protocol_component::external_id.eq_any(&component_ids))
+            .filter(schema::protocol_state::valid_to.eq(MAX_TS))
+            .select((
+                schema::protocol_component::external_id,
+                schema::protocol_state::attribute_name,
+            ))
+            .get_results::<(String, String)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+
+        for (tx, delta) in deltas.iter_mut() {
+            let component_id = delta.component_id.clone();
+            delta
+                .updated_attributes
+                .retain(|attr, _| {
+                    let key = (component_id.clone(), attr.clone());
+                    if applied.contains(&(key.0.clone(), key.1.clone(), tx.clone())) {
+                        return false;
+                    }
+                    current.insert(key);
+                    true
+                });
+            delta
+                .deleted_attributes
+                .retain(|attr| current.remove(&(component_id.clone(), attr.clone())));
+        }
+        Ok(())
+    }
+
     pub async fn update_protocol_states(
         &self,
         chain: &Chain,
@@ -757,6 +1679,9 @@ impl PostgresGateway {
         conn: &mut AsyncPgConnection,
     ) -> Result<(), StorageError> {
         let chain_db_id = self.get_chain_id(chain);
+        for (_, delta) in new.iter() {
+            validate_attribute_values(delta)?;
+        }
         let new = new
             .iter()
             .map(|(tx, delta)| WithTxHash { entity: delta, tx: Some(tx.to_owned()) })
@@ -898,6 +1823,45 @@ impl PostgresGateway {
         Ok(())
     }
 
+    /// Reverts the protocol states of a protocol system to the state at block `to`.
+    ///
+    /// State rows created after the target block are deleted and the rows that were valid at the
+    /// target block become valid again. This restores each reverted attribute to the
+    /// `previous_value` of its first reverted change, attributes created after the target block
+    /// disappear and attributes deleted after it reappear. Either all changes are applied or
+    /// none.
+    pub async fn revert_protocol_state(
+        &self,
+        chain: &Chain,
+        protocol_system: &str,
+        to: &BlockIdentifier,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        let block = orm::Block::by_id(to, conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Block", &to.to_string(), None))?;
+        let chain_id = self.get_chain_id(chain);
+        let protocol_system = protocol_system.to_owned();
+        conn.transaction(|conn| {
+            async move {
+                let component_ids = schema::protocol_component::table
+                    .inner_join(schema::protocol_system::table)
+                    .filter(schema::protocol_component::chain_id.eq(chain_id))
+                    .filter(schema::protocol_system::name.eq(&protocol_system))
+                    .select(schema::protocol_component::id)
+                    .get_results::<i64>(conn)
+                    .await?;
+
+                revert_protocol_states(&component_ids, block.ts, conn).await?;
+
+                Ok::<(), PostgresError>(())
+            }
+            .scope_boxed()
+        })
+        .await?;
+        Ok(())
+    }
+
     #[instrument(level = Level::DEBUG, skip(self, addresses, conn))]
     pub async fn get_tokens(
         &self,
@@ -967,27 +1931,130 @@ impl PostgresGateway {
 
         let tokens: Vec<models::token::CurrencyToken> = results
             .into_iter()
-            .map(|(orm_token, address_)| {
-                let gas_usage: Vec<_> = orm_token
-                    .gas
-                    .iter()
-                    .map(|u| u.map(|g| g as u64))
-                    .collect();
-                models::token::CurrencyToken::new(
-                    &address_,
-                    orm_token.symbol.as_str(),
-                    orm_token.decimals as u32,
-                    orm_token.tax as u64,
-                    gas_usage.as_slice(),
-                    chain,
-                    orm_token.quality as u32,
-                )
-            })
+            .map(|(orm_token, address_)| currency_token_from_orm(orm_token, &address_, chain))
             .collect();
 
         Ok(WithTotal { entity: tokens, total: Some(count) })
     }
 
+    /// Exports all tokens of a chain as CSV.
+    ///
+    /// Writes a header followed by one row per token with the columns `address`, `symbol`,
+    /// `decimals`, `tax`, `gas` and `quality`. Gas measurements are JSON encoded and quality is
+    /// rendered as a lowercase label, see [`token_quality_label`]. Tokens are fetched in pages
+    /// of [`EXPORT_TOKENS_PAGE_SIZE`].
+    ///
+    /// # Returns
+    /// The number of exported tokens.
+    pub async fn export_tokens_csv<W: AsyncWrite + Unpin + Send>(
+        &self,
+        chain: Chain,
+        writer: &mut W,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<usize, StorageError> {
+        let write_err = |err: std::io::Error| {
+            StorageError::Unexpected(format!("Failed to write tokens: {err}"))
+        };
+        writer
+            .write_all(b"address,symbol,decimals,tax,gas,quality\n")
+            .await
+            .map_err(write_err)?;
+
+        let mut exported = 0;
+        let mut page = 0;
+        loop {
+            let pagination = PaginationParams { page, page_size: EXPORT_TOKENS_PAGE_SIZE };
+            let tokens = self
+                .get_tokens(chain, None, None, None, Some(&pagination), conn)
+                .await?
+                .entity;
+            for token in tokens.iter() {
+                let gas = serde_json::to_string(&token.gas).map_err(|err| {
+                    StorageError::Unexpected(format!(
+                        "Failed to encode gas of token {}: {err}",
+                        token.address
+                    ))
+                })?;
+                let row = [
+                    token.address.to_string(),
+                    token.symbol.clone(),
+                    token.decimals.to_string(),
+                    token.tax.to_string(),
+                    gas,
+                    token_quality_label(token.quality),
+                ]
+                .iter()
+                .map(|field| csv_field(field))
+                .join(",");
+                writer
+                    .write_all(format!("{row}\n").as_bytes())
+                    .await
+                    .map_err(write_err)?;
+            }
+            exported += tokens.len();
+            if (tokens.len() as i64) < EXPORT_TOKENS_PAGE_SIZE {
+                break;
+            }
+            page += 1;
+        }
+        writer
+            .flush()
+            .await
+            .map_err(write_err)?;
+        Ok(exported)
+    }
+
+    /// Retrieves a single token by its address.
+    ///
+    /// Returns `NotFound` if no token is stored for this address on the given chain.
+    #[instrument(skip(self, conn))]
+    pub async fn get_token(
+        &self,
+        chain: Chain,
+        address: &Address,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<models::token::CurrencyToken, StorageError> {
+        let chain_db_id = self.get_chain_id(&chain);
+        let (orm_token, address) = schema::token::table
+            .inner_join(schema::account::table)
+            .filter(schema::account::chain_id.eq(chain_db_id))
+            .filter(schema::account::address.eq(address))
+            .select((orm::Token::as_select(), schema::account::address))
+            .first::<(orm::Token, Address)>(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Token", &address.to_string(), None))?;
+        Ok(currency_token_from_orm(orm_token, &address, chain))
+    }
+
+    /// Retrieves all tokens of a chain whose quality is one of `qualities`.
+    ///
+    /// Tokens are ordered by symbol.
+    #[instrument(skip(self, conn))]
+    pub async fn get_tokens_by_quality(
+        &self,
+        chain: Chain,
+        qualities: &[u32],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<models::token::CurrencyToken>, StorageError> {
+        let chain_db_id = self.get_chain_id(&chain);
+        let qualities: Vec<i32> = qualities
+            .iter()
+            .map(|q| *q as i32)
+            .collect();
+        Ok(schema::token::table
+            .inner_join(schema::account::table)
+            .filter(schema::account::chain_id.eq(chain_db_id))
+            .filter(schema::token::quality.eq_any(qualities))
+            .order_by((schema::token::symbol.asc(), schema::token::id.asc()))
+            .select((orm::Token::as_select(), schema::account::address))
+            .load::<(orm::Token, Address)>(conn)
+            .await
+            .map_err(|err| storage_error_from_diesel(err, "Token", &chain.to_string(), None))?
+            .into_iter()
+            .map(|(orm_token, address)| currency_token_from_orm(orm_token, &address, chain))
+            .collect())
+    }
+
     pub async fn add_tokens(
         &self,
         tokens: &[models::token::CurrencyToken],
@@ -1120,19 +2187,117 @@ impl PostgresGateway {
         Ok(())
     }
 
-    pub async fn add_component_balances(
+    /// Updates the quality of already stored tokens.
+    ///
+    /// Executes a single update keyed on the token's account address, leaving all other token
+    /// attributes untouched. Addresses without a stored token are ignored.
+    ///
+    /// # Returns
+    /// The number of token rows that were updated.
+    #[instrument(skip_all)]
+    pub async fn update_token_qualities(
         &self,
-        component_balances: &[models::protocol::ComponentBalance],
+        updates: &[(Address, u32)],
         chain: &Chain,
         conn: &mut AsyncPgConnection,
-    ) -> Result<(), StorageError> {
-        use super::schema::{account::dsl::*, token::dsl::*};
-
+    ) -> Result<usize, StorageError> {
+        use diesel::sql_types::{Array, BigInt, Binary, Integer};
+        if updates.is_empty() {
+            return Ok(0);
+        }
         let chain_db_id = self.get_chain_id(chain);
-        let token_addresses: Vec<Address> = component_balances
+        let (addresses, qualities): (Vec<Vec<u8>>, Vec<i32>) = updates
             .iter()
-            .map(|component_balance| component_balance.token.clone())
-            .collect();
+            .map(|(address, quality)| (address.to_vec(), *quality as i32))
+            .unzip();
+        trace!(n = updates.len(), "Updating token qualities");
+        Ok(diesel::sql_query(
+            r#"
+            UPDATE token
+            SET quality = u.quality
+            FROM account, unnest($1, $2) AS u(address, quality)
+            WHERE token.account_id = account.id
+                AND account.address = u.address
+                AND account.chain_id = $3
+            "#,
+        )
+        .bind::<Array<Binary>, _>(addresses)
+        .bind::<Array<Integer>, _>(qualities)
+        .bind::<BigInt, _>(chain_db_id)
+        .execute(conn)
+        .await
+        .map_err(PostgresError::from)?)
+    }
+
+    /// Drops component balances that are stored already.
+    ///
+    /// Used to replay blocks that may already be (partially) stored. A balance is dropped if a
+    /// version of it modified by the same transaction exists already.
+    pub(crate) async fn prune_applied_component_balances(
+        &self,
+        chain: &Chain,
+        balances: &mut Vec<models::protocol::ComponentBalance>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        let chain_db_id = self.get_chain_id(chain);
+        let component_ids: HashSet<&str> = balances
+            .iter()
+            .map(|balance| balance.component_id.as_str())
+            .collect();
+        if component_ids.is_empty() {
+            return Ok(());
+        }
+        let tx_hashes: HashSet<&TxHash> = balances
+            .iter()
+            .map(|balance| &balance.modify_tx)
+            .collect();
+
+        let applied: HashSet<(String, Address, TxHash)> = schema::component_balance::table
+            .inner_join(schema::protocol_component::table)
+            .inner_join(schema::token::table.inner_join(schema::account::table))
+            .inner_join(schema::transaction::table)
+            .filter(schema::protocol_component::chain_id.eq(chain_db_id))
+            .filter(schema::protocol_component::external_id.eq_any(&component_ids))
+            .filter(schema::transaction::hash.eq_any(&tx_hashes))
+            .select((
+                schema::protocol_component::external_id,
+                schema::account::address,
+                schema::transaction::hash,
+            ))
+            .get_results::<(String, Address, TxHash)>(conn)
+            .await
+            .map_err(PostgresError::from)?
+            .into_iter()
+            .collect();
+
+        balances.retain(|balance| {
+            !applied.contains(&(
+                balance.component_id.clone(),
+                balance.token.clone(),
+                balance.modify_tx.clone(),
+            ))
+        });
+        Ok(())
+    }
+
+    /// Stores new component balances.
+    ///
+    /// The `previous_value` of each balance is filled from the currently stored balance of the
+    /// same component and token, or from the preceding balance within the batch. This is what
+    /// allows backward balance deltas, e.g. to revert balances on a reorg.
+    pub async fn add_component_balances(
+        &self,
+        component_balances: &[models::protocol::ComponentBalance],
+        chain: &Chain,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<(), StorageError> {
+        use super::schema::{account::dsl::*, token::dsl::*};
+
+        let chain_db_id = self.get_chain_id(chain);
+        let token_addresses: Vec<Address> = component_balances
+            .iter()
+            .map(|component_balance| component_balance.token.clone())
+            .collect();
         let token_ids: HashMap<Address, i64> = token
             .inner_join(account)
             .select((schema::account::address, schema::token::id))
@@ -1184,6 +2349,11 @@ impl PostgresGateway {
                     StorageError::NotFound("Transaction".to_string(), component_balance.modify_tx.to_string())
                 })?;
 
+            validate_balance_float(
+                component_balance.balance_float,
+                &component_balance.component_id,
+                &component_balance.token,
+            )?;
             let protocol_component_id = protocol_component_ids[&component_balance.component_id];
 
             let new_component_balance = orm::NewComponentBalance::new(
@@ -1255,10 +2425,15 @@ impl PostgresGateway {
         let chain_id = self.get_chain_id(chain);
 
         let start_ts = match start_version {
-            Some(version) => maybe_lookup_block_ts(version, conn).await?,
+            Some(version) => {
+                self.version_to_ts(version, conn)
+                    .await?
+            }
             None => Utc::now().naive_utc(),
         };
-        let target_ts = maybe_lookup_block_ts(target_version, conn).await?;
+        let target_ts = self
+            .version_to_ts(target_version, conn)
+            .await?;
 
         let res = if start_ts <= target_ts {
             // Going forward
@@ -1467,6 +2642,7 @@ impl PostgresGateway {
             for (tid, bals) in balance_map {
                 match tokens.get(&tid) {
                     Some(address) => {
+                        validate_balance_float(bals.1, &component_id, address)?;
                         let balance = models::protocol::ComponentBalance::new(
                             address.clone(),
                             bals.0,
@@ -1488,6 +2664,29 @@ impl PostgresGateway {
         Ok(balances)
     }
 
+    /// Retrieves the latest token balances of the given components as of `version`.
+    ///
+    /// Convenience wrapper around [`Self::get_balances`] for callers that address versions by
+    /// block or timestamp. Without a version the currently valid balances are returned.
+    pub async fn get_component_balances(
+        &self,
+        external_ids: &[String],
+        chain: &Chain,
+        version: Option<&BlockOrTimestamp>,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<
+        HashMap<ComponentId, HashMap<Address, models::protocol::ComponentBalance>>,
+        StorageError,
+    > {
+        let ids = external_ids
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        let version = version.map(|v| Version(v.clone(), VersionKind::Last));
+        self.get_balances(chain, Some(&ids), version.as_ref(), conn)
+            .await
+    }
+
     #[instrument(level = Level::DEBUG, skip(self, conn))]
     pub async fn get_protocol_states_delta(
         &self,
@@ -1497,10 +2696,15 @@ impl PostgresGateway {
         conn: &mut AsyncPgConnection,
     ) -> Result<Vec<models::protocol::ProtocolComponentStateDelta>, StorageError> {
         let start_ts = match start_version {
-            Some(version) => maybe_lookup_block_ts(version, conn).await?,
+            Some(version) => {
+                self.version_to_ts(version, conn)
+                    .await?
+            }
             None => Utc::now().naive_utc(),
         };
-        let end_ts = maybe_lookup_block_ts(end_version, conn).await?;
+        let end_ts = self
+            .version_to_ts(end_version, conn)
+            .await?;
 
         if start_ts <= end_ts {
             // Going forward
@@ -1724,6 +2928,94 @@ impl PostgresGateway {
         Ok(())
     }
 
+    /// Resolves a protocol system name to its database id.
+    ///
+    /// See [`Self::get_protocol_system_ids`] for how ids are cached. Returns `NotFound` if no
+    /// system with this name is registered.
+    pub async fn get_protocol_system_id(
+        &self,
+        name: &str,
+        conn: &mut AsyncPgConnection,
+    ) -> Result<i64, StorageError> {
+        let ids = self
+            .get_protocol_system_ids(&[name], conn)
+            .await?;
+        Ok(ids[0])
+    }
+
+    /// Resolves protocol system names to their database ids, in the order of `names`.
+    ///
+    /// Systems known when the gateway was created are served from the preloaded cache. Systems
+    /// registered later are looked up in a single query and cached for subsequent calls.
+    /// Returns `NotFound` for the first name that is not registered.
+    pub async fn get_protocol_system_ids(
+        &self,
+        names: &[&str],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<i64>, StorageError> {
+        let missing: Vec<&str> = names
+            .iter()
+            .copied()
+            .filter(|name| {
+                self.try_get_protocol_system_id(name)
+                    .is_none()
+            })
+            .unique()
+            .collect();
+        if !missing.is_empty() {
+            let found = schema::protocol_system::table
+                .filter(schema::protocol_system::name.eq_any(&missing))
+                .select((schema::protocol_system::name, schema::protocol_system::id))
+                .get_results::<(String, i64)>(conn)
+                .await
+                .map_err(PostgresError::from)?;
+            self.protocol_system_ids().extend(found);
+        }
+        names
+            .iter()
+            .map(|name| {
+                self.try_get_protocol_system_id(name)
+                    .ok_or_else(|| {
+                        StorageError::NotFound("ProtocolSystem".to_string(), name.to_string())
+                    })
+            })
+            .collect()
+    }
+
+    /// Registers the protocol systems among `names` that are not known yet, then resolves all
+    /// `names` to their database ids.
+    ///
+    /// Concurrent registrations of the same system are resolved by the unique system name, so
+    /// the first insert wins and all callers resolve the same id.
+    async fn register_protocol_systems(
+        &self,
+        names: &[&str],
+        conn: &mut AsyncPgConnection,
+    ) -> Result<Vec<i64>, StorageError> {
+        let missing: Vec<_> = names
+            .iter()
+            .copied()
+            .filter(|name| {
+                self.try_get_protocol_system_id(name)
+                    .is_none()
+            })
+            .unique()
+            .map(|name| schema::protocol_system::name.eq(name))
+            .collect();
+        if !missing.is_empty() {
+            diesel::insert_into(schema::protocol_system::table)
+                .values(&missing)
+                .on_conflict(schema::protocol_system::name)
+                .do_nothing()
+                .execute(conn)
+                .await
+                .map_err(PostgresError::from)?;
+            debug!(n_systems = missing.len(), "Registered new protocol systems");
+        }
+        self.get_protocol_system_ids(names, conn)
+            .await
+    }
+
     pub async fn get_protocol_systems(
         &self,
         chain: &Chain,
@@ -1763,8 +3055,6 @@ mod test {
     use rstest::rstest;
     use serde_json::json;
 
-    use tycho_core::storage::BlockIdentifier;
-
     use crate::postgres::{db_fixtures, db_fixtures::yesterday_half_past_midnight};
 
     use super::*;
@@ -2367,6 +3657,66 @@ mod test {
         assert_eq!(deleted_state.valid_to, older_state.valid_to);
     }
 
+    #[tokio::test]
+    async fn test_revert_protocol_state() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gateway = EVMGateway::from_connection(&mut conn).await;
+        let chain = Chain::Ethereum;
+        let revision = |value: u128| {
+            models::protocol::ProtocolComponentStateDelta::new(
+                "state3",
+                [("reserve1".to_owned(), Bytes::from(value).lpad(32, 0))].into(),
+                HashSet::new(),
+            )
+        };
+        let (first, second) = (revision(700), revision(800));
+        // block 1
+        let tx_1 =
+            Bytes::from("0x794f7df7a3fe973f1583fbb92536f9a8def3a89902439289315326c04068de54");
+        // block 2
+        let tx_2 =
+            Bytes::from("0x50449de1973d86f21bfafa7c72011854a7e33a226709dc3e2e4edcca34188388");
+        gateway
+            .update_protocol_states(&chain, &[(tx_1, &first), (tx_2, &second)], &mut conn)
+            .await
+            .expect("Failed to update protocol states");
+
+        gateway
+            .revert_protocol_state(
+                &chain,
+                "ambient",
+                &BlockIdentifier::Number((chain, 1)),
+                &mut conn,
+            )
+            .await
+            .expect("Failed to revert protocol states");
+
+        let states = gateway
+            .get_protocol_states(&chain, None, None, Some(&["state3"]), false, None, &mut conn)
+            .await
+            .expect("Failed to get protocol states")
+            .entity;
+        assert_eq!(states[0].attributes.get("reserve1"), Some(&Bytes::from(700u128).lpad(32, 0)));
+        // the fixture update of state1 within block 2 is reverted as well
+        let states = gateway
+            .get_protocol_states(&chain, None, None, Some(&["state1"]), false, None, &mut conn)
+            .await
+            .expect("Failed to get protocol states")
+            .entity;
+        assert_eq!(states[0].attributes.get("reserve1"), Some(&Bytes::from(1100u128).lpad(32, 0)));
+        let reverted = schema::protocol_state::table
+            .inner_join(schema::transaction::table)
+            .filter(schema::transaction::hash.eq(Bytes::from(
+                "0x50449de1973d86f21bfafa7c72011854a7e33a226709dc3e2e4edcca34188388",
+            )))
+            .count()
+            .get_result::<i64>(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(reverted, 0);
+    }
+
     #[tokio::test]
     async fn test_get_balance_deltas() {
         let mut conn = setup_db().await;
@@ -2780,6 +4130,120 @@ mod test {
         assert_eq!(inserted_data.implementation, orm::ImplementationType::Custom);
     }
 
+    #[tokio::test]
+    async fn test_insert_protocol_type_updates_in_place() {
+        let mut conn = setup_db().await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let mut protocol_type = models::ProtocolType {
+            name: "Protocol".to_string(),
+            financial_type: FinancialType::Debt,
+            attribute_schema: Some(json!({"required": ["a"]})),
+            implementation: ImplementationType::Custom,
+        };
+        let first_id = gw
+            .insert_protocol_type(&protocol_type, &mut conn)
+            .await
+            .unwrap();
+        protocol_type.financial_type = FinancialType::Swap;
+        protocol_type.attribute_schema = None;
+
+        let second_id = gw
+            .insert_protocol_type(&protocol_type, &mut conn)
+            .await
+            .unwrap();
+
+        let stored = schema::protocol_type::table
+            .filter(schema::protocol_type::name.eq("Protocol"))
+            .select(orm::ProtocolType::as_select())
+            .get_results::<orm::ProtocolType>(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(first_id, second_id);
+        assert_eq!(stored.len(), 1);
+        assert_eq!(stored[0].id, first_id);
+        assert_eq!(stored[0].financial_type, orm::FinancialType::Swap);
+        assert_eq!(stored[0].attribute_schema, None);
+    }
+
+    #[rstest]
+    #[case::checked(true)]
+    #[case::unchecked(false)]
+    #[tokio::test]
+    async fn test_get_protocol_types(#[case] validate_schema: bool) {
+        let mut conn = setup_db().await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let protocol_type = models::ProtocolType {
+            name: "Protocol".to_string(),
+            financial_type: FinancialType::Debt,
+            attribute_schema: Some(
+                json!({"required": ["a"], "properties": {"a": {"type": "string"}}}),
+            ),
+            implementation: ImplementationType::Custom,
+        };
+        gw.add_protocol_types(std::slice::from_ref(&protocol_type), &mut conn)
+            .await
+            .unwrap();
+
+        let res = gw
+            .get_protocol_types(validate_schema, &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(res, vec![protocol_type]);
+    }
+
+    #[rstest]
+    #[case::not_an_object(json!(["a"]))]
+    #[case::required_not_a_list(json!({"required": "a"}))]
+    #[case::property_not_an_object(json!({"properties": {"a": "string"}}))]
+    #[case::property_type_not_a_string(json!({"properties": {"a": {"type": 1}}}))]
+    fn test_protocol_type_into_model_invalid_schema(#[case] attribute_schema: serde_json::Value) {
+        let protocol_type = |schema| orm::ProtocolType {
+            id: 1,
+            name: "Protocol".to_string(),
+            financial_type: orm::FinancialType::Swap,
+            attribute_schema: Some(schema),
+            implementation: orm::ImplementationType::Vm,
+            inserted_ts: NaiveDateTime::default(),
+            modified_ts: NaiveDateTime::default(),
+        };
+
+        let checked = protocol_type(attribute_schema.clone()).into_model(true);
+        let unchecked = protocol_type(attribute_schema).into_model(false);
+
+        assert!(matches!(checked, Err(StorageError::DecodeError(_))));
+        assert!(unchecked.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_export_tokens_csv() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let mut buf = Vec::new();
+
+        let exported = gw
+            .export_tokens_csv(Chain::Ethereum, &mut buf, &mut conn)
+            .await
+            .unwrap();
+
+        let csv = String::from_utf8(buf).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(exported, 4);
+        assert_eq!(lines.next(), Some("address,symbol,decimals,tax,gas,quality"));
+        assert!(lines
+            .any(|line| line ==
+                "0xc02aaa39b223fe8d0a0e5c4f27ead9083c756cc2,WETH,18,10,[10],no_decimals"));
+    }
+
+    #[rstest]
+    #[case::plain("WETH", "WETH")]
+    #[case::comma("A,B", "\"A,B\"")]
+    #[case::quote("A\"B", "\"A\"\"B\"")]
+    fn test_csv_field(#[case] value: &str, #[case] exp: &str) {
+        assert_eq!(csv_field(value), exp);
+    }
+
     #[tokio::test]
     async fn test_get_tokens() {
         let mut conn = setup_db().await;
@@ -2928,6 +4392,64 @@ mod test {
         assert_eq!(tokens[0], expected_token);
     }
 
+    #[tokio::test]
+    async fn test_get_token() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let exp = models::token::CurrencyToken::new(
+            &WETH.parse().unwrap(),
+            "WETH",
+            18,
+            10,
+            &[Some(10)],
+            Chain::Ethereum,
+            0,
+        );
+
+        let token = gw
+            .get_token(Chain::Ethereum, &Bytes::from(WETH), &mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(token, exp);
+    }
+
+    #[tokio::test]
+    async fn test_get_token_not_found() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_token(Chain::Ethereum, &Bytes::from(ZKSYNC_PEPE), &mut conn)
+            .await;
+
+        assert!(matches!(res, Err(StorageError::NotFound(_, _))));
+    }
+
+    #[rstest]
+    #[case::zero_quality(vec![0], vec!["USDC", "WETH"])]
+    #[case::good_quality(vec![70, 100], vec!["DAI", "LUSD"])]
+    #[case::no_match(vec![42], vec![])]
+    #[tokio::test]
+    async fn test_get_tokens_by_quality(#[case] qualities: Vec<u32>, #[case] exp: Vec<&str>) {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let tokens = gw
+            .get_tokens_by_quality(Chain::Ethereum, &qualities, &mut conn)
+            .await
+            .unwrap();
+
+        let symbols = tokens
+            .iter()
+            .map(|t| t.symbol.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(symbols, exp);
+    }
+
     #[tokio::test]
     async fn test_get_tokens_with_30_day_activity() {
         let mut conn = setup_db().await;
@@ -3050,6 +4572,28 @@ mod test {
         assert_eq!(updated, prev);
     }
 
+    #[tokio::test]
+    async fn test_update_token_qualities() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let dai_address = Bytes::from(DAI);
+
+        let updated = gw
+            .update_token_qualities(&[(dai_address.clone(), 5)], &Chain::Ethereum, &mut conn)
+            .await
+            .expect("failed to update token qualities");
+        let token = gw
+            .get_tokens(Chain::Ethereum, Some(&[&dai_address]), None, None, None, &mut conn)
+            .await
+            .expect("failed to get updated token")
+            .entity
+            .remove(0);
+
+        assert_eq!(updated, 1);
+        assert_eq!(token.quality, 5);
+    }
+
     #[tokio::test]
     async fn test_add_component_balances() {
         let mut conn = setup_db().await;
@@ -3139,16 +4683,80 @@ mod test {
     }
 
     #[tokio::test]
-    async fn test_add_protocol_components() {
+    async fn test_add_component_balances_backward_delta() {
         let mut conn = setup_db().await;
         setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
-        let protocol_type_name_1 = String::from("Test_Type_1");
-        let protocol_type_id_1 =
-            db_fixtures::insert_protocol_type(&mut conn, &protocol_type_name_1, None, None, None)
-                .await;
-        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_2", None, None, None).await;
-        let protocol_system = "ambient".to_string();
+        let balance = |value: u128, tx: &str| models::protocol::ComponentBalance {
+            token: Bytes::from(WETH),
+            balance: Balance::from(value).lpad(32, 0),
+            balance_float: value as f64,
+            modify_tx: Bytes::from(tx),
+            component_id: "state2".to_owned(),
+        };
+        // block 1
+        gw.add_component_balances(
+            &[balance(12, "0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945")],
+            &Chain::Starknet,
+            &mut conn,
+        )
+        .await
+        .unwrap();
+        // block 2
+        gw.add_component_balances(
+            &[balance(2000, "0x3108322284d0a89a7accb288d1a94384d499504fe7e04441b0706c7628dee7b7")],
+            &Chain::Starknet,
+            &mut conn,
+        )
+        .await
+        .unwrap();
+
+        let forward = gw
+            .get_balance_deltas(
+                &Chain::Starknet,
+                Some(&BlockOrTimestamp::Timestamp(db_fixtures::yesterday_half_past_midnight())),
+                &BlockOrTimestamp::Block(BlockIdentifier::Number((Chain::Ethereum, 2))),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+        let backward = gw
+            .get_balance_deltas(
+                &Chain::Starknet,
+                Some(&BlockOrTimestamp::Timestamp(Utc::now().naive_utc())),
+                &BlockOrTimestamp::Timestamp(db_fixtures::yesterday_half_past_midnight()),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(
+            forward
+                .iter()
+                .map(|b| b.balance.clone())
+                .collect::<Vec<_>>(),
+            vec![Balance::from(2000u128).lpad(32, 0)]
+        );
+        assert_eq!(
+            backward
+                .iter()
+                .map(|b| b.balance.clone())
+                .collect::<Vec<_>>(),
+            vec![Balance::from(12u128).lpad(32, 0)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_protocol_components() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let protocol_type_name_1 = String::from("Test_Type_1");
+        let protocol_type_id_1 =
+            db_fixtures::insert_protocol_type(&mut conn, &protocol_type_name_1, None, None, None)
+                .await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_2", None, None, None).await;
+        let protocol_system = "ambient".to_string();
         let chain = Chain::Ethereum;
         let original_component = models::protocol::ProtocolComponent::new(
             "test_contract_id",
@@ -3176,7 +4784,7 @@ mod test {
 
         assert_eq!(inserted_data.protocol_type_id, protocol_type_id_1);
         assert_eq!(
-            gw.get_protocol_system_id(
+            gw.get_cached_protocol_system_id(
                 &original_component
                     .protocol_system
                     .to_string()
@@ -3234,228 +4842,1150 @@ mod test {
         assert!(contract.is_ok())
     }
 
-    fn create_test_protocol_component(id: &str) -> models::protocol::ProtocolComponent {
+    fn component_of_system(system: &str) -> models::protocol::ProtocolComponent {
         models::protocol::ProtocolComponent::new(
-            id,
-            "ambient",
-            "type_id_1",
+            "test_contract_id",
+            system,
+            "Test_Type_1",
             Chain::Ethereum,
-            vec![],
-            vec![],
+            vec![Bytes::from(WETH)],
+            vec![Bytes::from(WETH)],
             HashMap::new(),
             ChangeType::Creation,
-            Bytes::from("0x0000000000000000000000000000000000000000000000000000000011121314"),
-            NaiveDateTime::from_timestamp_opt(1000, 0).unwrap(),
+            Bytes::from("0xbb7e16d797a9e2fbc537e30f91ed3d27a254dd9578aa4c3af3e5f0d3e8130945"),
+            Default::default(),
         )
     }
 
     #[tokio::test]
-    async fn test_delete_protocol_components() {
+    async fn test_add_protocol_components_auto_register_system() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn)
+            .await
+            .with_auto_register_systems(true);
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let component = component_of_system("balancer");
+
+        gw.add_protocol_components(std::slice::from_ref(&component), &mut conn)
+            .await
+            .expect("adding components failed");
+
+        let system_id: i64 = schema::protocol_system::table
+            .filter(schema::protocol_system::name.eq("balancer"))
+            .select(schema::protocol_system::id)
+            .first(&mut conn)
+            .await
+            .expect("system was registered");
+        let inserted_system_id: i64 = schema::protocol_component::table
+            .filter(schema::protocol_component::external_id.eq("test_contract_id"))
+            .select(schema::protocol_component::protocol_system_id)
+            .first(&mut conn)
+            .await
+            .expect("component was inserted");
+        assert_eq!(inserted_system_id, system_id);
+        let res = gw
+            .get_protocol_components(
+                &Chain::Ethereum,
+                Some("balancer".to_string()),
+                None,
+                None,
+                None,
+                false,
+                None,
+                &mut conn,
+            )
+            .await
+            .expect("reading components failed");
+        assert_eq!(res.entity.len(), 1);
+        assert_eq!(res.entity[0].id, component.id);
+        assert_eq!(res.entity[0].protocol_system, "balancer");
+    }
+
+    #[tokio::test]
+    async fn test_add_protocol_components_unknown_system() {
         let mut conn = setup_db().await;
         setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
 
-        let test_components = vec![
-            create_test_protocol_component("state1"),
-            create_test_protocol_component("state2"),
-        ];
+        let res = gw
+            .add_protocol_components(&[component_of_system("balancer")], &mut conn)
+            .await;
 
-        gw.delete_protocol_components(&test_components, Utc::now().naive_utc(), &mut conn)
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("ProtocolSystem".to_string(), "balancer".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_protocol_components_resolves_type_names() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let type_id_1 =
+            db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let type_id_2 =
+            db_fixtures::insert_protocol_type(&mut conn, "Test_Type_2", None, None, None).await;
+        let component_1 = component_of_system("ambient");
+        let mut component_2 = component_of_system("ambient");
+        component_2.id = "test_contract_id_2".to_string();
+        component_2.protocol_type_name = "Test_Type_2".to_string();
+
+        gw.add_protocol_components(&[component_1, component_2], &mut conn)
             .await
-            .expect("failed to delete protocol components");
+            .expect("adding components failed");
+
+        let res: Vec<(String, i64)> = schema::protocol_component::table
+            .select((
+                schema::protocol_component::external_id,
+                schema::protocol_component::protocol_type_id,
+            ))
+            .filter(
+                schema::protocol_component::external_id
+                    .eq_any(["test_contract_id", "test_contract_id_2"]),
+            )
+            .order_by(schema::protocol_component::external_id)
+            .get_results(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            res,
+            vec![
+                ("test_contract_id".to_string(), type_id_1),
+                ("test_contract_id_2".to_string(), type_id_2)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_protocol_components_unknown_type() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let mut component = component_of_system("ambient");
+        component.protocol_type_name = "Unknown_Type".to_string();
+
+        let res = gw
+            .add_protocol_components(&[component], &mut conn)
+            .await;
+
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("ProtocolType".to_string(), "Unknown_Type".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_add_protocol_components_missing_tokens() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let mut component = component_of_system("ambient");
+        // PEPE is only stored on zksync
+        component.tokens = vec![Bytes::from(WETH), Bytes::from(USDT), Bytes::from(ZKSYNC_PEPE)];
+
+        let res = gw
+            .add_protocol_components(&[component], &mut conn)
+            .await;
+
+        let exp_missing = [Bytes::from(USDT), Bytes::from(ZKSYNC_PEPE)]
+            .iter()
+            .sorted()
+            .join(", ");
+        assert_eq!(res, Err(StorageError::NotFound("Token".to_string(), exp_missing)));
+        let n_components: i64 = schema::protocol_component::table
+            .filter(schema::protocol_component::external_id.eq("test_contract_id"))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(n_components, 0);
+    }
+
+    #[tokio::test]
+    async fn test_add_protocol_components_auto_create_missing_tokens() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn)
+            .await
+            .with_auto_create_missing_tokens(true);
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let usdt = Bytes::from(USDT);
+        let mut component = component_of_system("ambient");
+        component.tokens = vec![Bytes::from(WETH), usdt.clone()];
+
+        gw.add_protocol_components(std::slice::from_ref(&component), &mut conn)
+            .await
+            .expect("adding components failed");
+
+        let tokens = gw
+            .get_tokens(Chain::Ethereum, Some(&[&usdt]), None, None, None, &mut conn)
+            .await
+            .expect("retrieving tokens failed")
+            .entity;
+        assert_eq!(
+            tokens,
+            vec![models::token::CurrencyToken::new(
+                &usdt,
+                &usdt.to_string(),
+                18,
+                0,
+                &[],
+                Chain::Ethereum,
+                0
+            )]
+        );
+        let res = gw
+            .get_protocol_components(
+                &Chain::Ethereum,
+                None,
+                Some(&["test_contract_id"]),
+                None,
+                None,
+                false,
+                None,
+                &mut conn,
+            )
+            .await
+            .expect("reading components failed");
+        assert_eq!(
+            res.entity[0]
+                .tokens
+                .iter()
+                .sorted()
+                .collect::<Vec<_>>(),
+            component
+                .tokens
+                .iter()
+                .sorted()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_component_tokens() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let mut component = component_of_system("ambient");
+        component.tokens = vec![Bytes::from(WETH), Bytes::from(USDC)];
+        gw.add_protocol_components(&[component], &mut conn)
+            .await
+            .expect("adding components failed");
+        let new_tokens = [Bytes::from(WETH), Bytes::from(DAI), Bytes::from(LUSD)];
+
+        gw.set_component_tokens("test_contract_id", &Chain::Ethereum, &new_tokens, &mut conn)
+            .await
+            .expect("setting tokens failed");
+
+        let held: Vec<Address> = schema::protocol_component_holds_token::table
+            .inner_join(schema::protocol_component::table)
+            .inner_join(schema::token::table.inner_join(schema::account::table))
+            .filter(schema::protocol_component::external_id.eq("test_contract_id"))
+            .select(schema::account::address)
+            .get_results(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(
+            held.into_iter()
+                .sorted()
+                .collect::<Vec<_>>(),
+            new_tokens
+                .into_iter()
+                .sorted()
+                .collect::<Vec<_>>()
+        );
+    }
+
+    #[tokio::test]
+    async fn test_set_component_tokens_unknown_token() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        gw.add_protocol_components(&[component_of_system("ambient")], &mut conn)
+            .await
+            .expect("adding components failed");
+
+        let res = gw
+            .set_component_tokens("test_contract_id", &Chain::Ethereum, &[USDT.into()], &mut conn)
+            .await;
+
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("Token".to_string(), Bytes::from(USDT).to_string()))
+        );
+        // the removal of WETH was rolled back
+        let n_held: i64 = schema::protocol_component_holds_token::table
+            .inner_join(schema::protocol_component::table)
+            .filter(schema::protocol_component::external_id.eq("test_contract_id"))
+            .count()
+            .get_result(&mut conn)
+            .await
+            .unwrap();
+        assert_eq!(n_held, 1);
+    }
+
+    #[tokio::test]
+    async fn test_get_component_contracts() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let usdc_account_id: i64 = schema::account::table
+            .filter(schema::account::address.eq(Bytes::from(USDC)))
+            .select(schema::account::id)
+            .first(&mut conn)
+            .await
+            .unwrap();
+        let tx_id: i64 = schema::transaction::table
+            .select(schema::transaction::id)
+            .first(&mut conn)
+            .await
+            .unwrap();
+        db_fixtures::insert_contract_code(
+            &mut conn,
+            usdc_account_id,
+            tx_id,
+            Bytes::from_str("C1C1C1").unwrap(),
+        )
+        .await;
+        let mut component = component_of_system("ambient");
+        component.contract_addresses = vec![Bytes::from(WETH), Bytes::from(USDC)];
+        gw.add_protocol_components(&[component], &mut conn)
+            .await
+            .expect("adding components failed");
+
+        let res = gw
+            .get_component_contracts("test_contract_id", &Chain::Ethereum, &mut conn)
+            .await
+            .expect("retrieving contracts failed");
+
+        assert_eq!(res, vec![Bytes::from(USDC), Bytes::from(WETH)]);
+    }
+
+    #[tokio::test]
+    async fn test_get_component_contracts_unknown_component() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_component_contracts("unknown", &Chain::Ethereum, &mut conn)
+            .await;
+
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("ProtocolComponent".to_string(), "unknown".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_link_component_across_chains() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        db_fixtures::insert_chain(&mut conn, "arbitrum").await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let mut arbitrum_leg = component_of_system("ambient");
+        arbitrum_leg.id = "arbitrum_leg".to_string();
+        arbitrum_leg.chain = Chain::Arbitrum;
+        arbitrum_leg.tokens = vec![];
+        arbitrum_leg.contract_addresses = vec![];
+        gw.add_protocol_components(&[component_of_system("ambient"), arbitrum_leg], &mut conn)
+            .await
+            .expect("adding components failed");
+
+        gw.link_component(
+            &Chain::Ethereum,
+            "test_contract_id",
+            (&Chain::Arbitrum, "arbitrum_leg"),
+            &mut conn,
+        )
+        .await
+        .expect("linking failed");
+
+        assert_eq!(
+            gw.get_linked_component(&Chain::Ethereum, "test_contract_id", &mut conn)
+                .await,
+            Ok(Some((Chain::Arbitrum, "arbitrum_leg".to_string())))
+        );
+        let component = gw
+            .get_protocol_component(&Chain::Ethereum, "test_contract_id", &mut conn)
+            .await
+            .expect("retrieving component failed");
+        assert_eq!(component.linked_component, Some((Chain::Arbitrum, "arbitrum_leg".to_string())));
+        assert_eq!(
+            gw.get_linked_component(&Chain::Arbitrum, "arbitrum_leg", &mut conn)
+                .await,
+            Ok(None)
+        );
+        assert!(matches!(
+            gw.link_component(
+                &Chain::Ethereum,
+                "test_contract_id",
+                (&Chain::Arbitrum, "unknown"),
+                &mut conn,
+            )
+            .await,
+            Err(StorageError::NotFound(_, _))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_component() {
+        let mut conn = setup_db().await;
+        let tx_hashes = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_protocol_component(&Chain::Starknet, "state2", &mut conn)
+            .await
+            .expect("retrieving component failed");
+
+        assert_eq!(res.id, "state2");
+        assert_eq!(res.protocol_system, "zigzag");
+        assert_eq!(res.creation_tx, Bytes::from_str(&tx_hashes[1]).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_component_not_found() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_protocol_component(&Chain::Ethereum, "state2", &mut conn)
+            .await;
+
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("ProtocolComponent".to_string(), "state2".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_component_deleted() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        gw.delete_protocol_components(
+            &[create_test_protocol_component("state2")],
+            Utc::now().naive_utc(),
+            &mut conn,
+        )
+        .await
+        .expect("failed to delete protocol component");
+
+        let res = gw
+            .get_protocol_component(&Chain::Starknet, "state2", &mut conn)
+            .await;
+
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("ProtocolComponent".to_string(), "state2".to_string()))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_components_created_in_block() {
+        let mut conn = setup_db().await;
+        let tx_hashes = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        db_fixtures::insert_protocol_type(&mut conn, "Test_Type_1", None, None, None).await;
+        let block_1 = BlockIdentifier::Number((Chain::Ethereum, 1));
+        let block_2 = BlockIdentifier::Number((Chain::Ethereum, 2));
+        let before = gw
+            .get_components_created_in_block(&block_2, &mut conn)
+            .await
+            .expect("retrieving components failed");
+        let mut component = component_of_system("ambient");
+        component.creation_tx = Bytes::from_str(&tx_hashes[2]).unwrap();
+        gw.add_protocol_components(std::slice::from_ref(&component), &mut conn)
+            .await
+            .expect("adding components failed");
+
+        let res_1 = gw
+            .get_components_created_in_block(&block_1, &mut conn)
+            .await
+            .expect("retrieving components failed");
+        let res_2 = gw
+            .get_components_created_in_block(&block_2, &mut conn)
+            .await
+            .expect("retrieving components failed");
+
+        assert!(before.is_empty());
+        assert_eq!(
+            res_1
+                .iter()
+                .map(|pc| pc.id.as_str())
+                .collect::<Vec<_>>(),
+            vec!["no_tvl", "state1", "state3"]
+        );
+        assert_eq!(res_2.len(), 1);
+        assert_eq!(res_2[0].id, component.id);
+        assert_eq!(res_2[0].creation_tx, component.creation_tx);
+    }
+
+    fn create_test_protocol_component(id: &str) -> models::protocol::ProtocolComponent {
+        models::protocol::ProtocolComponent::new(
+            id,
+            "ambient",
+            "type_id_1",
+            Chain::Ethereum,
+            vec![],
+            vec![],
+            HashMap::new(),
+            ChangeType::Creation,
+            Bytes::from("0x0000000000000000000000000000000000000000000000000000000011121314"),
+            NaiveDateTime::from_timestamp_opt(1000, 0).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_delete_protocol_components() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let test_components = vec![
+            create_test_protocol_component("state1"),
+            create_test_protocol_component("state2"),
+        ];
+
+        gw.delete_protocol_components(&test_components, Utc::now().naive_utc(), &mut conn)
+            .await
+            .expect("failed to delete protocol components");
+
+        let pc_ids: Vec<String> = test_components
+            .iter()
+            .map(|test_pc| test_pc.id.to_string())
+            .collect();
+
+        let updated_timestamps = schema::protocol_component::table
+            .filter(schema::protocol_component::external_id.eq_any(pc_ids))
+            .select(schema::protocol_component::deleted_at)
+            .load::<Option<NaiveDateTime>>(&mut conn)
+            .await
+            .unwrap();
+
+        assert_eq!(updated_timestamps.len(), 2);
+        updated_timestamps
+            .into_iter()
+            .for_each(|ts| assert!(ts.is_some(), "Found None in updated_ts"));
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_components_with_pagination() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let result = gw
+            .get_protocol_components(
+                &Chain::Ethereum,
+                None,
+                None,
+                None,
+                None,
+                false,
+                // Without pagination should return 3 components
+                Some(&PaginationParams { page: 0, page_size: 2 }),
+                &mut conn,
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(result.entity.len(), 2);
+        assert_eq!(result.total, Some(3));
+    }
+
+    #[rstest]
+    #[case::get_one(Some("zigzag".to_string()))]
+    #[case::get_none(Some("ambient".to_string()))]
+    #[tokio::test]
+    async fn test_get_protocol_components_with_system_only(#[case] system: Option<String>) {
+        let mut conn = setup_db().await;
+        let tx_hashes = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let chain = Chain::Starknet;
+
+        let result = gw
+            .get_protocol_components(
+                &chain,
+                system.clone(),
+                None,
+                None,
+                None,
+                false,
+                None,
+                &mut conn,
+            )
+            .await;
+
+        assert!(result.is_ok());
+
+        match system.unwrap().as_str() {
+            "zigzag" => {
+                let components = result.unwrap().entity;
+                assert_eq!(components.len(), 1);
+
+                let pc = &components[0];
+                assert_eq!(pc.id, "state2".to_string());
+                assert_eq!(pc.protocol_system, "zigzag");
+                assert_eq!(pc.chain, Chain::Starknet);
+                assert_eq!(pc.creation_tx, Bytes::from(tx_hashes.get(1).unwrap().as_str()));
+            }
+            "ambient" => {
+                let components = result.unwrap().entity;
+                assert_eq!(components.len(), 0)
+            }
+            _ => {}
+        }
+    }
+
+    #[rstest]
+    #[case::get_one("state1".to_string())]
+    #[case::get_none("state2".to_string())]
+    #[tokio::test]
+    async fn test_get_protocol_components_with_external_id_only(#[case] external_id: String) {
+        let mut conn = setup_db().await;
+        let tx_hashes = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let temp_ids_array = [external_id.as_str()];
+        let ids = Some(temp_ids_array.as_slice());
+        let chain = Chain::Ethereum;
+
+        let result = gw
+            .get_protocol_components(&chain, None, ids, None, None, false, None, &mut conn)
+            .await
+            .unwrap()
+            .entity;
+
+        match external_id.as_str() {
+            "state1" => {
+                assert_eq!(result.len(), 1);
+
+                let pc = &result[0];
+                assert_eq!(pc.id, external_id.to_string());
+                assert_eq!(pc.protocol_system, "ambient");
+                assert_eq!(pc.protocol_type_name, "Pool");
+                assert_eq!(pc.chain, Chain::Ethereum);
+                assert_eq!(pc.creation_tx, Bytes::from(tx_hashes[0].as_str()));
+            }
+            "state2" => {
+                assert_eq!(result.len(), 0)
+            }
+            _ => {}
+        }
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_components_with_system_and_ids() {
+        let mut conn = setup_db().await;
+        let tx_hashes = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let system = "ambient".to_string();
+        let ids = Some(["state1", "state2"].as_slice());
+        let chain = Chain::Ethereum;
+        let result = gw
+            .get_protocol_components(&chain, Some(system), ids, None, None, false, None, &mut conn)
+            .await;
+
+        let components = result.unwrap().entity;
+        assert_eq!(components.len(), 1);
+
+        let pc = &components[0];
+        assert_eq!(pc.id, "state1".to_string());
+        assert_eq!(pc.protocol_system, "ambient");
+        assert_eq!(pc.chain, Chain::Ethereum);
+        assert_eq!(pc.creation_tx, Bytes::from(tx_hashes[0].as_str()));
+    }
+
+    #[rstest]
+    #[case::ethereum(Chain::Ethereum, & ["state1", "state3", "no_tvl"])]
+    #[case::starknet(Chain::Starknet, & ["state2"])]
+    #[tokio::test]
+    async fn test_get_protocol_components_with_chain_filter(
+        #[case] chain: Chain,
+        #[case] exp_ids: &[&str],
+    ) {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let exp = exp_ids
+            .iter()
+            .map(|&s| s.to_owned())
+            .collect::<HashSet<_>>();
+
+        let components = gw
+            .get_protocol_components(&chain, None, None, None, None, false, None, &mut conn)
+            .await
+            .expect("failed retrieving components")
+            .entity
+            .into_iter()
+            .map(|c| c.id)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(components, exp);
+    }
+
+    #[rstest]
+    #[case::empty(Some(10.0), & [])]
+    #[case::all(None, & ["state1", "state3", "no_tvl"])]
+    #[case::with_tvl(Some(0.0), & ["state1", "state3"])]
+    #[case::with_tvl(Some(1.0), & ["state1"])]
+    #[tokio::test]
+    async fn test_get_protocol_components_with_min_tvl(
+        #[case] min_tvl: Option<f64>,
+        #[case] exp_ids: &[&str],
+    ) {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let exp = exp_ids
+            .iter()
+            .map(|&s| s.to_owned())
+            .collect::<HashSet<_>>();
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_protocol_components(
+                &Chain::Ethereum,
+                None,
+                None,
+                min_tvl,
+                None,
+                false,
+                None,
+                &mut conn,
+            )
+            .await
+            .expect("failed retrieving components")
+            .entity
+            .into_iter()
+            .map(|comp| comp.id)
+            .collect::<HashSet<_>>();
+
+        assert_eq!(res, exp);
+    }
+
+    #[test]
+    fn test_map_addresses_to_protocol_component_dedup() {
+        let weth = Bytes::from(WETH);
+        let usdc = Bytes::from(USDC);
+
+        let res = map_addresses_to_protocol_component(vec![
+            (1, weth.clone()),
+            (2, usdc.clone()),
+            (1, usdc.clone()),
+            (1, weth.clone()),
+        ]);
+
+        assert_eq!(res, HashMap::from([(1, vec![weth, usdc.clone()]), (2, vec![usdc])]));
+    }
+
+    #[test]
+    fn test_group_protocol_states() {
+        let row = |pc_id: i64, name: &str| orm::ProtocolState {
+            protocol_component_id: pc_id,
+            attribute_name: name.to_owned(),
+            attribute_value: Bytes::from(pc_id as u64),
+            previous_value: None,
+            modify_tx: 1,
+            valid_from: NaiveDateTime::default(),
+            valid_to: MAX_TS,
+            inserted_ts: NaiveDateTime::default(),
+            modified_ts: NaiveDateTime::default(),
+        };
+        let rows = vec![row(1, "reserve1"), row(2, "reserve1"), row(1, "reserve2"), row(2, "fee")];
+
+        let res = orm::group_protocol_states(rows)
+            .into_iter()
+            .map(|(pc_id, states)| {
+                (
+                    pc_id,
+                    states
+                        .into_iter()
+                        .map(|s| s.attribute_name)
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .collect::<HashMap<_, _>>();
+
+        assert_eq!(
+            res,
+            HashMap::from([
+                (1, vec!["reserve1".to_owned(), "reserve2".to_owned()]),
+                (2, vec!["reserve1".to_owned(), "fee".to_owned()]),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_validate_attribute_values_oversized() {
+        let delta = models::protocol::ProtocolComponentStateDelta::new(
+            "state1",
+            HashMap::from([
+                ("reserve1".to_owned(), Bytes::zero(32)),
+                ("blob".to_owned(), Bytes::zero(MAX_ATTR_BYTES + 1)),
+            ]),
+            HashSet::new(),
+        );
+
+        let res = validate_attribute_values(&delta);
+
+        assert_eq!(
+            res,
+            Err(StorageError::DecodeError(format!(
+                "Attribute blob of component state1 exceeds the size limit: {} > {} bytes",
+                MAX_ATTR_BYTES + 1,
+                MAX_ATTR_BYTES
+            )))
+        );
+    }
+
+    #[rstest]
+    #[case::nan(f64::NAN)]
+    #[case::inf(f64::INFINITY)]
+    #[case::neg_inf(f64::NEG_INFINITY)]
+    fn test_validate_balance_float_non_finite(#[case] value: f64) {
+        let token = Bytes::from(WETH);
+
+        let res = validate_balance_float(value, "state1", &token);
+
+        assert_eq!(
+            res,
+            Err(StorageError::DecodeError(format!(
+                "Balance of token {} in component state1 is not finite: {}",
+                token, value
+            )))
+        );
+        assert_eq!(validate_balance_float(1e18, "state1", &token), Ok(()));
+    }
+
+    #[rstest]
+    #[case::no_schema(None, Ok(()))]
+    #[case::satisfied(Some(json!({"required": ["fee"], "properties": {"fee": {"type": "string"}}})), Ok(()))]
+    #[case::missing(
+        Some(json!({"required": ["fee", "tick_spacing", "pool_id"]})),
+        Err(StorageError::DecodeError(
+            "ProtocolComponent state1 is missing required static attributes: tick_spacing, pool_id"
+                .to_owned()
+        ))
+    )]
+    #[case::wrong_type(
+        Some(json!({"properties": {"fee": {"type": "integer"}}})),
+        Err(StorageError::DecodeError(
+            "ProtocolComponent state1 static attribute fee has type string, expected integer"
+                .to_owned()
+        ))
+    )]
+    fn test_new_protocol_component_schema_validation(
+        #[case] schema: Option<serde_json::Value>,
+        #[case] exp: Result<(), StorageError>,
+    ) {
+        let attributes = HashMap::from([("fee".to_owned(), Bytes::from(3000u64))]);
+
+        let res = orm::NewProtocolComponent::new(
+            "state1",
+            1,
+            1,
+            1,
+            1,
+            NaiveDateTime::default(),
+            &attributes,
+            schema.as_ref(),
+        )
+        .map(|_| ());
+
+        assert_eq!(res, exp);
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_components_by_ids() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let ids = ["state3", "missing", "state1"].map(String::from);
+
+        let res = gw
+            .get_protocol_components_by_ids(&Chain::Ethereum, &ids, &mut conn)
+            .await
+            .expect("failed retrieving components")
+            .into_iter()
+            .map(|comp| comp.id)
+            .collect::<Vec<_>>();
 
-        let pc_ids: Vec<String> = test_components
-            .iter()
-            .map(|test_pc| test_pc.id.to_string())
-            .collect();
+        assert_eq!(res, vec!["state3".to_string(), "state1".to_string()]);
+    }
 
-        let updated_timestamps = schema::protocol_component::table
-            .filter(schema::protocol_component::external_id.eq_any(pc_ids))
-            .select(schema::protocol_component::deleted_at)
-            .load::<Option<NaiveDateTime>>(&mut conn)
-            .await
-            .unwrap();
+    #[tokio::test]
+    async fn test_get_protocol_components_resolves_type_names() {
+        let mut conn = setup_db().await;
+        setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let lending_type_id =
+            db_fixtures::insert_protocol_type(&mut conn, "Lending", None, None, None).await;
+        diesel::update(
+            schema::protocol_component::table
+                .filter(schema::protocol_component::external_id.eq("state3")),
+        )
+        .set(schema::protocol_component::protocol_type_id.eq(lending_type_id))
+        .execute(&mut conn)
+        .await
+        .unwrap();
+        let ids = ["state1", "state3"].map(String::from);
 
-        assert_eq!(updated_timestamps.len(), 2);
-        updated_timestamps
+        let res = gw
+            .get_protocol_components_by_ids(&Chain::Ethereum, &ids, &mut conn)
+            .await
+            .expect("failed retrieving components")
             .into_iter()
-            .for_each(|ts| assert!(ts.is_some(), "Found None in updated_ts"));
+            .map(|comp| (comp.id, comp.protocol_type_name))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            res,
+            vec![
+                ("state1".to_string(), "Pool".to_string()),
+                ("state3".to_string(), "Lending".to_string())
+            ]
+        );
     }
 
     #[tokio::test]
-    async fn test_get_protocol_components_with_pagination() {
+    async fn test_get_protocol_components_created_after() {
         let mut conn = setup_db().await;
         setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
+        let t0 = db_fixtures::yesterday_midnight();
+        let t1 = db_fixtures::yesterday_one_am();
+        let t2 = t1 + chrono::Duration::hours(1);
+        for (ext_id, ts) in [("state1", t0), ("no_tvl", t1), ("state3", t2)] {
+            diesel::update(
+                schema::protocol_component::table
+                    .filter(schema::protocol_component::external_id.eq(ext_id)),
+            )
+            .set(schema::protocol_component::created_at.eq(ts))
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        }
 
-        let result = gw
+        let res = gw
             .get_protocol_components(
                 &Chain::Ethereum,
                 None,
                 None,
                 None,
-                // Without pagination should return 3 components
-                Some(&PaginationParams { page: 0, page_size: 2 }),
+                Some(t0),
+                false,
+                None,
                 &mut conn,
             )
             .await
-            .unwrap();
+            .unwrap()
+            .entity
+            .into_iter()
+            .map(|comp| comp.id)
+            .collect::<Vec<_>>();
 
-        assert_eq!(result.entity.len(), 2);
-        assert_eq!(result.total, Some(3));
+        assert_eq!(res, vec!["no_tvl".to_string(), "state3".to_string()]);
     }
 
-    #[rstest]
-    #[case::get_one(Some("zigzag".to_string()))]
-    #[case::get_none(Some("ambient".to_string()))]
     #[tokio::test]
-    async fn test_get_protocol_components_with_system_only(#[case] system: Option<String>) {
+    async fn test_find_components_by_attribute() {
         let mut conn = setup_db().await;
-        let tx_hashes = setup_data(&mut conn).await;
+        setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
+        for (ext_id, fee) in [("state1", "0x1e"), ("state3", "0x0bb8")] {
+            let attributes = HashMap::from([
+                ("fee".to_string(), Bytes::from(fee)),
+                ("tick_spacing".to_string(), Bytes::from("0x01")),
+            ]);
+            diesel::update(
+                schema::protocol_component::table
+                    .filter(schema::protocol_component::external_id.eq(ext_id)),
+            )
+            .set(
+                schema::protocol_component::attributes
+                    .eq(serde_json::to_value(attributes).unwrap()),
+            )
+            .execute(&mut conn)
+            .await
+            .unwrap();
+        }
 
-        let chain = Chain::Starknet;
+        let res = gw
+            .find_components_by_attribute(&Chain::Ethereum, "fee", &Bytes::from("0x1e"), &mut conn)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|comp| comp.id)
+            .collect::<Vec<_>>();
 
-        let result = gw
-            .get_protocol_components(&chain, system.clone(), None, None, None, &mut conn)
-            .await;
+        assert_eq!(res, vec!["state1".to_string()]);
+    }
 
-        assert!(result.is_ok());
+    #[tokio::test]
+    async fn test_delete_and_restore_protocol_component() {
+        let mut conn = setup_db().await;
+        let tx_hashes = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let deletion_tx = orm::Transaction::by_hash(&Bytes::from(tx_hashes[1].as_str()), &mut conn)
+            .await
+            .unwrap()
+            .id;
+        let ids = ["state1"];
+        async fn visible(
+            gw: &EVMGateway,
+            ids: &[&str],
+            include_deleted: bool,
+            conn: &mut AsyncPgConnection,
+        ) -> usize {
+            gw.get_protocol_components(
+                &Chain::Ethereum,
+                None,
+                Some(ids),
+                None,
+                None,
+                include_deleted,
+                None,
+                conn,
+            )
+            .await
+            .unwrap()
+            .entity
+            .len()
+        }
 
-        match system.unwrap().as_str() {
-            "zigzag" => {
-                let components = result.unwrap().entity;
-                assert_eq!(components.len(), 1);
+        gw.delete_protocol_component(
+            "state1",
+            &Chain::Ethereum,
+            deletion_tx,
+            db_fixtures::yesterday_one_am(),
+            &mut conn,
+        )
+        .await
+        .unwrap();
+        let visible_deleted = visible(&gw, &ids, false, &mut conn).await;
+        let included_deleted = visible(&gw, &ids, true, &mut conn).await;
+        gw.restore_protocol_component("state1", &Chain::Ethereum, &mut conn)
+            .await
+            .unwrap();
+        let visible_restored = visible(&gw, &ids, false, &mut conn).await;
 
-                let pc = &components[0];
-                assert_eq!(pc.id, "state2".to_string());
-                assert_eq!(pc.protocol_system, "zigzag");
-                assert_eq!(pc.chain, Chain::Starknet);
-                assert_eq!(pc.creation_tx, Bytes::from(tx_hashes.get(1).unwrap().as_str()));
-            }
-            "ambient" => {
-                let components = result.unwrap().entity;
-                assert_eq!(components.len(), 0)
-            }
-            _ => {}
-        }
+        assert_eq!(visible_deleted, 0);
+        assert_eq!(included_deleted, 1);
+        assert_eq!(visible_restored, 1);
     }
 
-    #[rstest]
-    #[case::get_one("state1".to_string())]
-    #[case::get_none("state2".to_string())]
     #[tokio::test]
-    async fn test_get_protocol_components_with_external_id_only(#[case] external_id: String) {
+    async fn test_update_component_attributes() {
         let mut conn = setup_db().await;
-        let tx_hashes = setup_data(&mut conn).await;
+        setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
+        let initial = HashMap::from([
+            ("key1".to_string(), Bytes::from("0x01")),
+            ("key2".to_string(), Bytes::from("0x02")),
+        ]);
+        gw.update_component_attributes("state1", &Chain::Ethereum, &initial, &mut conn)
+            .await
+            .unwrap();
+        let patch = HashMap::from([("key1".to_string(), Bytes::from("0x03"))]);
 
-        let temp_ids_array = [external_id.as_str()];
-        let ids = Some(temp_ids_array.as_slice());
-        let chain = Chain::Ethereum;
+        gw.update_component_attributes("state1", &Chain::Ethereum, &patch, &mut conn)
+            .await
+            .unwrap();
 
-        let result = gw
-            .get_protocol_components(&chain, None, ids, None, None, &mut conn)
+        let component = gw
+            .get_protocol_components_by_ids(&Chain::Ethereum, &["state1".to_string()], &mut conn)
             .await
             .unwrap()
-            .entity;
-
-        match external_id.as_str() {
-            "state1" => {
-                assert_eq!(result.len(), 1);
-
-                let pc = &result[0];
-                assert_eq!(pc.id, external_id.to_string());
-                assert_eq!(pc.protocol_system, "ambient");
-                assert_eq!(pc.chain, Chain::Ethereum);
-                assert_eq!(pc.creation_tx, Bytes::from(tx_hashes[0].as_str()));
-            }
-            "state2" => {
-                assert_eq!(result.len(), 0)
-            }
-            _ => {}
-        }
+            .remove(0);
+        assert_eq!(
+            component.static_attributes,
+            HashMap::from([
+                ("key1".to_string(), Bytes::from("0x03")),
+                ("key2".to_string(), Bytes::from("0x02")),
+            ])
+        );
     }
 
     #[tokio::test]
-    async fn test_get_protocol_components_with_system_and_ids() {
+    async fn test_update_component_attributes_missing_component() {
         let mut conn = setup_db().await;
-        let tx_hashes = setup_data(&mut conn).await;
+        setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
+        let patch = HashMap::from([("key1".to_string(), Bytes::from("0x03"))]);
 
-        let system = "ambient".to_string();
-        let ids = Some(["state1", "state2"].as_slice());
-        let chain = Chain::Ethereum;
-        let result = gw
-            .get_protocol_components(&chain, Some(system), ids, None, None, &mut conn)
+        let res = gw
+            .update_component_attributes("missing", &Chain::Ethereum, &patch, &mut conn)
             .await;
 
-        let components = result.unwrap().entity;
-        assert_eq!(components.len(), 1);
-
-        let pc = &components[0];
-        assert_eq!(pc.id, "state1".to_string());
-        assert_eq!(pc.protocol_system, "ambient");
-        assert_eq!(pc.chain, Chain::Ethereum);
-        assert_eq!(pc.creation_tx, Bytes::from(tx_hashes[0].as_str()));
+        assert!(matches!(res, Err(StorageError::NotFound(_, _))));
     }
 
     #[rstest]
-    #[case::ethereum(Chain::Ethereum, & ["state1", "state3", "no_tvl"])]
-    #[case::starknet(Chain::Starknet, & ["state2"])]
+    #[case::weth_usdc(Some("ambient"), &[WETH, USDC], &["state1"])]
+    #[case::weth(Some("ambient"), &[WETH], &["state1", "state3", "no_tvl"])]
+    #[case::no_tokens(None, &[], &["state1", "state3", "no_tvl"])]
+    #[case::wrong_system(Some("zigzag"), &[WETH, USDC], &[])]
     #[tokio::test]
-    async fn test_get_protocol_components_with_chain_filter(
-        #[case] chain: Chain,
+    async fn test_get_protocol_components_by_tokens(
+        #[case] system: Option<&str>,
+        #[case] tokens: &[&str],
         #[case] exp_ids: &[&str],
     ) {
         let mut conn = setup_db().await;
         setup_data(&mut conn).await;
         let gw = EVMGateway::from_connection(&mut conn).await;
+        let tokens = tokens
+            .iter()
+            .map(|&t| Bytes::from(t))
+            .collect::<Vec<_>>();
         let exp = exp_ids
             .iter()
             .map(|&s| s.to_owned())
             .collect::<HashSet<_>>();
 
-        let components = gw
-            .get_protocol_components(&chain, None, None, None, None, &mut conn)
+        let res = gw
+            .get_protocol_components_by_tokens(&Chain::Ethereum, system, &tokens, &mut conn)
             .await
             .expect("failed retrieving components")
-            .entity
             .into_iter()
-            .map(|c| c.id)
+            .map(|comp| comp.id)
             .collect::<HashSet<_>>();
 
-        assert_eq!(components, exp);
+        assert_eq!(res, exp);
     }
 
-    #[rstest]
-    #[case::empty(Some(10.0), & [])]
-    #[case::all(None, & ["state1", "state3", "no_tvl"])]
-    #[case::with_tvl(Some(0.0), & ["state1", "state3"])]
-    #[case::with_tvl(Some(1.0), & ["state1"])]
     #[tokio::test]
-    async fn test_get_protocol_components_with_min_tvl(
-        #[case] min_tvl: Option<f64>,
-        #[case] exp_ids: &[&str],
-    ) {
+    async fn test_get_protocol_components_by_tokens_unknown_system() {
         let mut conn = setup_db().await;
         setup_data(&mut conn).await;
-        let exp = exp_ids
-            .iter()
-            .map(|&s| s.to_owned())
-            .collect::<HashSet<_>>();
         let gw = EVMGateway::from_connection(&mut conn).await;
 
         let res = gw
-            .get_protocol_components(&Chain::Ethereum, None, None, min_tvl, None, &mut conn)
-            .await
-            .expect("failed retrieving components")
-            .entity
-            .into_iter()
-            .map(|comp| comp.id)
-            .collect::<HashSet<_>>();
+            .get_protocol_components_by_tokens(
+                &Chain::Ethereum,
+                Some("unknown_system"),
+                &[Bytes::from(WETH)],
+                &mut conn,
+            )
+            .await;
 
-        assert_eq!(res, exp);
+        assert!(
+            matches!(res, Err(StorageError::NotFound(entity, name)) if entity == "ProtocolSystem" && name == "unknown_system")
+        );
     }
 
     #[rstest]
@@ -3557,6 +6087,54 @@ mod test {
         assert_eq!(res, exp);
     }
 
+    #[tokio::test]
+    async fn test_get_component_balances() {
+        let mut conn = setup_db().await;
+        let _ = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let exp: HashMap<_, _> = [(
+            "state1".to_string(),
+            [
+                (
+                    Bytes::from(WETH),
+                    models::protocol::ComponentBalance::new(
+                        Bytes::from(WETH),
+                        Balance::from(10u128.pow(18)).lpad(32, 0),
+                        1e18,
+                        Bytes::zero(32),
+                        "state1",
+                    ),
+                ),
+                (
+                    Bytes::from(USDC),
+                    models::protocol::ComponentBalance::new(
+                        Bytes::from(USDC),
+                        Balance::from(2000 * 10u128.pow(6)).lpad(32, 0),
+                        2000000000.0,
+                        Bytes::zero(32),
+                        "state1",
+                    ),
+                ),
+            ]
+            .into_iter()
+            .collect::<HashMap<_, _>>(),
+        )]
+        .into_iter()
+        .collect();
+
+        let res = gw
+            .get_component_balances(
+                &["state1".to_string()],
+                &Chain::Ethereum,
+                Some(&BlockOrTimestamp::Block(BlockIdentifier::Latest(Chain::Ethereum))),
+                &mut conn,
+            )
+            .await
+            .expect("retrieving balances failed!");
+
+        assert_eq!(res, exp);
+    }
+
     #[tokio::test]
     async fn test_get_balances_at() {
         let mut conn = setup_db().await;
@@ -3771,6 +6349,47 @@ mod test {
         }
     }
 
+    #[tokio::test]
+    async fn test_get_protocol_system_ids() {
+        let mut conn = setup_db().await;
+        let _ = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+        let ambient_id = gw
+            .get_protocol_system_id("ambient", &mut conn)
+            .await
+            .expect("ambient is preloaded");
+        // registered after the gateway was created, so it has to be looked up
+        let curve_id = db_fixtures::insert_protocol_system(&mut conn, "curve".to_owned()).await;
+
+        let res = gw
+            .get_protocol_system_ids(&["curve", "ambient", "curve"], &mut conn)
+            .await
+            .expect("resolving ids failed");
+
+        assert_eq!(res, vec![curve_id, ambient_id, curve_id]);
+        assert_eq!(
+            gw.get_protocol_system_id("curve", &mut conn)
+                .await,
+            Ok(curve_id)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_get_protocol_system_ids_not_found() {
+        let mut conn = setup_db().await;
+        let _ = setup_data(&mut conn).await;
+        let gw = EVMGateway::from_connection(&mut conn).await;
+
+        let res = gw
+            .get_protocol_system_ids(&["ambient", "unknown"], &mut conn)
+            .await;
+
+        assert_eq!(
+            res,
+            Err(StorageError::NotFound("ProtocolSystem".to_string(), "unknown".to_string()))
+        );
+    }
+
     #[tokio::test]
     async fn test_truncate_token_title() {
         let mut conn = setup_db().await;
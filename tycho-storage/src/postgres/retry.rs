@@ -0,0 +1,148 @@
+//! # Retrying of read queries
+//!
+//! Connection hiccups (a dropped connection, a pool checkout timing out) should not fail a read
+//! that would succeed moments later. Such failures surface as [`StorageError::Transient`] and are
+//! retried with exponential backoff, any other error is returned immediately.
+use std::{future::Future, time::Duration};
+
+use tracing::warn;
+use tycho_core::storage::StorageError;
+
+/// Controls how often and how fast failed read queries are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total number of attempts, including the first one.
+    pub max_attempts: u32,
+    /// Delay before the first retry, doubled for each further retry.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between two attempts.
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(50),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that attempts each query exactly once.
+    pub fn no_retry() -> Self {
+        Self { max_attempts: 1, ..Default::default() }
+    }
+
+    fn backoff(&self, retry: u32) -> Duration {
+        self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(retry))
+            .min(self.max_backoff)
+    }
+}
+
+/// Runs `f` until it succeeds, fails with a non transient error or `policy.max_attempts` is
+/// reached.
+pub(crate) async fn with_retry<T, F, Fut>(policy: &RetryPolicy, mut f: F) -> Result<T, StorageError>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, StorageError>>,
+{
+    let mut attempt = 1;
+    loop {
+        match f().await {
+            Err(StorageError::Transient(msg)) if attempt < policy.max_attempts => {
+                let backoff = policy.backoff(attempt - 1);
+                warn!(attempt, ?backoff, "Transient storage error, retrying: {msg}");
+                tokio::time::sleep(backoff).await;
+                attempt += 1;
+            }
+            res => return res,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    use rstest::rstest;
+
+    use super::*;
+
+    fn policy(max_attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            max_attempts,
+            initial_backoff: Duration::from_millis(1),
+            max_backoff: Duration::from_millis(2),
+        }
+    }
+
+    async fn failing_query(
+        calls: &AtomicU32,
+        failures: u32,
+        err: StorageError,
+    ) -> Result<u32, StorageError> {
+        let call = calls.fetch_add(1, Ordering::SeqCst);
+        if call < failures {
+            Err(err)
+        } else {
+            Ok(call)
+        }
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_recovers_from_transient_errors() {
+        let calls = AtomicU32::new(0);
+
+        let res = with_retry(&policy(5), || {
+            failing_query(&calls, 3, StorageError::Transient("closed".to_string()))
+        })
+        .await;
+
+        assert_eq!(res, Ok(3));
+        assert_eq!(calls.load(Ordering::SeqCst), 4);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_gives_up_after_max_attempts() {
+        let calls = AtomicU32::new(0);
+
+        let res = with_retry(&policy(3), || {
+            failing_query(&calls, 10, StorageError::Transient("closed".to_string()))
+        })
+        .await;
+
+        assert_eq!(res, Err(StorageError::Transient("closed".to_string())));
+        assert_eq!(calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_with_retry_skips_non_transient_errors() {
+        let calls = AtomicU32::new(0);
+
+        let res = with_retry(&policy(3), || {
+            failing_query(&calls, 1, StorageError::DecodeError("bad".to_string()))
+        })
+        .await;
+
+        assert_eq!(res, Err(StorageError::DecodeError("bad".to_string())));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+
+    #[rstest]
+    #[case(0, 10)]
+    #[case(1, 20)]
+    #[case(2, 40)]
+    #[case(10, 100)]
+    fn test_backoff(#[case] retry: u32, #[case] exp_ms: u64) {
+        let policy = RetryPolicy {
+            max_attempts: 3,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_millis(100),
+        };
+
+        assert_eq!(policy.backoff(retry), Duration::from_millis(exp_ms));
+    }
+}
@@ -63,6 +63,14 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    code (hash) {
+        hash -> Bytea,
+        bytecode -> Bytea,
+        inserted_ts -> Timestamptz,
+    }
+}
+
 diesel::table! {
     component_balance (token_id, protocol_component_id, valid_to) {
         token_id -> Int8,
@@ -104,7 +112,6 @@ diesel::table! {
 diesel::table! {
     contract_code (id) {
         id -> Int8,
-        code -> Bytea,
         hash -> Bytea,
         account_id -> Int8,
         modify_tx -> Int8,
@@ -121,6 +128,7 @@ diesel::table! {
         value -> Nullable<Bytea>,
         previous_value -> Nullable<Bytea>,
         account_id -> Int8,
+        chain_id -> Int8,
         modify_tx -> Int8,
         ordinal -> Int8,
         valid_from -> Timestamptz,
@@ -131,15 +139,25 @@ diesel::table! {
 }
 
 diesel::table! {
-    contract_storage_default (account_id, slot, valid_to) {
-        slot -> Bytea,
-        value -> Nullable<Bytea>,
-        previous_value -> Nullable<Bytea>,
+    contract_storage_current (account_id, slot) {
         account_id -> Int8,
+        chain_id -> Int8,
+        slot -> Bytea,
+        value -> Bytea,
         modify_tx -> Int8,
         ordinal -> Int8,
         valid_from -> Timestamptz,
-        valid_to -> Timestamptz,
+        inserted_ts -> Timestamptz,
+        modified_ts -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    cursor (id) {
+        id -> Int8,
+        chain_id -> Int8,
+        protocol_system_id -> Int8,
+        block_id -> Int8,
         inserted_ts -> Timestamptz,
         modified_ts -> Timestamptz,
     }
@@ -197,6 +215,15 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    protocol_component_link (protocol_component_id) {
+        protocol_component_id -> Int8,
+        linked_component_id -> Int8,
+        inserted_ts -> Timestamptz,
+        modified_ts -> Timestamptz,
+    }
+}
+
 diesel::table! {
     protocol_state (protocol_component_id, attribute_name, valid_to) {
         attribute_name -> Varchar,
@@ -302,11 +329,17 @@ diesel::joinable!(component_balance_default -> token (token_id));
 diesel::joinable!(component_balance_default -> transaction (modify_tx));
 diesel::joinable!(component_tvl -> protocol_component (protocol_component_id));
 diesel::joinable!(contract_code -> account (account_id));
+diesel::joinable!(contract_code -> code (hash));
 diesel::joinable!(contract_code -> transaction (modify_tx));
 diesel::joinable!(contract_storage -> account (account_id));
+diesel::joinable!(contract_storage -> chain (chain_id));
 diesel::joinable!(contract_storage -> transaction (modify_tx));
-diesel::joinable!(contract_storage_default -> account (account_id));
-diesel::joinable!(contract_storage_default -> transaction (modify_tx));
+diesel::joinable!(contract_storage_current -> account (account_id));
+diesel::joinable!(contract_storage_current -> chain (chain_id));
+diesel::joinable!(contract_storage_current -> transaction (modify_tx));
+diesel::joinable!(cursor -> block (block_id));
+diesel::joinable!(cursor -> chain (chain_id));
+diesel::joinable!(cursor -> protocol_system (protocol_system_id));
 diesel::joinable!(extraction_state -> block (block_id));
 diesel::joinable!(extraction_state -> chain (chain_id));
 diesel::joinable!(protocol_component -> chain (chain_id));
@@ -329,16 +362,19 @@ diesel::allow_tables_to_appear_in_same_query!(
     account_balance,
     block,
     chain,
+    code,
     component_balance,
     component_balance_default,
     component_tvl,
     contract_code,
     contract_storage,
-    contract_storage_default,
+    contract_storage_current,
+    cursor,
     extraction_state,
     protocol_component,
     protocol_component_holds_contract,
     protocol_component_holds_token,
+    protocol_component_link,
     protocol_state,
     protocol_state_default,
     protocol_system,